@@ -0,0 +1,266 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::color_format::*;
+use crate::gif_extentions::{load_gif_animation, save_gif_animation};
+use crate::image::{checked_image_size, format_from_magic, Image, ImageDesc, Layout};
+use crate::png_extentions::{load_apng, save_apng};
+use crate::save_options::SaveOptions;
+use crate::webp_extentions::load_webp_animation;
+
+/// What happens to the area of a frame once it has been displayed.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Dispose {
+    /// The frame stays, the next one is drawn over it.
+    #[default]
+    None,
+    /// The area is cleared to transparent black.
+    Background,
+    /// The area is restored to what it was before the frame.
+    Previous,
+}
+
+/// How a frame is drawn over the canvas.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Blend {
+    /// The frame replaces the pixels of its area, transparent ones included.
+    #[default]
+    Source,
+    /// The frame is alpha composited over the canvas.
+    Over,
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    pub image: Image,
+    /// Time the frame is displayed for.
+    pub delay: Duration,
+    pub dispose: Dispose,
+    pub blend: Blend,
+}
+
+impl Frame {
+    pub fn new(image: Image, delay: Duration) -> Self {
+        Self {
+            image,
+            delay,
+            dispose: Dispose::default(),
+            blend: Blend::default(),
+        }
+    }
+    pub fn with_dispose(mut self, dispose: Dispose) -> Self {
+        self.dispose = dispose;
+
+        self
+    }
+    pub fn with_blend(mut self, blend: Blend) -> Self {
+        self.blend = blend;
+
+        self
+    }
+}
+
+/// Frames of an animated image, all the size of the canvas.
+#[derive(Clone, Default)]
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    /// Number of times the animation plays, 0 for forever.
+    pub loop_count: u32,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<Frame>, loop_count: u32) -> Self {
+        Self { frames, loop_count }
+    }
+
+    /// Reads an APNG, animated GIF or animated WebP, a still image being a single frame.
+    ///
+    /// Frames are composited on the canvas as they would be displayed, so every
+    /// frame is a full 8 bit RGBA image replacing the previous one.
+    pub fn read_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Animation> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str());
+        let format = match extension {
+            Some(extension @ ("png" | "apng" | "gif" | "webp")) => extension,
+            _ => format_from_magic(&filename)?,
+        };
+
+        match format {
+            "png" | "apng" => load_apng(filename),
+            "gif" => load_gif_animation(filename),
+            "webp" => load_webp_animation(filename),
+
+            _ => Err(anyhow::anyhow!("Unsupported animation format: {}", format)),
+        }
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
+        self.save_with_options(filename, &SaveOptions::default())
+    }
+    /// Saves the frames as an APNG or an animated GIF, as the extension says. Frames
+    /// must share their size and have 8 bit channels.
+    pub fn save_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &SaveOptions,
+    ) -> anyhow::Result<()> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            .ok_or(anyhow::anyhow!("Failed to get file extension"))?;
+
+        match extension {
+            "png" | "apng" => save_apng(self, filename, &options.png),
+            "gif" => save_gif_animation(self, filename, &options.gif),
+
+            _ => Err(anyhow::anyhow!(
+                "Unsupported animation extension: {}",
+                extension
+            )),
+        }
+    }
+
+    /// Returns the size of the frames and their pixels as interleaved 8 bit RGBA.
+    pub(crate) fn rgba_frames(&self) -> anyhow::Result<((u32, u32), Vec<Image>)> {
+        let first = self
+            .frames
+            .first()
+            .ok_or(anyhow::anyhow!("No frame to save"))?;
+        let size = (first.image.desc.width(), first.image.desc.height());
+
+        let mut images = Vec::with_capacity(self.frames.len());
+        for frame in &self.frames {
+            let color_format = frame.image.desc.color_format();
+            if color_format.channel_size != ChannelSize::_8bit
+                || color_format.channel_type != ChannelType::UInt
+            {
+                return Err(anyhow::anyhow!(
+                    "Unsupported animation color format: {}",
+                    color_format
+                ));
+            }
+            if (frame.image.desc.width(), frame.image.desc.height()) != size {
+                return Err(anyhow::anyhow!("Animation frames differ in size"));
+            }
+            images.push(
+                frame
+                    .image
+                    .clone()
+                    .convert(ColorFormat::RGBA_U8)?
+                    .convert_layout(Layout::Interleaved)?,
+            );
+        }
+
+        Ok((size, images))
+    }
+}
+
+/// Area of the canvas a frame covers.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct FrameRect {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+/// Canvas the frames of a file are drawn on, starting transparent black.
+pub(crate) struct Compositor {
+    width: u32,
+    height: u32,
+    canvas: Vec<u8>,
+}
+
+impl Compositor {
+    pub(crate) fn new(width: u32, height: u32) -> anyhow::Result<Self> {
+        let size = checked_image_size(width, height, 4).ok_or(anyhow::anyhow!(
+            "Invalid animation size: {}x{}",
+            width,
+            height
+        ))?;
+
+        Ok(Self {
+            width,
+            height,
+            canvas: vec![0; size],
+        })
+    }
+
+    /// Draws the RGBA pixels of a frame, clipped to the canvas, and returns the
+    /// canvas as displayed before disposing of the frame area.
+    pub(crate) fn compose(
+        &mut self,
+        pixels: &[u8],
+        rect: FrameRect,
+        blend: Blend,
+        dispose: Dispose,
+    ) -> anyhow::Result<Image> {
+        if pixels.len() < rect.width as usize * rect.height as usize * 4 {
+            return Err(anyhow::anyhow!("Truncated animation frame"));
+        }
+        let previous = (dispose == Dispose::Previous).then(|| self.canvas.clone());
+
+        // offsets and sizes come from the file, the pixel math is done in usize
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (rect_x, rect_y) = (rect.x as usize, rect.y as usize);
+        let columns = rect_x.min(width)..(rect_x + rect.width as usize).min(width);
+        let rows = rect_y.min(height)..(rect_y + rect.height as usize).min(height);
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let from = ((y - rect_y) * rect.width as usize + x - rect_x) * 4;
+                let to = (y * width + x) * 4;
+                let source: [u8; 4] = pixels[from..from + 4].try_into()?;
+                let target: [u8; 4] = self.canvas[to..to + 4].try_into()?;
+                let pixel = match blend {
+                    Blend::Source => source,
+                    Blend::Over => over(source, target),
+                };
+                self.canvas[to..to + 4].copy_from_slice(&pixel);
+            }
+        }
+
+        let image = Image::new_with_data(
+            ImageDesc::new(self.width, self.height, ColorFormat::RGBA_U8),
+            self.canvas.clone(),
+        )?;
+
+        match previous {
+            Some(previous) => self.canvas = previous,
+            None if dispose == Dispose::Background => {
+                for y in rows {
+                    let start = (y * width + columns.start) * 4;
+                    let end = (y * width + columns.end) * 4;
+                    self.canvas[start..end].fill(0);
+                }
+            }
+            None => {}
+        }
+
+        Ok(image)
+    }
+}
+
+/// Composites straight alpha colors.
+fn over(source: [u8; 4], target: [u8; 4]) -> [u8; 4] {
+    match (source[3], target[3]) {
+        (255, _) | (_, 0) => return source,
+        (0, _) => return target,
+        _ => {}
+    }
+
+    let source_alpha = source[3] as f32 / 255.0;
+    let target_alpha = target[3] as f32 / 255.0 * (1.0 - source_alpha);
+    let alpha = source_alpha + target_alpha;
+    let mut result = [0u8; 4];
+    for channel in 0..3 {
+        let value =
+            (source[channel] as f32 * source_alpha + target[channel] as f32 * target_alpha) / alpha;
+        result[channel] = value.round() as u8;
+    }
+    result[3] = (alpha * 255.0).round() as u8;
+
+    result
+}
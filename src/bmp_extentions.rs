@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use ::image::codecs::bmp::BmpEncoder;
+use ::image::ExtendedColorType;
+
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+
+/// Writes an 8 bit image as a bottom-up BGR(A) BMP, gray through a gray palette.
+/// Gray with alpha is written as RGBA, the format having no gray alpha pixels.
+pub(crate) fn save_bmp<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format.channel_size != ChannelSize::_8bit
+        || color_format.channel_type != ChannelType::UInt
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported BMP color format: {}",
+            color_format
+        ));
+    }
+
+    let image = match color_format.channel_count {
+        ChannelCount::GrayAlpha => image.clone().convert(ColorFormat::RGBA_U8)?,
+        _ => image.clone(),
+    }
+    .convert_layout(Layout::Interleaved)?;
+    let color_type = match image.desc.color_format().channel_count {
+        // @formatter:off
+        ChannelCount::Gray => ExtendedColorType::L8,
+        ChannelCount::Rgb => ExtendedColorType::Rgb8,
+        _ => ExtendedColorType::Rgba8,
+        // @formatter:on
+    };
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    BmpEncoder::new(&mut writer).encode(
+        &image.bytes[..image.desc.size_in_bytes() as usize],
+        image.desc.width(),
+        image.desc.height(),
+        color_type,
+    )?;
+
+    Ok(())
+}
@@ -303,9 +303,10 @@ impl ColorFormat {
     };
 }
 
-impl ToString for ColorFormat {
-    fn to_string(&self) -> String {
-        format!(
+impl std::fmt::Display for ColorFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
             "{:?}_{:?}{:?}",
             self.channel_count, self.channel_type, self.channel_size
         )
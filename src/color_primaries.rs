@@ -0,0 +1,192 @@
+use crate::color_format::*;
+use crate::color_space::{
+    bradford_adaptation, convert_pixels, inverse, linear_to_srgb, mul, mul_mat, srgb_to_linear,
+    Mat3, WhitePoint,
+};
+use crate::image::Image;
+
+/// RGB primaries (gamut) the channels of an image refer to.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ColorPrimaries {
+    #[default]
+    Srgb,
+    DisplayP3,
+    Rec2020,
+    AdobeRgb,
+    AcesCg,
+}
+
+/// Transfer function (encoding curve) of the RGB channels of an image.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum TransferFunction {
+    #[default]
+    Srgb,
+    Linear,
+    /// ITU-R BT.709 / BT.2020 camera curve.
+    Bt709,
+    /// Pure 2.2 gamma, as used by Adobe RGB.
+    Gamma22,
+}
+
+/// How colors outside of the target gamut are handled by a primaries conversion.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum GamutMapping {
+    /// Values are kept as they are, negative and above-one components included.
+    #[default]
+    None,
+    /// Components are clamped to `[0, 1]`.
+    Clip,
+    /// Out of gamut colors are desaturated towards their luminance until
+    /// no component is negative, preserving the luminance.
+    Compress,
+}
+
+impl ColorPrimaries {
+    /// CIE xy chromaticities of the red, green and blue primaries.
+    pub fn chromaticities(&self) -> [[f64; 2]; 3] {
+        match self {
+            // @formatter:off
+            ColorPrimaries::Srgb => [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]],
+            ColorPrimaries::DisplayP3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]],
+            ColorPrimaries::Rec2020 => [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046]],
+            ColorPrimaries::AdobeRgb => [[0.64, 0.33], [0.21, 0.71], [0.15, 0.06]],
+            ColorPrimaries::AcesCg => [[0.713, 0.293], [0.165, 0.830], [0.128, 0.044]],
+            // @formatter:on
+        }
+    }
+    pub fn white_point(&self) -> WhitePoint {
+        match self {
+            ColorPrimaries::AcesCg => WhitePoint::D60,
+            _ => WhitePoint::D65,
+        }
+    }
+    /// Matrix converting linear RGB into CIE XYZ relative to the white point of the primaries.
+    pub fn to_xyz(&self) -> Mat3 {
+        let xyz = self
+            .chromaticities()
+            .map(|[x, y]| [x / y, 1.0, (1.0 - x - y) / y]);
+        let m = [
+            [xyz[0][0], xyz[1][0], xyz[2][0]],
+            [xyz[0][1], xyz[1][1], xyz[2][1]],
+            [xyz[0][2], xyz[1][2], xyz[2][2]],
+        ];
+        let s = mul(&inverse(&m), self.white_point().xyz());
+
+        [
+            [m[0][0] * s[0], m[0][1] * s[1], m[0][2] * s[2]],
+            [m[1][0] * s[0], m[1][1] * s[1], m[1][2] * s[2]],
+            [m[2][0] * s[0], m[2][1] * s[1], m[2][2] * s[2]],
+        ]
+    }
+    /// Matrix converting linear RGB in these primaries into linear RGB in `to` primaries,
+    /// white points are adapted with the Bradford transform.
+    pub fn conversion_matrix(&self, to: ColorPrimaries) -> Mat3 {
+        let adaptation = bradford_adaptation(self.white_point(), to.white_point());
+
+        mul_mat(
+            &inverse(&to.to_xyz()),
+            &mul_mat(&adaptation, &self.to_xyz()),
+        )
+    }
+}
+
+impl TransferFunction {
+    /// Converts an encoded value into linear light.
+    pub fn decode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => srgb_to_linear(value),
+            TransferFunction::Linear => value,
+            TransferFunction::Bt709 => {
+                if value < 0.081 {
+                    value / 4.5
+                } else {
+                    ((value + 0.099) / 1.099).powf(1.0 / 0.45)
+                }
+            }
+            TransferFunction::Gamma22 => value.max(0.0).powf(563.0 / 256.0),
+        }
+    }
+    /// Converts a linear light value into its encoded form.
+    pub fn encode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => linear_to_srgb(value),
+            TransferFunction::Linear => value,
+            TransferFunction::Bt709 => {
+                if value < 0.018 {
+                    value * 4.5
+                } else {
+                    1.099 * value.powf(0.45) - 0.099
+                }
+            }
+            TransferFunction::Gamma22 => value.max(0.0).powf(256.0 / 563.0),
+        }
+    }
+}
+
+impl GamutMapping {
+    /// Brings a linear color into gamut, `luminance` is the Y row of the target RGB to XYZ matrix.
+    pub fn apply(&self, rgb: [f64; 3], luminance: [f64; 3]) -> [f64; 3] {
+        match self {
+            GamutMapping::None => rgb,
+            GamutMapping::Clip => rgb.map(|v| v.clamp(0.0, 1.0)),
+            GamutMapping::Compress => {
+                let min = rgb[0].min(rgb[1]).min(rgb[2]);
+                if min >= 0.0 {
+                    return rgb;
+                }
+
+                let y = (luminance[0] * rgb[0] + luminance[1] * rgb[1] + luminance[2] * rgb[2])
+                    .max(0.0);
+                let t = y / (y - min);
+
+                rgb.map(|v| y + (v - y) * t)
+            }
+        }
+    }
+}
+
+pub(crate) fn convert_primaries(
+    from: &Image,
+    to: &mut Image,
+    gamut_mapping: GamutMapping,
+) -> anyhow::Result<()> {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let matrix = from
+        .desc
+        .color_primaries()
+        .conversion_matrix(to.desc.color_primaries());
+    let luminance = to.desc.color_primaries().to_xyz()[1];
+    let from_transfer = from.desc.transfer_function();
+    let to_transfer = to.desc.transfer_function();
+
+    let convert = |rgb: [f64; 3]| {
+        let linear = mul(&matrix, rgb.map(|v| from_transfer.decode(v)));
+
+        gamut_mapping
+            .apply(linear, luminance)
+            .map(|v| to_transfer.encode(v))
+    };
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            convert_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, convert)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            convert_pixels::<f64>(from, to, |v| v, |v| v, convert)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Primaries conversion requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
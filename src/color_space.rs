@@ -0,0 +1,408 @@
+use bytemuck::Pod;
+
+use crate::color_format::*;
+use crate::color_primaries::{ColorPrimaries, TransferFunction};
+use crate::image::Image;
+
+/// Reference white of the CIE based color spaces.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum WhitePoint {
+    #[default]
+    D65,
+    D50,
+    /// ACES white point, close to D60.
+    D60,
+}
+
+/// Color model the channels of an image are expressed in.
+///
+/// `Rgb` is gamma encoded (sRGB transfer) RGB, which is what all codecs produce.
+/// Hues of `Hsv`, `Hsl` and `Lch` are stored in degrees in `[0, 360)`,
+/// `Lab` and `Lch` lightness is in `[0, 100]`, everything else is roughly in `[0, 1]`.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ColorSpace {
+    #[default]
+    Rgb,
+    Hsv,
+    Hsl,
+    Xyz(WhitePoint),
+    Lab(WhitePoint),
+    Lch(WhitePoint),
+    Oklab,
+}
+
+pub type Mat3 = [[f64; 3]; 3];
+
+const BRADFORD: Mat3 = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+impl WhitePoint {
+    pub fn xy(&self) -> [f64; 2] {
+        match self {
+            WhitePoint::D65 => [0.3127, 0.3290],
+            WhitePoint::D50 => [0.3457, 0.3585],
+            WhitePoint::D60 => [0.32168, 0.33767],
+        }
+    }
+    pub fn xyz(&self) -> [f64; 3] {
+        let [x, y] = self.xy();
+
+        [x / y, 1.0, (1.0 - x - y) / y]
+    }
+}
+
+impl ColorSpace {
+    /// Converts a single color from gamma encoded sRGB into this color space.
+    pub fn convert_from_rgb(self, rgb: [f64; 3]) -> [f64; 3] {
+        self.convert_from_rgb_with(rgb, ColorPrimaries::Srgb, TransferFunction::Srgb)
+    }
+    /// Converts a single color from this color space into gamma encoded sRGB.
+    pub fn convert_to_rgb(self, color: [f64; 3]) -> [f64; 3] {
+        self.convert_to_rgb_with(color, ColorPrimaries::Srgb, TransferFunction::Srgb)
+    }
+    /// Converts a single color from RGB with the given primaries and transfer function
+    /// into this color space. HSV and HSL are computed on the encoded RGB values.
+    pub fn convert_from_rgb_with(
+        self,
+        rgb: [f64; 3],
+        primaries: ColorPrimaries,
+        transfer: TransferFunction,
+    ) -> [f64; 3] {
+        let to_xyz = |white: WhitePoint| {
+            let xyz = mul(&primaries.to_xyz(), rgb.map(|v| transfer.decode(v)));
+            mul(&bradford_adaptation(primaries.white_point(), white), xyz)
+        };
+
+        match self {
+            ColorSpace::Rgb => rgb,
+            ColorSpace::Hsv => rgb_to_hsv(rgb),
+            ColorSpace::Hsl => rgb_to_hsl(rgb),
+            ColorSpace::Xyz(white) => to_xyz(white),
+            ColorSpace::Lab(white) => xyz_to_lab(to_xyz(white), white),
+            ColorSpace::Lch(white) => lab_to_lch(xyz_to_lab(to_xyz(white), white)),
+            ColorSpace::Oklab => {
+                let matrix = primaries.conversion_matrix(ColorPrimaries::Srgb);
+                linear_to_oklab(mul(&matrix, rgb.map(|v| transfer.decode(v))))
+            }
+        }
+    }
+    /// Converts a single color from this color space into RGB with the given primaries
+    /// and transfer function.
+    pub fn convert_to_rgb_with(
+        self,
+        color: [f64; 3],
+        primaries: ColorPrimaries,
+        transfer: TransferFunction,
+    ) -> [f64; 3] {
+        let from_xyz = |xyz: [f64; 3], white: WhitePoint| {
+            let xyz = mul(&bradford_adaptation(white, primaries.white_point()), xyz);
+            mul(&inverse(&primaries.to_xyz()), xyz).map(|v| transfer.encode(v))
+        };
+
+        match self {
+            ColorSpace::Rgb => color,
+            ColorSpace::Hsv => hsv_to_rgb(color),
+            ColorSpace::Hsl => hsl_to_rgb(color),
+            ColorSpace::Xyz(white) => from_xyz(color, white),
+            ColorSpace::Lab(white) => from_xyz(lab_to_xyz(color, white), white),
+            ColorSpace::Lch(white) => from_xyz(lab_to_xyz(lch_to_lab(color), white), white),
+            ColorSpace::Oklab => {
+                let matrix = ColorPrimaries::Srgb.conversion_matrix(primaries);
+                mul(&matrix, oklab_to_linear(color)).map(|v| transfer.encode(v))
+            }
+        }
+    }
+}
+
+pub(crate) fn convert_color_space(from: &Image, to: &mut Image) -> anyhow::Result<()> {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let from_space = from.desc.color_space();
+    let to_space = to.desc.color_space();
+    let primaries = from.desc.color_primaries();
+    let transfer = from.desc.transfer_function();
+
+    let convert = |color: [f64; 3]| {
+        let rgb = from_space.convert_to_rgb_with(color, primaries, transfer);
+        to_space.convert_from_rgb_with(rgb, primaries, transfer)
+    };
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            convert_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, convert)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            convert_pixels::<f64>(from, to, |v| v, |v| v, convert)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Color space conversion requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies `convert` to the first three channels of every pixel of a float image,
+/// any further channel is left as it is in `to`.
+pub(crate) fn convert_pixels<T>(
+    from: &Image,
+    to: &mut Image,
+    to_f64: fn(T) -> f64,
+    from_f64: fn(f64) -> T,
+    convert: impl Fn([f64; 3]) -> [f64; 3],
+) where
+    T: Copy + Pod,
+{
+    let pixel_size = from.desc.color_format().byte_count();
+
+    for i in 0..from.desc.height() {
+        for j in 0..from.desc.width() {
+            let from_offset = (i * from.desc.stride() + j * pixel_size) as usize;
+            let from_pixel: &[T] =
+                bytemuck::cast_slice(&from.bytes[from_offset..from_offset + pixel_size as usize]);
+
+            let color = convert([
+                to_f64(from_pixel[0]),
+                to_f64(from_pixel[1]),
+                to_f64(from_pixel[2]),
+            ]);
+
+            let to_offset = (i * to.desc.stride() + j * pixel_size) as usize;
+            let to_pixel: &mut [T] =
+                bytemuck::cast_slice_mut(&mut to.bytes[to_offset..to_offset + pixel_size as usize]);
+
+            to_pixel[0] = from_f64(color[0]);
+            to_pixel[1] = from_f64(color[1]);
+            to_pixel[2] = from_f64(color[2]);
+        }
+    }
+}
+
+pub(crate) fn mul(m: &Mat3, v: [f64; 3]) -> [f64; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+pub(crate) fn mul_mat(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+
+    result
+}
+
+pub(crate) fn inverse(m: &Mat3) -> Mat3 {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+    [
+        [
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) / det,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) / det,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) / det,
+        ],
+        [
+            (m[1][2] * m[2][0] - m[1][0] * m[2][2]) / det,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) / det,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) / det,
+        ],
+        [
+            (m[1][0] * m[2][1] - m[1][1] * m[2][0]) / det,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) / det,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) / det,
+        ],
+    ]
+}
+
+/// Bradford chromatic adaptation matrix between two white points, applied to XYZ.
+pub fn bradford_adaptation(from: WhitePoint, to: WhitePoint) -> Mat3 {
+    let from_lms = mul(&BRADFORD, from.xyz());
+    let to_lms = mul(&BRADFORD, to.xyz());
+
+    let scale = [
+        [to_lms[0] / from_lms[0], 0.0, 0.0],
+        [0.0, to_lms[1] / from_lms[1], 0.0],
+        [0.0, 0.0, to_lms[2] / from_lms[2]],
+    ];
+
+    mul_mat(&inverse(&BRADFORD), &mul_mat(&scale, &BRADFORD))
+}
+
+#[inline]
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+#[inline]
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn xyz_to_lab(xyz: [f64; 3], white: WhitePoint) -> [f64; 3] {
+    const DELTA: f64 = 6.0 / 29.0;
+    let f = |t: f64| {
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+
+    let white = white.xyz();
+    let fx = f(xyz[0] / white[0]);
+    let fy = f(xyz[1] / white[1]);
+    let fz = f(xyz[2] / white[2]);
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+fn lab_to_xyz(lab: [f64; 3], white: WhitePoint) -> [f64; 3] {
+    const DELTA: f64 = 6.0 / 29.0;
+    let f_inv = |t: f64| {
+        if t > DELTA {
+            t * t * t
+        } else {
+            3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+        }
+    };
+
+    let white = white.xyz();
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+
+    [
+        white[0] * f_inv(fx),
+        white[1] * f_inv(fy),
+        white[2] * f_inv(fz),
+    ]
+}
+
+fn lab_to_lch(lab: [f64; 3]) -> [f64; 3] {
+    let c = (lab[1] * lab[1] + lab[2] * lab[2]).sqrt();
+    let h = lab[2].atan2(lab[1]).to_degrees().rem_euclid(360.0);
+
+    [lab[0], c, h]
+}
+fn lch_to_lab(lch: [f64; 3]) -> [f64; 3] {
+    let h = lch[2].to_radians();
+
+    [lch[0], lch[1] * h.cos(), lch[1] * h.sin()]
+}
+
+fn linear_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let l = 0.4122214708 * rgb[0] + 0.5363325363 * rgb[1] + 0.0514459929 * rgb[2];
+    let m = 0.2119034982 * rgb[0] + 0.6806995451 * rgb[1] + 0.1073969566 * rgb[2];
+    let s = 0.0883024619 * rgb[0] + 0.2817188376 * rgb[1] + 0.6299787005 * rgb[2];
+
+    let (l, m, s) = (l.cbrt(), m.cbrt(), s.cbrt());
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+fn oklab_to_linear(lab: [f64; 3]) -> [f64; 3] {
+    let l = lab[0] + 0.3963377774 * lab[1] + 0.2158037573 * lab[2];
+    let m = lab[0] - 0.1055613458 * lab[1] - 0.0638541728 * lab[2];
+    let s = lab[0] - 0.0894841775 * lab[1] - 1.2914855480 * lab[2];
+
+    let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+fn rgb_to_hsv(rgb: [f64; 3]) -> [f64; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let delta = max - min;
+
+    let s = if max > 0.0 { delta / max } else { 0.0 };
+
+    [hue(rgb, max, delta), s, max]
+}
+fn hsv_to_rgb(hsv: [f64; 3]) -> [f64; 3] {
+    let c = hsv[2] * hsv[1];
+
+    from_hue(hsv[0], c, hsv[2] - c)
+}
+
+fn rgb_to_hsl(rgb: [f64; 3]) -> [f64; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let delta = max - min;
+
+    let l = (max + min) / 2.0;
+    let s = if delta > 0.0 {
+        delta / (1.0 - (2.0 * l - 1.0).abs())
+    } else {
+        0.0
+    };
+
+    [hue(rgb, max, delta), s, l]
+}
+fn hsl_to_rgb(hsl: [f64; 3]) -> [f64; 3] {
+    let c = (1.0 - (2.0 * hsl[2] - 1.0).abs()) * hsl[1];
+
+    from_hue(hsl[0], c, hsl[2] - c / 2.0)
+}
+
+fn hue(rgb: [f64; 3], max: f64, delta: f64) -> f64 {
+    if delta <= 0.0 {
+        return 0.0;
+    }
+
+    let h = if max == rgb[0] {
+        (rgb[1] - rgb[2]) / delta
+    } else if max == rgb[1] {
+        (rgb[2] - rgb[0]) / delta + 2.0
+    } else {
+        (rgb[0] - rgb[1]) / delta + 4.0
+    };
+
+    (h * 60.0).rem_euclid(360.0)
+}
+fn from_hue(h: f64, c: f64, m: f64) -> [f64; 3] {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    [r + m, g + m, b + m]
+}
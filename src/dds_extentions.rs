@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::texture::{
+    format_code, format_from_code, image_from_texels, mip_chain_size, mip_size, texel_bytes,
+    texel_size, FormatTable, TexelEncoding, Texture,
+};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: u32 = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+/// Magic, header and DX10 header.
+const DATA_OFFSET: usize = 4 + HEADER_SIZE as usize + 20;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+/// Cube map with all six faces.
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFE00;
+const RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Uncompressed DXGI formats, the gray ones being red and red green.
+const DXGI_FORMATS: &FormatTable = &[
+    (2, ColorFormat::RGBA_F32, TexelEncoding::Plain),
+    (3, ColorFormat::RGBA_U32, TexelEncoding::Plain),
+    (4, ColorFormat::RGBA_I32, TexelEncoding::Plain),
+    (6, ColorFormat::RGB_F32, TexelEncoding::Plain),
+    (7, ColorFormat::RGB_U32, TexelEncoding::Plain),
+    (8, ColorFormat::RGB_I32, TexelEncoding::Plain),
+    (10, ColorFormat::RGBA_F32, TexelEncoding::Half),
+    (11, ColorFormat::RGBA_U16, TexelEncoding::Plain),
+    (13, ColorFormat::RGBA_I16, TexelEncoding::Plain),
+    (16, ColorFormat::GRAY_ALPHA_F32, TexelEncoding::Plain),
+    (17, ColorFormat::GRAY_ALPHA_U32, TexelEncoding::Plain),
+    (18, ColorFormat::GRAY_ALPHA_I32, TexelEncoding::Plain),
+    (28, ColorFormat::RGBA_U8, TexelEncoding::Plain),
+    (29, ColorFormat::RGBA_U8, TexelEncoding::Srgb),
+    (31, ColorFormat::RGBA_I8, TexelEncoding::Plain),
+    (34, ColorFormat::GRAY_ALPHA_F32, TexelEncoding::Half),
+    (35, ColorFormat::GRAY_ALPHA_U16, TexelEncoding::Plain),
+    (37, ColorFormat::GRAY_ALPHA_I16, TexelEncoding::Plain),
+    (41, ColorFormat::GRAY_F32, TexelEncoding::Plain),
+    (42, ColorFormat::GRAY_U32, TexelEncoding::Plain),
+    (43, ColorFormat::GRAY_I32, TexelEncoding::Plain),
+    (49, ColorFormat::GRAY_ALPHA_U8, TexelEncoding::Plain),
+    (51, ColorFormat::GRAY_ALPHA_I8, TexelEncoding::Plain),
+    (54, ColorFormat::GRAY_F32, TexelEncoding::Half),
+    (56, ColorFormat::GRAY_U16, TexelEncoding::Plain),
+    (58, ColorFormat::GRAY_I16, TexelEncoding::Plain),
+    (61, ColorFormat::GRAY_U8, TexelEncoding::Plain),
+    (63, ColorFormat::GRAY_I8, TexelEncoding::Plain),
+];
+
+/// Reads a 2D DDS with a DX10 header, its array elements, cube faces and mip levels.
+pub(crate) fn load_dds<P: AsRef<Path>>(filename: P) -> anyhow::Result<Texture> {
+    let data = std::fs::read(filename)?;
+    if data.len() < DATA_OFFSET || &data[..4] != MAGIC {
+        return Err(anyhow::anyhow!("Not a DDS file"));
+    }
+    let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+    // header fields follow the magic
+    let flags = u32_at(8);
+    let (height, width) = (u32_at(12), u32_at(16));
+    let level_count = match u32_at(28) {
+        count if flags & DDSD_MIPMAPCOUNT != 0 && count > 0 => count as usize,
+        _ => 1,
+    };
+    let pixel_format_flags = u32_at(80);
+    if pixel_format_flags & DDPF_FOURCC == 0 || &data[84..88] != b"DX10" {
+        return Err(anyhow::anyhow!("Only DDS with a DX10 header are supported"));
+    }
+
+    let dxgi_format = u32_at(128);
+    let (color_format, encoding, transfer_function) =
+        format_from_code(DXGI_FORMATS, dxgi_format)
+            .ok_or(anyhow::anyhow!("Unsupported DXGI format: {}", dxgi_format))?;
+    if u32_at(132) != RESOURCE_DIMENSION_TEXTURE2D {
+        return Err(anyhow::anyhow!("Only 2D DDS textures are supported"));
+    }
+    let cube_map = u32_at(136) & RESOURCE_MISC_TEXTURECUBE != 0;
+    let face_count = if cube_map { 6 } else { 1 };
+    let layer_count = u32_at(140).max(1) as usize * face_count;
+
+    let texel_size = texel_size(color_format, encoding);
+    let chain_size = mip_chain_size(width, height, level_count, texel_size)?;
+    if chain_size
+        .checked_mul(layer_count)
+        .is_none_or(|size| size > data.len() - DATA_OFFSET)
+    {
+        return Err(anyhow::anyhow!("Truncated DDS data"));
+    }
+
+    let mut position = DATA_OFFSET;
+    let mut layers = Vec::with_capacity(layer_count);
+    for _ in 0..layer_count {
+        let mut chain = Vec::with_capacity(level_count);
+        for level in 0..level_count {
+            let size = mip_size(width, height, level);
+            let length = size.0 as usize * size.1 as usize * texel_size;
+            let texels = position
+                .checked_add(length)
+                .and_then(|end| data.get(position..end))
+                .ok_or(anyhow::anyhow!("Truncated DDS data"))?;
+            position += length;
+
+            chain.push(image_from_texels(
+                texels,
+                size,
+                color_format,
+                encoding,
+                transfer_function,
+            )?);
+        }
+        layers.push(chain);
+    }
+
+    Ok(Texture::new(layers, cube_map))
+}
+
+/// Writes a texture as a DDS with a DX10 header.
+pub(crate) fn save_dds<P: AsRef<Path>>(texture: &Texture, filename: P) -> anyhow::Result<()> {
+    let shape = texture.shape()?;
+    let dxgi_format = format_code(DXGI_FORMATS, &shape.desc).ok_or(anyhow::anyhow!(
+        "Unsupported DDS color format: {}",
+        shape.desc.color_format()
+    ))?;
+    let element_count = u32::try_from(shape.element_count)?;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT;
+    let mut caps = DDSCAPS_TEXTURE;
+    if shape.level_count > 1 {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    if texture.cube_map || shape.element_count > 1 {
+        caps |= DDSCAPS_COMPLEX;
+    }
+    let caps2 = if texture.cube_map {
+        DDSCAPS2_CUBEMAP_ALL_FACES
+    } else {
+        0
+    };
+    let pitch = shape.desc.width() * shape.desc.color_format().byte_count();
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(MAGIC)?;
+    for value in [
+        HEADER_SIZE,
+        flags,
+        shape.desc.height(),
+        shape.desc.width(),
+        pitch,
+        0,
+        shape.level_count as u32,
+    ] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(&[0; 11 * 4])?;
+
+    writer.write_all(&PIXEL_FORMAT_SIZE.to_le_bytes())?;
+    writer.write_all(&DDPF_FOURCC.to_le_bytes())?;
+    writer.write_all(b"DX10")?;
+    // bit count and masks are unused with a four character code
+    writer.write_all(&[0; 5 * 4])?;
+
+    for value in [caps, caps2, 0, 0, 0] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    let misc_flags = if texture.cube_map {
+        RESOURCE_MISC_TEXTURECUBE
+    } else {
+        0
+    };
+    // cube maps count cubes rather than faces
+    for value in [
+        dxgi_format,
+        RESOURCE_DIMENSION_TEXTURE2D,
+        misc_flags,
+        element_count,
+        0,
+    ] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for chain in &texture.layers {
+        for image in chain {
+            writer.write_all(&texel_bytes(image)?)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
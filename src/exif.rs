@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use crate::ifd::{read_header, read_ifd, write_ifd};
+use crate::image::Image;
+use crate::metadata::TagValue;
+
+/// Pointer to the EXIF directory.
+pub(crate) const EXIF_IFD_TAG: u16 = 34665;
+/// Pointer to the GPS directory.
+pub(crate) const GPS_IFD_TAG: u16 = 34853;
+/// Pointer to the interoperability directory, only kept by the camera.
+const INTEROPERABILITY_IFD_TAG: u16 = 40965;
+
+const ORIENTATION_TAG: u16 = 274;
+/// Tags of the main directory that belong to the EXIF metadata of a TIFF.
+pub(crate) const EXIF_IMAGE_TAGS: [u16; 4] = [271, 272, ORIENTATION_TAG, 33432];
+
+/// Transform to apply to the stored pixels to display them upright.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Orientation {
+    #[default]
+    Normal,
+    FlipHorizontal,
+    Rotate180,
+    FlipVertical,
+    /// Flip around the top left to bottom right diagonal.
+    Transpose,
+    /// Quarter turn clockwise.
+    Rotate90,
+    /// Flip around the top right to bottom left diagonal.
+    Transverse,
+    /// Quarter turn counterclockwise.
+    Rotate270,
+}
+
+impl Orientation {
+    /// Reads an EXIF orientation value, from 1 to 8.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            // @formatter:off
+            1 => Some(Orientation::Normal),
+            2 => Some(Orientation::FlipHorizontal),
+            3 => Some(Orientation::Rotate180),
+            4 => Some(Orientation::FlipVertical),
+            5 => Some(Orientation::Transpose),
+            6 => Some(Orientation::Rotate90),
+            7 => Some(Orientation::Transverse),
+            8 => Some(Orientation::Rotate270),
+            _ => None,
+            // @formatter:on
+        }
+    }
+    pub fn to_u16(&self) -> u16 {
+        *self as u16 + 1
+    }
+    /// Whether the width and height are exchanged.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(
+            self,
+            Orientation::Transpose
+                | Orientation::Rotate90
+                | Orientation::Transverse
+                | Orientation::Rotate270
+        )
+    }
+}
+
+/// GPS position, in degrees and meters above sea level.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct GpsPosition {
+    /// Positive to the north.
+    pub latitude: f64,
+    /// Positive to the east.
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+/// EXIF metadata, kept as the tags of its three directories.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Exif {
+    /// Tags of the main image directory, e.g. Make, Model and Orientation.
+    pub image_tags: BTreeMap<u16, TagValue>,
+    /// Tags of the EXIF directory, e.g. exposure and timestamps.
+    pub exif_tags: BTreeMap<u16, TagValue>,
+    pub gps_tags: BTreeMap<u16, TagValue>,
+}
+
+impl Exif {
+    /// Parses an EXIF block, a TIFF structure optionally preceded by the `Exif\0\0` marker.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Exif> {
+        let bytes = bytes.strip_prefix(b"Exif\0\0").unwrap_or(bytes);
+        let mut reader = Cursor::new(bytes);
+
+        let header = read_header(&mut reader)?;
+        let mut image_tags = read_ifd(&mut reader, &header, header.first_ifd)?;
+
+        let mut sub_ifd = |tag| match image_tags.remove(&tag) {
+            Some(TagValue::Long(offsets)) if !offsets.is_empty() => {
+                read_ifd(&mut reader, &header, offsets[0] as u64)
+            }
+            Some(TagValue::Long8(offsets)) if !offsets.is_empty() => {
+                read_ifd(&mut reader, &header, offsets[0])
+            }
+            _ => Ok(BTreeMap::new()),
+        };
+        let mut exif_tags = sub_ifd(EXIF_IFD_TAG)?;
+        let gps_tags = sub_ifd(GPS_IFD_TAG)?;
+        exif_tags.remove(&INTEROPERABILITY_IFD_TAG);
+
+        Ok(Exif {
+            image_tags,
+            exif_tags,
+            gps_tags,
+        })
+    }
+
+    /// Serializes the metadata as a little endian EXIF block, without the `Exif\0\0` marker.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut image_tags = self.image_tags.clone();
+        // the pointers have a fixed size, their placeholders give the size of the directory
+        for (tag, tags) in [
+            (EXIF_IFD_TAG, &self.exif_tags),
+            (GPS_IFD_TAG, &self.gps_tags),
+        ] {
+            if !tags.is_empty() {
+                image_tags.insert(tag, TagValue::Long(vec![0]));
+            }
+        }
+
+        let mut offset = 8 + write_ifd(&image_tags, 8, false, false).len() as u64;
+        let mut sub_ifds = Vec::new();
+        for (tag, tags) in [
+            (EXIF_IFD_TAG, &self.exif_tags),
+            (GPS_IFD_TAG, &self.gps_tags),
+        ] {
+            if !tags.is_empty() {
+                image_tags.insert(tag, TagValue::Long(vec![offset as u32]));
+                let ifd = write_ifd(tags, offset, false, false);
+                offset += ifd.len() as u64;
+                sub_ifds.extend(ifd);
+            }
+        }
+
+        let mut bytes = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        bytes.extend(write_ifd(&image_tags, 8, false, false));
+        bytes.extend(sub_ifds);
+        bytes
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        match self.image_tags.get(&ORIENTATION_TAG) {
+            Some(TagValue::Short(values)) => values
+                .first()
+                .and_then(|&value| Orientation::from_u16(value))
+                .unwrap_or_default(),
+            _ => Orientation::default(),
+        }
+    }
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.image_tags
+            .insert(ORIENTATION_TAG, TagValue::Short(vec![orientation.to_u16()]));
+    }
+
+    pub fn make(&self) -> Option<&str> {
+        ascii(self.image_tags.get(&271))
+    }
+    pub fn model(&self) -> Option<&str> {
+        ascii(self.image_tags.get(&272))
+    }
+    /// Date and time the picture was taken, formatted as `YYYY:MM:DD HH:MM:SS`.
+    pub fn date_time_original(&self) -> Option<&str> {
+        ascii(self.exif_tags.get(&36867))
+    }
+    /// Exposure time in seconds.
+    pub fn exposure_time(&self) -> Option<f64> {
+        rational(self.exif_tags.get(&33434), 0)
+    }
+    pub fn f_number(&self) -> Option<f64> {
+        rational(self.exif_tags.get(&33437), 0)
+    }
+    pub fn iso(&self) -> Option<u32> {
+        match self.exif_tags.get(&34855) {
+            Some(TagValue::Short(values)) => values.first().map(|&v| v as u32),
+            Some(TagValue::Long(values)) => values.first().copied(),
+            _ => None,
+        }
+    }
+    /// Focal length in millimeters.
+    pub fn focal_length(&self) -> Option<f64> {
+        rational(self.exif_tags.get(&37386), 0)
+    }
+    pub fn gps(&self) -> Option<GpsPosition> {
+        let degrees = |tag| {
+            let value = self.gps_tags.get(&tag);
+            Some(
+                rational(value, 0)?
+                    + rational(value, 1).unwrap_or(0.0) / 60.0
+                    + rational(value, 2).unwrap_or(0.0) / 3600.0,
+            )
+        };
+        let sign = |tag, negative| match ascii(self.gps_tags.get(&tag)) {
+            Some(reference) if reference.starts_with(negative) => -1.0,
+            _ => 1.0,
+        };
+
+        let latitude = degrees(2)? * sign(1, 'S');
+        let longitude = degrees(4)? * sign(3, 'W');
+        let altitude = rational(self.gps_tags.get(&6), 0).map(|altitude| {
+            match self.gps_tags.get(&5) {
+                // below sea level
+                Some(TagValue::Byte(reference)) if reference.first() == Some(&1) => -altitude,
+                _ => altitude,
+            }
+        });
+
+        Some(GpsPosition {
+            latitude,
+            longitude,
+            altitude,
+        })
+    }
+}
+
+fn ascii(value: Option<&TagValue>) -> Option<&str> {
+    match value {
+        Some(TagValue::Ascii(text)) => Some(text.as_str()),
+        _ => None,
+    }
+}
+
+fn rational(value: Option<&TagValue>, index: usize) -> Option<f64> {
+    match value {
+        Some(TagValue::Rational(values)) => values
+            .get(index)
+            .filter(|[_, d]| *d != 0)
+            .map(|[n, d]| *n as f64 / *d as f64),
+        _ => None,
+    }
+}
+
+/// Copies the pixels of an interleaved image into `to`, transformed by `orientation`.
+pub(crate) fn orient(from: &Image, to: &mut Image, orientation: Orientation) {
+    let (w, h) = (from.desc.width(), from.desc.height());
+    assert_eq!(
+        (to.desc.width(), to.desc.height()),
+        if orientation.swaps_dimensions() {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    );
+
+    let pixel_size = from.bytes_per_pixel() as usize;
+    for y in 0..to.desc.height() {
+        for x in 0..to.desc.width() {
+            let (from_x, from_y) = match orientation {
+                // @formatter:off
+                Orientation::Normal => (x, y),
+                Orientation::FlipHorizontal => (w - 1 - x, y),
+                Orientation::Rotate180 => (w - 1 - x, h - 1 - y),
+                Orientation::FlipVertical => (x, h - 1 - y),
+                Orientation::Transpose => (y, x),
+                Orientation::Rotate90 => (y, h - 1 - x),
+                Orientation::Transverse => (w - 1 - y, h - 1 - x),
+                Orientation::Rotate270 => (w - 1 - y, x),
+                // @formatter:on
+            };
+
+            let from_offset = (from_y * from.desc.stride()) as usize + from_x as usize * pixel_size;
+            let to_offset = (y * to.desc.stride()) as usize + x as usize * pixel_size;
+            to.bytes[to_offset..to_offset + pixel_size]
+                .copy_from_slice(&from.bytes[from_offset..from_offset + pixel_size]);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use ::exr::prelude::{
+    f16, read, AnyChannel, AnyChannels, Blocks, Compression, Encoding, FlatSamples,
+    ImageAttributes, IntegerBounds, Layer, LayerAttributes, LineOrder, ReadChannels, ReadLayers,
+    SmallVec, Vec2, WritableImage,
+};
+
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{Image, ImageDesc, Layout};
+use crate::save_options::{ExrCompression, ExrOptions};
+
+/// Image of an EXR file, one per part or per channel name prefix, e.g. `diffuse.R`.
+#[derive(Clone)]
+pub struct ExrLayer {
+    /// Part and prefix names joined by dots, `None` for the main layer.
+    pub name: Option<String>,
+    pub image: Image,
+}
+
+impl ExrLayer {
+    pub fn new(name: Option<String>, image: Image) -> Self {
+        Self { name, image }
+    }
+}
+
+/// Reads the layers of an EXR, the largest resolution level of the mipmapped ones.
+///
+/// R, G, B and A channels form RGB(A) images and Y and A gray ones, other channels
+/// are read as separate gray images. Samples are 32 bit floats, or 32 bit unsigned
+/// integers when all the channels of an image are, and marked as linear.
+pub(crate) fn load_exr_layers<P: AsRef<Path>>(filename: P) -> anyhow::Result<Vec<ExrLayer>> {
+    let exr = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .all_channels()
+        .all_layers()
+        .all_attributes()
+        .from_file(filename)?;
+
+    let mut layers = Vec::new();
+    for part in &exr.layer_data {
+        let part_name = part
+            .attributes
+            .layer_name
+            .as_ref()
+            .map(|name| name.to_string());
+
+        // channels named `prefix.channel` belong to the `prefix` layer
+        let mut groups: BTreeMap<String, BTreeMap<String, &FlatSamples>> = BTreeMap::new();
+        for channel in &part.channel_data.list {
+            let name = channel.name.to_string();
+            let (prefix, base) = match name.rsplit_once('.') {
+                Some((prefix, base)) => (prefix.to_string(), base.to_string()),
+                None => (String::new(), name),
+            };
+            groups
+                .entry(prefix)
+                .or_default()
+                .insert(base, &channel.sample_data);
+        }
+
+        for (prefix, mut channels) in groups {
+            let layer_name = |suffix: Option<&str>| {
+                let names: Vec<&str> = [part_name.as_deref(), Some(prefix.as_str()), suffix]
+                    .into_iter()
+                    .flatten()
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                (!names.is_empty()).then(|| names.join("."))
+            };
+
+            let color_channels: &[&str] = if ["R", "G", "B"]
+                .iter()
+                .all(|name| channels.contains_key(*name))
+            {
+                &["R", "G", "B", "A"]
+            } else if channels.contains_key("Y") {
+                &["Y", "A"]
+            } else {
+                &[]
+            };
+            let samples: Vec<&FlatSamples> = color_channels
+                .iter()
+                .filter_map(|name| channels.remove(*name))
+                .collect();
+            if !samples.is_empty() {
+                let image = interleave(&samples, part.size)?;
+                layers.push(ExrLayer::new(layer_name(None), image));
+            }
+
+            for (name, samples) in channels {
+                let image = interleave(&[samples], part.size)?;
+                layers.push(ExrLayer::new(layer_name(Some(&name)), image));
+            }
+        }
+    }
+
+    Ok(layers)
+}
+
+/// Builds an interleaved image from the planar samples of its channels.
+fn interleave(channels: &[&FlatSamples], size: Vec2<usize>) -> anyhow::Result<Image> {
+    let channel_count = match channels.len() {
+        // @formatter:off
+        1 => ChannelCount::Gray,
+        2 => ChannelCount::GrayAlpha,
+        3 => ChannelCount::Rgb,
+        _ => ChannelCount::Rgba,
+        // @formatter:on
+    };
+    let all_uint = channels
+        .iter()
+        .all(|samples| matches!(samples, FlatSamples::U32(_)));
+    let channel_type = if all_uint {
+        ChannelType::UInt
+    } else {
+        ChannelType::Float
+    };
+    let desc = ImageDesc::new(
+        size.width() as u32,
+        size.height() as u32,
+        ColorFormat::from((channel_count, ChannelSize::_32bit, channel_type)),
+    )
+    .with_transfer_function(TransferFunction::Linear);
+
+    let pixel_count = size.area();
+    let mut bytes = Vec::with_capacity(pixel_count * channels.len() * 4);
+    for index in 0..pixel_count {
+        for samples in channels {
+            let sample = match samples {
+                // @formatter:off
+                FlatSamples::F16(values) => values[index].to_f32().to_ne_bytes(),
+                FlatSamples::F32(values) => values[index].to_ne_bytes(),
+                FlatSamples::U32(values) if all_uint => values[index].to_ne_bytes(),
+                FlatSamples::U32(values) => (values[index] as f32).to_ne_bytes(),
+                // @formatter:on
+            };
+            bytes.extend_from_slice(&sample);
+        }
+    }
+
+    Image::new_with_data(desc, bytes)
+}
+
+/// Writes the images as the parts of an EXR, named after their layers, which
+/// must all have a name when there are several.
+pub(crate) fn save_exr_layers<P: AsRef<Path>>(
+    layers: &[ExrLayer],
+    filename: P,
+    options: &ExrOptions,
+) -> anyhow::Result<()> {
+    if layers.is_empty() {
+        return Err(anyhow::anyhow!("No layer to save"));
+    }
+    // parts are told apart by their names
+    if layers.len() > 1 && layers.iter().any(|layer| layer.name.is_none()) {
+        return Err(anyhow::anyhow!(
+            "Every layer of a multi-layer EXR must be named"
+        ));
+    }
+
+    let encoding = Encoding {
+        compression: match options.compression {
+            // @formatter:off
+            ExrCompression::None => Compression::Uncompressed,
+            ExrCompression::Rle => Compression::RLE,
+            ExrCompression::Zip => Compression::ZIP16,
+            ExrCompression::Piz => Compression::PIZ,
+            // @formatter:on
+        },
+        blocks: match options.tile_size {
+            Some((width, height)) => Blocks::Tiles(Vec2(width as usize, height as usize)),
+            None => Blocks::ScanLines,
+        },
+        line_order: LineOrder::Increasing,
+    };
+
+    let mut exr_layers = Vec::with_capacity(layers.len());
+    let mut display_size = Vec2(0, 0);
+    for layer in layers {
+        let image = layer.image.clone().convert_layout(Layout::Interleaved)?;
+        let size = Vec2(image.desc.width() as usize, image.desc.height() as usize);
+        display_size = Vec2(
+            display_size.width().max(size.width()),
+            display_size.height().max(size.height()),
+        );
+
+        let attributes = match &layer.name {
+            Some(name) => LayerAttributes::named(name.as_str()),
+            None => LayerAttributes::default(),
+        };
+        exr_layers.push(Layer::new(
+            size,
+            attributes,
+            encoding,
+            AnyChannels::sort(deinterleave(&image, options.half)?),
+        ));
+    }
+
+    let exr = ::exr::prelude::Image::from_layers(
+        ImageAttributes::new(IntegerBounds::from_dimensions(display_size)),
+        exr_layers,
+    );
+    exr.write().to_file(filename)?;
+
+    Ok(())
+}
+
+pub(crate) fn save_exr<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &ExrOptions,
+) -> anyhow::Result<()> {
+    save_exr_layers(&[ExrLayer::new(None, image.clone())], filename, options)
+}
+
+/// Splits an interleaved image into named planar channels.
+fn deinterleave(
+    image: &Image,
+    half: bool,
+) -> anyhow::Result<SmallVec<[AnyChannel<FlatSamples>; 4]>> {
+    let color_format = image.desc.color_format();
+    let names: &[&str] = match color_format.channel_count {
+        // @formatter:off
+        ChannelCount::Gray => &["Y"],
+        ChannelCount::GrayAlpha => &["Y", "A"],
+        ChannelCount::Rgb => &["R", "G", "B"],
+        ChannelCount::Rgba => &["R", "G", "B", "A"],
+        // @formatter:on
+    };
+
+    let bytes = &image.bytes[..image.desc.size_in_bytes() as usize];
+    let channel = |index: usize| {
+        let samples = bytes
+            .chunks_exact(color_format.byte_count() as usize)
+            .map(move |pixel| {
+                let sample = &pixel[index * 4..index * 4 + 4];
+                [sample[0], sample[1], sample[2], sample[3]]
+            });
+
+        match (color_format.channel_type, color_format.channel_size) {
+            (ChannelType::Float, ChannelSize::_32bit) if half => Ok(FlatSamples::F16(
+                samples
+                    .map(|sample| f16::from_f32(f32::from_ne_bytes(sample)))
+                    .collect(),
+            )),
+            (ChannelType::Float, ChannelSize::_32bit) => {
+                Ok(FlatSamples::F32(samples.map(f32::from_ne_bytes).collect()))
+            }
+            (ChannelType::UInt, ChannelSize::_32bit) => {
+                Ok(FlatSamples::U32(samples.map(u32::from_ne_bytes).collect()))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Unsupported EXR color format: {}",
+                color_format
+            )),
+        }
+    };
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let samples = channel(index)?;
+            // alpha is quantized linearly by the lossy compressions
+            Ok(AnyChannel {
+                name: (*name).into(),
+                sample_data: samples,
+                quantize_linearly: *name == "A",
+                sampling: Vec2(1, 1),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,220 @@
+use std::borrow::Cow;
+
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::save_options::{Adaptation, SaveOptions, TiffPredictor};
+
+/// Converts the image to the nearest color format the file format of `extension` can hold,
+/// and the options to ones valid for it. Supported images are borrowed unchanged.
+pub(crate) fn adapt_for_format<'a>(
+    image: &'a Image,
+    extension: &str,
+    options: &SaveOptions,
+) -> anyhow::Result<(Cow<'a, Image>, SaveOptions, Vec<Adaptation>)> {
+    let color_format = image.desc.color_format();
+    let mut options = *options;
+    let mut adaptations = Vec::new();
+
+    let target = match extension {
+        "jpeg" | "jpg" => {
+            let channel_count = match color_format.channel_count {
+                ChannelCount::Gray | ChannelCount::GrayAlpha => ChannelCount::Gray,
+                ChannelCount::Rgb | ChannelCount::Rgba => ChannelCount::Rgb,
+            };
+            ColorFormat::from((channel_count, ChannelSize::_8bit, ChannelType::UInt))
+        }
+        // packing keeps the color format
+        "png" if options.png.pack_range => color_format,
+        "png" => {
+            let channel_size = match color_format.channel_size {
+                ChannelSize::_8bit => ChannelSize::_8bit,
+                _ => ChannelSize::_16bit,
+            };
+            ColorFormat::from((color_format.channel_count, channel_size, ChannelType::UInt))
+        }
+        "tiff" => {
+            let predictor = match (options.tiff.predictor, color_format.channel_type) {
+                (TiffPredictor::Horizontal, ChannelType::Float) => TiffPredictor::FloatingPoint,
+                (TiffPredictor::FloatingPoint, ChannelType::UInt | ChannelType::Int) => {
+                    TiffPredictor::Horizontal
+                }
+                (predictor, _) => predictor,
+            };
+            if predictor != options.tiff.predictor {
+                adaptations.push(Adaptation::ChangedPredictor {
+                    from: options.tiff.predictor,
+                    to: predictor,
+                });
+                options.tiff.predictor = predictor;
+            }
+            // every valid color format can be written
+            color_format
+        }
+        // integers other than 32 bit unsigned are normalized to floats
+        "exr" => match (color_format.channel_type, color_format.channel_size) {
+            (ChannelType::UInt, ChannelSize::_32bit)
+            | (ChannelType::Float, ChannelSize::_32bit) => color_format,
+            _ => ColorFormat::from((
+                color_format.channel_count,
+                ChannelSize::_32bit,
+                ChannelType::Float,
+            )),
+        },
+        // gray is written as RGB by the HDR encoder
+        "hdr" | "pfm" => {
+            let channel_count = match color_format.channel_count {
+                ChannelCount::Gray | ChannelCount::GrayAlpha => ChannelCount::Gray,
+                ChannelCount::Rgb | ChannelCount::Rgba => ChannelCount::Rgb,
+            };
+            ColorFormat::from((channel_count, ChannelSize::_32bit, ChannelType::Float))
+        }
+        "pam" => {
+            let channel_size = match color_format.channel_size {
+                ChannelSize::_8bit => ChannelSize::_8bit,
+                _ => ChannelSize::_16bit,
+            };
+            ColorFormat::from((color_format.channel_count, channel_size, ChannelType::UInt))
+        }
+        // without alpha, the writer rejects gray in PPM and RGB in PGM and PBM
+        "pbm" | "pgm" | "ppm" | "pnm" => {
+            let channel_count = match color_format.channel_count {
+                ChannelCount::Gray | ChannelCount::GrayAlpha => ChannelCount::Gray,
+                ChannelCount::Rgb | ChannelCount::Rgba => ChannelCount::Rgb,
+            };
+            let channel_size = match color_format.channel_size {
+                ChannelSize::_8bit => ChannelSize::_8bit,
+                _ if extension == "pbm" => ChannelSize::_8bit,
+                _ => ChannelSize::_16bit,
+            };
+            ColorFormat::from((channel_count, channel_size, ChannelType::UInt))
+        }
+        "qoi" => {
+            let channel_count = match color_format.channel_count {
+                ChannelCount::Gray | ChannelCount::Rgb => ChannelCount::Rgb,
+                ChannelCount::GrayAlpha | ChannelCount::Rgba => ChannelCount::Rgba,
+            };
+            ColorFormat::from((channel_count, ChannelSize::_8bit, ChannelType::UInt))
+        }
+        // DDS has no 8 and 16 bit RGB formats
+        "dds" | "ktx2" => {
+            let channel_size = match color_format.channel_size {
+                ChannelSize::_64bit => ChannelSize::_32bit,
+                channel_size => channel_size,
+            };
+            let channel_count = match color_format.channel_count {
+                ChannelCount::Rgb if extension == "dds" && channel_size != ChannelSize::_32bit => {
+                    ChannelCount::Rgba
+                }
+                channel_count => channel_count,
+            };
+            ColorFormat::from((channel_count, channel_size, color_format.channel_type))
+        }
+        "bmp" | "tga" | "ico" | "cur" | "webp" | "gif" => ColorFormat::from((
+            color_format.channel_count,
+            ChannelSize::_8bit,
+            ChannelType::UInt,
+        )),
+
+        _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
+    };
+
+    if target == color_format {
+        return Ok((Cow::Borrowed(image), options, adaptations));
+    }
+
+    let mut adapted = image.clone();
+    if target.channel_type == ChannelType::Float {
+        match color_format.channel_type {
+            ChannelType::Float
+                if target.channel_size.byte_count() < color_format.channel_size.byte_count() =>
+            {
+                adaptations.push(Adaptation::ReducedDepth {
+                    from: color_format.channel_size,
+                    to: target.channel_size,
+                })
+            }
+            // widening half floats loses nothing
+            ChannelType::Float => {}
+            _ => adaptations.push(Adaptation::ConvertedToFloat),
+        }
+    } else {
+        match color_format.channel_type {
+            ChannelType::Float => adaptations.push(Adaptation::NormalizedFloat {
+                to: target.channel_size,
+            }),
+            ChannelType::Int if target.channel_type == ChannelType::UInt => {
+                adaptations.push(Adaptation::Unsigned);
+                // offset first, flattening goes through normalized floats
+                adapted = adapted.convert(ColorFormat::from((
+                    color_format.channel_count,
+                    color_format.channel_size,
+                    ChannelType::UInt,
+                )))?;
+            }
+            ChannelType::Int | ChannelType::UInt => {}
+        }
+        if color_format.channel_type != ChannelType::Float
+            && target.channel_size.byte_count() < color_format.channel_size.byte_count()
+        {
+            adaptations.push(Adaptation::ReducedDepth {
+                from: color_format.channel_size,
+                to: target.channel_size,
+            });
+        }
+    }
+    let has_alpha = |count| matches!(count, ChannelCount::GrayAlpha | ChannelCount::Rgba);
+    let is_gray = |count| matches!(count, ChannelCount::Gray | ChannelCount::GrayAlpha);
+    if has_alpha(color_format.channel_count) && !has_alpha(target.channel_count) {
+        adaptations.push(Adaptation::FlattenedAlpha);
+        adapted = flatten_alpha(adapted, target.channel_count)?;
+    }
+    if !has_alpha(color_format.channel_count) && has_alpha(target.channel_count) {
+        adaptations.push(Adaptation::AddedAlpha);
+    }
+    // the final conversion replicates the gray channel
+    if is_gray(color_format.channel_count) && !is_gray(target.channel_count) {
+        adaptations.push(Adaptation::ExpandedGray);
+    }
+
+    Ok((Cow::Owned(adapted.convert(target)?), options, adaptations))
+}
+
+/// Composites the colors over white and removes the alpha channel, through 32 bit floats.
+fn flatten_alpha(image: Image, channel_count: ChannelCount) -> anyhow::Result<Image> {
+    let from_count = image.desc.color_format().channel_count;
+    let layout = image.desc.layout();
+
+    let image = image
+        .convert_layout(Layout::Interleaved)?
+        .convert(ColorFormat::from((
+            from_count,
+            ChannelSize::_32bit,
+            ChannelType::Float,
+        )))?;
+    let mut result = image.clone().convert(ColorFormat::from((
+        channel_count,
+        ChannelSize::_32bit,
+        ChannelType::Float,
+    )))?;
+    composite_over_white(&image, &mut result);
+
+    result.convert_layout(layout)
+}
+
+fn composite_over_white(from: &Image, to: &mut Image) {
+    let from_channels = from.desc.color_format().channel_count.channel_count() as usize;
+    let to_channels = to.desc.color_format().channel_count.channel_count() as usize;
+    assert_eq!(from_channels, to_channels + 1);
+
+    let from_pixels: &[f32] = bytemuck::cast_slice(&from.bytes);
+    let to_pixels: &mut [f32] = bytemuck::cast_slice_mut(&mut to.bytes);
+    for (from_pixel, to_pixel) in from_pixels
+        .chunks_exact(from_channels)
+        .zip(to_pixels.chunks_exact_mut(to_channels))
+    {
+        let alpha = from_pixel[to_channels].clamp(0.0, 1.0);
+        for (to_value, from_value) in to_pixel.iter_mut().zip(from_pixel) {
+            *to_value = from_value * alpha + (1.0 - alpha);
+        }
+    }
+}
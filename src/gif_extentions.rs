@@ -0,0 +1,143 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::Duration;
+
+use ::image::codecs::gif::GifEncoder;
+use ::image::ExtendedColorType;
+
+use crate::animation::{Animation, Blend, Compositor, Dispose, Frame, FrameRect};
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::save_options::GifOptions;
+
+/// Writes an 8 bit image as a GIF, quantized to a palette of at most 256 colors.
+/// Pixels with an alpha below half are transparent.
+pub(crate) fn save_gif<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &GifOptions,
+) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format.channel_size != ChannelSize::_8bit
+        || color_format.channel_type != ChannelType::UInt
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported GIF color format: {}",
+            color_format
+        ));
+    }
+    if !(1..=30).contains(&options.speed) {
+        return Err(anyhow::anyhow!(
+            "GIF quantization speed must be between 1 and 30, got {}",
+            options.speed
+        ));
+    }
+
+    // the quantizer takes RGBA pixels
+    let image = image
+        .clone()
+        .convert(ColorFormat::RGBA_U8)?
+        .convert_layout(Layout::Interleaved)?;
+    let file = BufWriter::new(File::create(filename)?);
+    GifEncoder::new_with_speed(file, options.speed as i32).encode(
+        &image.bytes[..image.desc.size_in_bytes() as usize],
+        image.desc.width(),
+        image.desc.height(),
+        ExtendedColorType::Rgba8,
+    )?;
+
+    Ok(())
+}
+
+/// Reads the frames of a GIF composited on the canvas, transparent pixels showing
+/// the previous frames.
+pub(crate) fn load_gif_animation<P: AsRef<Path>>(filename: P) -> anyhow::Result<Animation> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(BufReader::new(File::open(filename)?))?;
+
+    let mut compositor = Compositor::new(decoder.width() as u32, decoder.height() as u32)?;
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        let rect = FrameRect {
+            x: frame.left as u32,
+            y: frame.top as u32,
+            width: frame.width as u32,
+            height: frame.height as u32,
+        };
+        let dispose = match frame.dispose {
+            // @formatter:off
+            gif::DisposalMethod::Any | gif::DisposalMethod::Keep => Dispose::None,
+            gif::DisposalMethod::Background => Dispose::Background,
+            gif::DisposalMethod::Previous => Dispose::Previous,
+            // @formatter:on
+        };
+        // the delay counts hundredths of a second
+        let delay = Duration::from_millis(frame.delay as u64 * 10);
+
+        frames.push(Frame::new(
+            compositor.compose(&frame.buffer, rect, Blend::Over, dispose)?,
+            delay,
+        ));
+    }
+
+    // the repetitions follow the first play
+    let loop_count = match decoder.repeat() {
+        gif::Repeat::Infinite => 0,
+        gif::Repeat::Finite(repetitions) => repetitions as u32 + 1,
+    };
+
+    Ok(Animation::new(frames, loop_count))
+}
+
+/// Writes the frames as an animated GIF, each quantized to its own palette. Frames
+/// are drawn over the previous ones whatever their blending, delays are rounded
+/// down to hundredths of a second.
+pub(crate) fn save_gif_animation<P: AsRef<Path>>(
+    animation: &Animation,
+    filename: P,
+    options: &GifOptions,
+) -> anyhow::Result<()> {
+    if !(1..=30).contains(&options.speed) {
+        return Err(anyhow::anyhow!(
+            "GIF quantization speed must be between 1 and 30, got {}",
+            options.speed
+        ));
+    }
+    let ((width, height), images) = animation.rgba_frames()?;
+    let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "GIF dimensions are limited to 65535, got {}x{}",
+                width,
+                height
+            ))
+        }
+    };
+
+    let file = BufWriter::new(File::create(filename)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+    encoder.set_repeat(match animation.loop_count {
+        0 => gif::Repeat::Infinite,
+        plays => gif::Repeat::Finite(u16::try_from(plays - 1).unwrap_or(u16::MAX)),
+    })?;
+
+    for (frame, image) in animation.frames.iter().zip(images) {
+        let mut pixels = image.bytes;
+        let mut gif_frame =
+            gif::Frame::from_rgba_speed(width, height, &mut pixels, options.speed as i32);
+        gif_frame.delay = u16::try_from(frame.delay.as_millis() / 10).unwrap_or(u16::MAX);
+        gif_frame.dispose = match frame.dispose {
+            // @formatter:off
+            Dispose::None => gif::DisposalMethod::Keep,
+            Dispose::Background => gif::DisposalMethod::Background,
+            Dispose::Previous => gif::DisposalMethod::Previous,
+            // @formatter:on
+        };
+        encoder.write_frame(&gif_frame)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,384 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{checked_image_size, Image, ImageDesc, Layout};
+
+/// Scanlines of this width range can be run-length encoded per component.
+const RLE_WIDTHS: std::ops::Range<u32> = 8..0x8000;
+
+/// Reads a Radiance RGBE file as linear 32 bit float RGB, divided by its exposure.
+pub(crate) fn load_hdr<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    let data = std::fs::read(filename)?;
+    let mut position = 0;
+    let mut next_line = || {
+        let end = data[position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(anyhow::anyhow!("Truncated HDR header"))?;
+        let line = String::from_utf8_lossy(&data[position..position + end]).into_owned();
+        position += end + 1;
+        anyhow::Ok(line)
+    };
+
+    let magic = next_line()?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(anyhow::anyhow!("Not a Radiance HDR file"));
+    }
+    let mut exposure = 1.0f32;
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(anyhow::anyhow!("Unsupported HDR format: {}", format));
+            }
+        } else if let Some(value) = line.strip_prefix("EXPOSURE=") {
+            exposure *= value.trim().parse::<f32>()?;
+        }
+    }
+
+    // only the standard orientations, rows from the top or from the bottom
+    let resolution = next_line()?;
+    let (bottom_up, height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (false, height.parse::<u32>()?, width.parse::<u32>()?),
+        ["+Y", height, "+X", width] => (true, height.parse::<u32>()?, width.parse::<u32>()?),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported HDR orientation: {}",
+                resolution
+            ))
+        }
+    };
+
+    let color_format = ColorFormat::RGB_F32;
+    checked_image_size(width, height, color_format.byte_count() as usize)
+        .ok_or(anyhow::anyhow!("Invalid HDR size: {}x{}", width, height))?;
+
+    let desc = ImageDesc::new(width, height, color_format)
+        .with_transfer_function(TransferFunction::Linear);
+    // grown per decoded scanline, so truncated files fail before the header size is allocated
+    let mut pixels = Vec::new();
+    let mut data = &data[position..];
+    let mut scanline = vec![[0u8; 4]; width as usize];
+    for _ in 0..height {
+        data = read_scanline(data, &mut scanline)?;
+
+        pixels.extend(
+            scanline
+                .iter()
+                .flat_map(|&rgbe| rgbe_to_rgb(rgbe).map(|value| value / exposure)),
+        );
+    }
+    if bottom_up {
+        pixels = pixels
+            .chunks_exact(width as usize * 3)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+    }
+
+    Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec())
+}
+
+/// Decodes a flat, old run-length or per component run-length encoded scanline,
+/// returning the remaining data.
+fn read_scanline<'a>(mut data: &'a [u8], scanline: &mut [[u8; 4]]) -> anyhow::Result<&'a [u8]> {
+    let truncated = || anyhow::anyhow!("Truncated HDR pixels");
+    let width = scanline.len();
+
+    if RLE_WIDTHS.contains(&(width as u32))
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && data[2] & 0x80 == 0
+    {
+        if ((data[2] as usize) << 8 | data[3] as usize) != width {
+            return Err(anyhow::anyhow!("HDR scanline width mismatch"));
+        }
+        data = &data[4..];
+
+        for component in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let (&count, rest) = data.split_first().ok_or_else(truncated)?;
+                data = rest;
+                if count > 128 {
+                    let count = (count - 128) as usize;
+                    let (&value, rest) = data.split_first().ok_or_else(truncated)?;
+                    data = rest;
+                    if x + count > width {
+                        return Err(anyhow::anyhow!("HDR run overflows its scanline"));
+                    }
+                    scanline[x..x + count]
+                        .iter_mut()
+                        .for_each(|pixel| pixel[component] = value);
+                    x += count;
+                } else {
+                    let count = count as usize;
+                    if count == 0 || x + count > width {
+                        return Err(anyhow::anyhow!("Invalid HDR run length"));
+                    }
+                    let values = data.get(..count).ok_or_else(truncated)?;
+                    data = &data[count..];
+                    for (pixel, &value) in scanline[x..x + count].iter_mut().zip(values) {
+                        pixel[component] = value;
+                    }
+                    x += count;
+                }
+            }
+        }
+
+        return Ok(data);
+    }
+
+    // 1, 1, 1 pixels repeat the previous one, shifted counts for consecutive repeats
+    let mut x = 0;
+    let mut shift = 0;
+    while x < width {
+        let pixel: [u8; 4] = data
+            .get(..4)
+            .ok_or_else(truncated)?
+            .try_into()
+            .expect("slice of 4 bytes");
+        data = &data[4..];
+
+        if pixel[..3] == [1, 1, 1] && x > 0 {
+            let count = (pixel[3] as usize)
+                .checked_shl(shift)
+                .filter(|count| x + count <= width)
+                .ok_or(anyhow::anyhow!("HDR run overflows its scanline"))?;
+            let previous = scanline[x - 1];
+            scanline[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+    }
+
+    Ok(data)
+}
+
+fn rgbe_to_rgb([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+    if e == 0 {
+        return [0.0; 3];
+    }
+    // the middle of the quantization interval
+    let scale = 2.0f32.powi(e as i32 - (128 + 8));
+    [r, g, b].map(|value| (value as f32 + 0.5) * scale)
+}
+
+fn rgb_to_rgbe(rgb: [f32; 3]) -> [u8; 4] {
+    let max = rgb.iter().fold(0.0f32, |max, &value| max.max(value));
+    // NaN components are ignored by the maximum
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    let max = max.min(f32::MAX);
+    // max = mantissa * 2^exponent with the mantissa in [0.5, 1)
+    let mut exponent = max.log2().floor() as i32 + 1;
+    if max / 2.0f32.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2.0f32.powi(exponent);
+    let [r, g, b] = rgb.map(|value| (value.max(0.0) * scale).min(255.0) as u8);
+
+    [r, g, b, (exponent + 128).clamp(0, 255) as u8]
+}
+
+/// Writes a 32 bit float gray or RGB image as a Radiance RGBE file, run-length
+/// encoded per component when the width allows it. Gray is written as RGB.
+pub(crate) fn save_hdr<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+    let pixels = float_pixels(image, "HDR")?;
+    let (width, height) = (image.desc.width(), image.desc.height());
+    let channels = image.desc.color_format().channel_count.channel_count() as usize;
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let mut scanline = Vec::with_capacity(width as usize);
+    for row in pixels.chunks_exact(width as usize * channels) {
+        scanline.clear();
+        scanline.extend(row.chunks_exact(channels).map(|pixel| match pixel {
+            [gray] => rgb_to_rgbe([*gray; 3]),
+            _ => rgb_to_rgbe([pixel[0], pixel[1], pixel[2]]),
+        }));
+
+        if RLE_WIDTHS.contains(&width) {
+            writer.write_all(&[2, 2, (width >> 8) as u8, width as u8])?;
+            for component in 0..4 {
+                let values: Vec<u8> = scanline.iter().map(|pixel| pixel[component]).collect();
+                write_runs(&mut writer, &values)?;
+            }
+        } else {
+            writer.write_all(bytemuck::cast_slice(&scanline))?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes runs of at least 4 equal values, up to 127, and literals up to 128 values.
+fn write_runs<W: Write>(writer: &mut W, values: &[u8]) -> anyhow::Result<()> {
+    let run_length = |start: usize| {
+        values[start..]
+            .iter()
+            .take(127)
+            .take_while(|&&value| value == values[start])
+            .count()
+    };
+
+    let mut x = 0;
+    while x < values.len() {
+        let run = run_length(x);
+        if run >= 4 {
+            writer.write_all(&[128 + run as u8, values[x]])?;
+            x += run;
+            continue;
+        }
+
+        let start = x;
+        while x < values.len() && x - start < 128 && run_length(x) < 4 {
+            x += 1;
+        }
+        writer.write_all(&[(x - start) as u8])?;
+        writer.write_all(&values[start..x])?;
+    }
+
+    Ok(())
+}
+
+/// Reads a Portable Float Map, `PF` for RGB or `Pf` for gray, stored bottom-up and
+/// little endian when its scale is negative.
+pub(crate) fn load_pfm<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    let data = std::fs::read(filename)?;
+
+    // the header is three whitespace separated tokens after the magic, then a single whitespace
+    let mut position = 0;
+    let mut token = || {
+        while data.get(position).is_some_and(u8::is_ascii_whitespace) {
+            position += 1;
+        }
+        let start = position;
+        while data
+            .get(position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            position += 1;
+        }
+        if start == position {
+            return Err(anyhow::anyhow!("Truncated PFM header"));
+        }
+        Ok(String::from_utf8_lossy(&data[start..position]).into_owned())
+    };
+
+    let color_format = match token()?.as_str() {
+        // @formatter:off
+        "PF" => ColorFormat::RGB_F32,
+        "Pf" => ColorFormat::GRAY_F32,
+        _ => return Err(anyhow::anyhow!("Not a PFM file")),
+        // @formatter:on
+    };
+    let width: u32 = token()?.parse()?;
+    let height: u32 = token()?.parse()?;
+    let scale: f32 = token()?.parse()?;
+    let little_endian = scale < 0.0;
+    position += 1;
+
+    let row_size = width as usize * color_format.byte_count() as usize;
+    let size = checked_image_size(width, height, color_format.byte_count() as usize)
+        .ok_or(anyhow::anyhow!("Invalid PFM size: {}x{}", width, height))?;
+
+    let desc = ImageDesc::new(width, height, color_format)
+        .with_transfer_function(TransferFunction::Linear);
+    let raster = data
+        .get(position..position + size)
+        .ok_or(anyhow::anyhow!("Truncated PFM pixels"))?;
+
+    let mut bytes = Vec::with_capacity(raster.len());
+    for row in raster.chunks_exact(row_size).rev() {
+        bytes.extend(row.chunks_exact(4).flat_map(|sample| {
+            let sample = sample.try_into().expect("slice of 4 bytes");
+            let value = if little_endian {
+                f32::from_le_bytes(sample)
+            } else {
+                f32::from_be_bytes(sample)
+            };
+            value.to_ne_bytes()
+        }));
+    }
+
+    Image::new_with_data(desc, bytes)
+}
+
+/// Writes a 32 bit float gray or RGB image as a little endian Portable Float Map.
+pub(crate) fn save_pfm<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+    let pixels = float_pixels(image, "PFM")?;
+    let color_format = image.desc.color_format();
+    let magic = match color_format.channel_count {
+        ChannelCount::Gray => "Pf",
+        _ => "PF",
+    };
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    write!(
+        writer,
+        "{}\n{} {}\n-1.0\n",
+        magic,
+        image.desc.width(),
+        image.desc.height()
+    )?;
+    let row_length = (image.desc.width() * color_format.channel_count.channel_count()) as usize;
+    for row in pixels.chunks_exact(row_length).rev() {
+        for value in row {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Returns the samples of an interleaved gray or RGB 32 bit float image.
+fn float_pixels(image: &Image, format: &str) -> anyhow::Result<Vec<f32>> {
+    let color_format = image.desc.color_format();
+    if !matches!(
+        color_format.channel_count,
+        ChannelCount::Gray | ChannelCount::Rgb
+    ) || color_format.channel_size != ChannelSize::_32bit
+        || color_format.channel_type != ChannelType::Float
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported {} color format: {}",
+            format,
+            color_format
+        ));
+    }
+
+    if image.desc.width() == 0 || image.desc.height() == 0 {
+        return Err(anyhow::anyhow!(
+            "Cannot save an empty {}x{} image as {}",
+            image.desc.width(),
+            image.desc.height(),
+            format
+        ));
+    }
+
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    let size = image.desc.size_in_bytes() as usize;
+
+    Ok(bytemuck::pod_collect_to_vec(&image.bytes[..size]))
+}
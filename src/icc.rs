@@ -0,0 +1,565 @@
+use crate::color_format::*;
+use crate::color_primaries::{ColorPrimaries, TransferFunction};
+use crate::color_space::{
+    bradford_adaptation, convert_pixels, inverse, mul, mul_mat, Mat3, WhitePoint,
+};
+use crate::image::Image;
+
+/// D50 illuminant of the ICC profile connection space.
+const PCS_WHITE: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+const HEADER_SIZE: usize = 128;
+/// Sample count of the tables used to invert non-gamma curves.
+const INVERSE_TABLE_SIZE: usize = 4096;
+/// Entry count of the `curv` tables written for non-gamma curves.
+const CURVE_TABLE_SIZE: usize = 1024;
+
+/// Tone reproduction curve of one channel of an ICC profile,
+/// converting encoded values into linear light.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ToneCurve {
+    Gamma(f64),
+    /// Uniformly sampled curve with values in `[0, 1]`.
+    Table(Vec<f64>),
+    /// ICC parametric curve of the given function type (0 to 4) with its `g, a, b, c, d, e, f` parameters.
+    Parametric(u16, [f64; 7]),
+}
+
+/// RGB matrix/TRC ICC profile.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IccProfile {
+    description: String,
+    white_point: [f64; 3],
+    to_xyz: Mat3,
+    curves: [ToneCurve; 3],
+}
+
+impl ToneCurve {
+    /// Converts an encoded value into linear light.
+    pub fn eval(&self, x: f64) -> f64 {
+        match self {
+            ToneCurve::Gamma(g) => x.max(0.0).powf(*g),
+            ToneCurve::Table(table) => match table.len() {
+                0 => x,
+                1 => table[0],
+                n => {
+                    let pos = x.clamp(0.0, 1.0) * (n - 1) as f64;
+                    let i = (pos as usize).min(n - 2);
+                    let t = pos - i as f64;
+
+                    table[i] + (table[i + 1] - table[i]) * t
+                }
+            },
+            ToneCurve::Parametric(kind, [g, a, b, c, d, e, f]) => {
+                let x = x.max(0.0);
+                match kind {
+                    0 => x.powf(*g),
+                    1 => {
+                        if x >= -b / a {
+                            (a * x + b).powf(*g)
+                        } else {
+                            0.0
+                        }
+                    }
+                    2 => {
+                        if x >= -b / a {
+                            (a * x + b).powf(*g) + c
+                        } else {
+                            *c
+                        }
+                    }
+                    3 => {
+                        if x >= *d {
+                            (a * x + b).powf(*g)
+                        } else {
+                            c * x
+                        }
+                    }
+                    _ => {
+                        if x >= *d {
+                            (a * x + b).powf(*g) + e
+                        } else {
+                            c * x + f
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn from_transfer_function(transfer_function: TransferFunction) -> ToneCurve {
+        match transfer_function {
+            TransferFunction::Srgb => ToneCurve::Parametric(
+                3,
+                [
+                    2.4,
+                    1.0 / 1.055,
+                    0.055 / 1.055,
+                    1.0 / 12.92,
+                    0.04045,
+                    0.0,
+                    0.0,
+                ],
+            ),
+            TransferFunction::Linear => ToneCurve::Gamma(1.0),
+            TransferFunction::Bt709 => ToneCurve::Parametric(
+                3,
+                [
+                    1.0 / 0.45,
+                    1.0 / 1.099,
+                    0.099 / 1.099,
+                    1.0 / 4.5,
+                    0.081,
+                    0.0,
+                    0.0,
+                ],
+            ),
+            TransferFunction::Gamma22 => ToneCurve::Gamma(563.0 / 256.0),
+        }
+    }
+}
+
+impl IccProfile {
+    /// Parses an RGB matrix/TRC profile, LUT based profiles are not supported.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<IccProfile> {
+        if bytes.len() < HEADER_SIZE + 4 || &bytes[36..40] != b"acsp" {
+            return Err(anyhow::anyhow!("Invalid ICC profile"));
+        }
+        if &bytes[16..20] != b"RGB " {
+            return Err(anyhow::anyhow!(
+                "Unsupported ICC profile color space: {}",
+                String::from_utf8_lossy(&bytes[16..20])
+            ));
+        }
+
+        let find_tag = |signature: &[u8; 4]| -> Option<&[u8]> {
+            // the tag table can't extend past the profile
+            let tag_count = (read_u32(bytes, HEADER_SIZE)? as usize)
+                .min((bytes.len() - HEADER_SIZE - 4) / 12);
+            (0..tag_count).find_map(|i| {
+                let entry = HEADER_SIZE + 4 + i * 12;
+                if bytes.get(entry..entry + 4)? != signature {
+                    return None;
+                }
+                let offset = read_u32(bytes, entry + 4)? as usize;
+                let size = read_u32(bytes, entry + 8)? as usize;
+                bytes.get(offset..offset.checked_add(size)?)
+            })
+        };
+        let required_tag = |signature: &[u8; 4]| {
+            find_tag(signature).ok_or(anyhow::anyhow!(
+                "Unsupported ICC profile, missing {} tag, only matrix/TRC profiles are supported",
+                String::from_utf8_lossy(signature)
+            ))
+        };
+
+        let columns = [
+            parse_xyz(required_tag(b"rXYZ")?)?,
+            parse_xyz(required_tag(b"gXYZ")?)?,
+            parse_xyz(required_tag(b"bXYZ")?)?,
+        ];
+        let curves = [
+            parse_curve(required_tag(b"rTRC")?)?,
+            parse_curve(required_tag(b"gTRC")?)?,
+            parse_curve(required_tag(b"bTRC")?)?,
+        ];
+        let white_point = match find_tag(b"wtpt") {
+            Some(tag) => parse_xyz(tag)?,
+            None => PCS_WHITE,
+        };
+        let description = find_tag(b"desc")
+            .and_then(parse_description)
+            .unwrap_or_default();
+
+        Ok(IccProfile {
+            description,
+            white_point,
+            to_xyz: [
+                [columns[0][0], columns[1][0], columns[2][0]],
+                [columns[0][1], columns[1][1], columns[2][1]],
+                [columns[0][2], columns[1][2], columns[2][2]],
+            ],
+            curves,
+        })
+    }
+
+    /// Builds the profile of RGB data in the given primaries and transfer function.
+    pub fn from_primaries(
+        color_primaries: ColorPrimaries,
+        transfer_function: TransferFunction,
+    ) -> IccProfile {
+        let adaptation = bradford_adaptation(color_primaries.white_point(), WhitePoint::D50);
+        let curve = ToneCurve::from_transfer_function(transfer_function);
+
+        IccProfile {
+            description: format!("{:?} {:?}", color_primaries, transfer_function),
+            white_point: color_primaries.white_point().xyz(),
+            to_xyz: mul_mat(&adaptation, &color_primaries.to_xyz()),
+            curves: [curve.clone(), curve.clone(), curve],
+        }
+    }
+
+    pub fn srgb() -> IccProfile {
+        IccProfile::from_primaries(ColorPrimaries::Srgb, TransferFunction::Srgb)
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+    /// Media white point of the profile.
+    pub fn white_point(&self) -> [f64; 3] {
+        self.white_point
+    }
+    /// Matrix converting linear RGB into the D50 profile connection space.
+    pub fn to_xyz(&self) -> Mat3 {
+        self.to_xyz
+    }
+    pub fn curves(&self) -> &[ToneCurve; 3] {
+        &self.curves
+    }
+
+    /// Primaries and transfer function closest to the matrix and the green curve of the profile.
+    pub fn closest_primaries(&self) -> (ColorPrimaries, TransferFunction) {
+        let matrix_distance = |color_primaries: ColorPrimaries| {
+            let to_xyz =
+                IccProfile::from_primaries(color_primaries, TransferFunction::Linear).to_xyz;
+            (0..9)
+                .map(|i| (to_xyz[i / 3][i % 3] - self.to_xyz[i / 3][i % 3]).abs())
+                .sum::<f64>()
+        };
+        let curve_distance = |transfer_function: TransferFunction| {
+            let curve = ToneCurve::from_transfer_function(transfer_function);
+            (0..=16)
+                .map(|i| (curve.eval(i as f64 / 16.0) - self.curves[1].eval(i as f64 / 16.0)).abs())
+                .sum::<f64>()
+        };
+
+        let color_primaries = [
+            ColorPrimaries::Srgb,
+            ColorPrimaries::DisplayP3,
+            ColorPrimaries::Rec2020,
+            ColorPrimaries::AdobeRgb,
+            ColorPrimaries::AcesCg,
+        ]
+        .into_iter()
+        .min_by(|a, b| matrix_distance(*a).total_cmp(&matrix_distance(*b)))
+        .unwrap();
+        let transfer_function = [
+            TransferFunction::Srgb,
+            TransferFunction::Linear,
+            TransferFunction::Bt709,
+            TransferFunction::Gamma22,
+        ]
+        .into_iter()
+        .min_by(|a, b| curve_distance(*a).total_cmp(&curve_distance(*b)))
+        .unwrap();
+
+        (color_primaries, transfer_function)
+    }
+
+    /// Serializes the profile as an ICC v2 display profile.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let column = |i: usize| [self.to_xyz[0][i], self.to_xyz[1][i], self.to_xyz[2][i]];
+        let tags: [(&[u8; 4], Vec<u8>); 9] = [
+            (b"desc", description_tag(&self.description)),
+            (b"cprt", text_tag("No copyright, use freely")),
+            (b"wtpt", xyz_tag(self.white_point)),
+            (b"rXYZ", xyz_tag(column(0))),
+            (b"gXYZ", xyz_tag(column(1))),
+            (b"bXYZ", xyz_tag(column(2))),
+            (b"rTRC", curve_tag(&self.curves[0])),
+            (b"gTRC", curve_tag(&self.curves[1])),
+            (b"bTRC", curve_tag(&self.curves[2])),
+        ];
+
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[8..12].copy_from_slice(&0x0210_0000_u32.to_be_bytes());
+        bytes[12..16].copy_from_slice(b"mntr");
+        bytes[16..20].copy_from_slice(b"RGB ");
+        bytes[20..24].copy_from_slice(b"XYZ ");
+        bytes[36..40].copy_from_slice(b"acsp");
+        bytes[68..80].copy_from_slice(&xyz_numbers(PCS_WHITE));
+
+        bytes.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+
+        let mut offset = HEADER_SIZE + 4 + tags.len() * 12;
+        for (signature, data) in &tags {
+            bytes.extend_from_slice(*signature);
+            bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in &tags {
+            bytes.extend_from_slice(data);
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+        }
+
+        let size = bytes.len() as u32;
+        bytes[0..4].copy_from_slice(&size.to_be_bytes());
+
+        bytes
+    }
+}
+
+/// Converts the RGB channels of a float image from one profile into another,
+/// through the D50 profile connection space.
+pub(crate) fn apply_icc_profile(
+    from: &Image,
+    to: &mut Image,
+    from_profile: &IccProfile,
+    to_profile: &IccProfile,
+) -> anyhow::Result<()> {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let matrix = mul_mat(&inverse(&to_profile.to_xyz), &from_profile.to_xyz);
+    let inverse_curves = to_profile.curves.clone().map(InverseCurve::new);
+
+    let convert = |rgb: [f64; 3]| {
+        let linear = mul(
+            &matrix,
+            [
+                from_profile.curves[0].eval(rgb[0]),
+                from_profile.curves[1].eval(rgb[1]),
+                from_profile.curves[2].eval(rgb[2]),
+            ],
+        );
+
+        [
+            inverse_curves[0].eval(linear[0]),
+            inverse_curves[1].eval(linear[1]),
+            inverse_curves[2].eval(linear[2]),
+        ]
+    };
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            convert_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, convert)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            convert_pixels::<f64>(from, to, |v| v, |v| v, convert)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "ICC profile conversion requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Inverse of a tone curve, gamma and parametric curves are inverted exactly
+/// (values above one included), tables through a sampled monotonic table.
+enum InverseCurve {
+    Gamma(f64),
+    Parametric(u16, [f64; 7]),
+    Sampled(Vec<f64>),
+}
+
+impl InverseCurve {
+    fn new(curve: ToneCurve) -> InverseCurve {
+        match curve {
+            ToneCurve::Gamma(g) => InverseCurve::Gamma(g),
+            ToneCurve::Parametric(kind, params) => InverseCurve::Parametric(kind, params),
+            ToneCurve::Table(_) => InverseCurve::Sampled(
+                (0..INVERSE_TABLE_SIZE)
+                    .map(|i| curve.eval(i as f64 / (INVERSE_TABLE_SIZE - 1) as f64))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn eval(&self, y: f64) -> f64 {
+        match self {
+            InverseCurve::Gamma(g) => y.max(0.0).powf(1.0 / g),
+            InverseCurve::Parametric(kind, [g, a, b, c, d, e, f]) => {
+                let root = |v: f64| v.max(0.0).powf(1.0 / g);
+                match kind {
+                    0 => root(y),
+                    1 => (root(y) - b) / a,
+                    2 => (root(y - c) - b) / a,
+                    3 if y >= c * d => (root(y) - b) / a,
+                    3 => y / c,
+                    _ if y >= c * d + f => (root(y - e) - b) / a,
+                    _ => (y - f) / c,
+                }
+            }
+            InverseCurve::Sampled(samples) => {
+                let last = samples.len() - 1;
+                if y <= samples[0] {
+                    return 0.0;
+                }
+                if y >= samples[last] {
+                    return 1.0;
+                }
+
+                let i = samples.partition_point(|&s| s <= y).clamp(1, last);
+                let (y0, y1) = (samples[i - 1], samples[i]);
+                let t = if y1 > y0 { (y - y0) / (y1 - y0) } else { 0.0 };
+
+                (i as f64 - 1.0 + t) / last as f64
+            }
+        }
+    }
+}
+
+/// Returns whether the color space of a raw ICC profile matches images with the given channel count.
+pub(crate) fn profile_matches(bytes: &[u8], channel_count: ChannelCount) -> bool {
+    match bytes.get(16..20) {
+        Some(b"GRAY") => matches!(channel_count, ChannelCount::Gray | ChannelCount::GrayAlpha),
+        Some(b"RGB ") => matches!(channel_count, ChannelCount::Rgb | ChannelCount::Rgba),
+        _ => false,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_s15_fixed16(bytes: &[u8], offset: usize) -> Option<f64> {
+    Some(read_u32(bytes, offset)? as i32 as f64 / 65536.0)
+}
+
+fn parse_xyz(tag: &[u8]) -> anyhow::Result<[f64; 3]> {
+    let invalid = || anyhow::anyhow!("Invalid ICC XYZ tag");
+    if tag.get(0..4) != Some(b"XYZ ") {
+        return Err(invalid());
+    }
+
+    Ok([
+        read_s15_fixed16(tag, 8).ok_or_else(invalid)?,
+        read_s15_fixed16(tag, 12).ok_or_else(invalid)?,
+        read_s15_fixed16(tag, 16).ok_or_else(invalid)?,
+    ])
+}
+
+fn parse_curve(tag: &[u8]) -> anyhow::Result<ToneCurve> {
+    let invalid = || anyhow::anyhow!("Invalid ICC curve tag");
+
+    match tag.get(0..4) {
+        Some(b"curv") => {
+            let count = read_u32(tag, 8).ok_or_else(invalid)? as usize;
+            let entries = tag.get(12..12 + count * 2).ok_or_else(invalid)?;
+            let values: Vec<u16> = entries
+                .chunks_exact(2)
+                .map(|e| u16::from_be_bytes([e[0], e[1]]))
+                .collect();
+
+            Ok(match values.as_slice() {
+                [] => ToneCurve::Gamma(1.0),
+                [gamma] => ToneCurve::Gamma(*gamma as f64 / 256.0),
+                _ => ToneCurve::Table(values.iter().map(|&v| v as f64 / 65535.0).collect()),
+            })
+        }
+        Some(b"para") => {
+            let kind = tag
+                .get(8..10)
+                .map(|k| u16::from_be_bytes([k[0], k[1]]))
+                .ok_or_else(invalid)?;
+            let param_count = match kind {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unsupported ICC parametric curve: {}",
+                        kind
+                    ))
+                }
+            };
+
+            let mut params = [0.0; 7];
+            for (i, param) in params.iter_mut().take(param_count).enumerate() {
+                *param = read_s15_fixed16(tag, 12 + i * 4).ok_or_else(invalid)?;
+            }
+
+            Ok(ToneCurve::Parametric(kind, params))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_description(tag: &[u8]) -> Option<String> {
+    match tag.get(0..4)? {
+        b"desc" => {
+            let count = read_u32(tag, 8)? as usize;
+            let text = tag.get(12..12 + count)?;
+            Some(
+                String::from_utf8_lossy(text)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            )
+        }
+        b"mluc" => {
+            // first record of the localized strings
+            let length = read_u32(tag, 20)? as usize;
+            let offset = read_u32(tag, 24)? as usize;
+            let text: Vec<u16> = tag
+                .get(offset..offset + length)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&text))
+        }
+        _ => None,
+    }
+}
+
+fn xyz_numbers(xyz: [f64; 3]) -> Vec<u8> {
+    xyz.iter()
+        .flat_map(|v| ((v * 65536.0).round() as i32).to_be_bytes())
+        .collect()
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    tag.extend(xyz_numbers(xyz));
+    tag
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut tag = b"text\0\0\0\0".to_vec();
+    tag.extend_from_slice(text.as_bytes());
+    tag.push(0);
+    tag
+}
+
+fn description_tag(text: &str) -> Vec<u8> {
+    let ascii: Vec<u8> = text.bytes().filter(u8::is_ascii).chain([0]).collect();
+
+    let mut tag = b"desc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(ascii.len() as u32).to_be_bytes());
+    tag.extend_from_slice(&ascii);
+    // empty unicode and script code descriptions
+    tag.extend_from_slice(&[0; 8]);
+    tag.extend_from_slice(&[0; 3]);
+    tag.extend_from_slice(&[0; 67]);
+    tag
+}
+
+fn curve_tag(curve: &ToneCurve) -> Vec<u8> {
+    let values: Vec<u16> = match curve {
+        ToneCurve::Gamma(g) => vec![(g * 256.0).round() as u16],
+        curve => (0..CURVE_TABLE_SIZE)
+            .map(|i| {
+                let v = curve.eval(i as f64 / (CURVE_TABLE_SIZE - 1) as f64);
+                (v.clamp(0.0, 1.0) * 65535.0).round() as u16
+            })
+            .collect(),
+    };
+
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(values.len() as u32).to_be_bytes());
+    tag.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+    tag
+}
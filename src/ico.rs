@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+
+use ::image::codecs::ico::IcoDecoder;
+use ::image::codecs::png::PngEncoder;
+use ::image::{ExtendedColorType, ImageEncoder};
+
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+
+const ICO_TYPE: u16 = 1;
+const CUR_TYPE: u16 = 2;
+const HEADER_SIZE: usize = 6;
+const ENTRY_SIZE: usize = 16;
+/// Largest width and height of an entry, stored as 0.
+const MAX_SIZE: u32 = 256;
+
+/// Image of an ICO or CUR file, one per resolution.
+#[derive(Clone)]
+pub struct IconEntry {
+    pub image: Image,
+    /// Position of the pointer in a cursor, from the top left corner.
+    pub hotspot: Option<(u16, u16)>,
+}
+
+impl IconEntry {
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            hotspot: None,
+        }
+    }
+    pub fn with_hotspot(mut self, x: u16, y: u16) -> Self {
+        self.hotspot = Some((x, y));
+
+        self
+    }
+}
+
+/// Reads every entry of an ICO or CUR, in file order, as 8 bit RGBA with the AND mask
+/// of BMP entries applied.
+pub(crate) fn load_icon_entries<P: AsRef<Path>>(filename: P) -> anyhow::Result<Vec<IconEntry>> {
+    let data = std::fs::read(filename)?;
+    let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+
+    if data.len() < HEADER_SIZE || u16_at(0) != 0 || !matches!(u16_at(2), ICO_TYPE | CUR_TYPE) {
+        return Err(anyhow::anyhow!("Not an ICO or CUR file"));
+    }
+    let cursor = u16_at(2) == CUR_TYPE;
+    let count = u16_at(4) as usize;
+    if data.len() < HEADER_SIZE + count * ENTRY_SIZE {
+        return Err(anyhow::anyhow!("Truncated ICO directory"));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for index in 0..count {
+        let entry = &data[HEADER_SIZE + index * ENTRY_SIZE..][..ENTRY_SIZE];
+        let size = u32::from_le_bytes(entry[8..12].try_into()?) as usize;
+        let offset = u32::from_le_bytes(entry[12..16].try_into()?) as usize;
+        let payload = offset
+            .checked_add(size)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(anyhow::anyhow!("Truncated ICO entry"))?;
+
+        // a single entry icon decodes one entry at a time
+        let mut icon = Vec::with_capacity(HEADER_SIZE + ENTRY_SIZE + size);
+        icon.extend_from_slice(&[0, 0]);
+        icon.extend_from_slice(&ICO_TYPE.to_le_bytes());
+        icon.extend_from_slice(&1u16.to_le_bytes());
+        icon.extend_from_slice(&entry[..12]);
+        icon.extend_from_slice(&((HEADER_SIZE + ENTRY_SIZE) as u32).to_le_bytes());
+        icon.extend_from_slice(payload);
+
+        let image = Image::from_image_decoder(IcoDecoder::new(Cursor::new(icon))?)?;
+        let hotspot = cursor.then(|| {
+            (
+                u16::from_le_bytes([entry[4], entry[5]]),
+                u16::from_le_bytes([entry[6], entry[7]]),
+            )
+        });
+        entries.push(IconEntry { image, hotspot });
+    }
+
+    Ok(entries)
+}
+
+/// Reads the largest entry of an ICO or CUR.
+pub(crate) fn load_icon<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    load_icon_entries(filename)?
+        .into_iter()
+        .map(|entry| entry.image)
+        .max_by_key(|image| image.desc.width() as u64 * image.desc.height() as u64)
+        .ok_or(anyhow::anyhow!("ICO without entries"))
+}
+
+/// Writes 8 bit images of up to 256x256 pixels as the PNG entries of an ICO, or of a
+/// CUR with their hotspots, by default at the top left corner, when `cursor` is set.
+pub(crate) fn save_icon_entries<P: AsRef<Path>>(
+    entries: &[IconEntry],
+    filename: P,
+    cursor: bool,
+) -> anyhow::Result<()> {
+    if entries.is_empty() {
+        return Err(anyhow::anyhow!("No entry to save"));
+    }
+    let count = u16::try_from(entries.len())
+        .map_err(|_| anyhow::anyhow!("Too many ICO entries: {}", entries.len()))?;
+
+    let mut payloads = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let color_format = entry.image.desc.color_format();
+        if color_format.channel_size != ChannelSize::_8bit
+            || color_format.channel_type != ChannelType::UInt
+        {
+            return Err(anyhow::anyhow!(
+                "Unsupported ICO color format: {}",
+                color_format
+            ));
+        }
+        let (width, height) = (entry.image.desc.width(), entry.image.desc.height());
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(anyhow::anyhow!(
+                "ICO entries are limited to {}x{}, got {}x{}",
+                MAX_SIZE,
+                MAX_SIZE,
+                width,
+                height
+            ));
+        }
+
+        // PNG entries are always RGBA
+        let image = entry
+            .image
+            .clone()
+            .convert(ColorFormat::RGBA_U8)?
+            .convert_layout(Layout::Interleaved)?;
+        let mut payload = Vec::new();
+        PngEncoder::new(&mut payload).write_image(
+            &image.bytes[..image.desc.size_in_bytes() as usize],
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?;
+        payloads.push(payload);
+    }
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(&[0, 0])?;
+    writer.write_all(&(if cursor { CUR_TYPE } else { ICO_TYPE }).to_le_bytes())?;
+    writer.write_all(&count.to_le_bytes())?;
+
+    let mut offset = HEADER_SIZE + entries.len() * ENTRY_SIZE;
+    for (entry, payload) in entries.iter().zip(&payloads) {
+        // color planes and bits per pixel, or the hotspot of a cursor
+        let (first, second) = if cursor {
+            entry.hotspot.unwrap_or_default()
+        } else {
+            (1, 32)
+        };
+        writer.write_all(&[
+            (entry.image.desc.width() % MAX_SIZE) as u8,
+            (entry.image.desc.height() % MAX_SIZE) as u8,
+            0,
+            0,
+        ])?;
+        writer.write_all(&first.to_le_bytes())?;
+        writer.write_all(&second.to_le_bytes())?;
+        writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+        writer.write_all(&(offset as u32).to_le_bytes())?;
+        offset += payload.len();
+    }
+    for payload in &payloads {
+        writer.write_all(payload)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
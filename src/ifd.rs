@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::metadata::TagValue;
+
+/// Byte order and offset size of a TIFF structured file or EXIF block.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TiffHeader {
+    pub big_endian: bool,
+    pub big_tiff: bool,
+    pub first_ifd: u64,
+}
+
+impl TiffHeader {
+    /// Sizes of the entry count, of an entry and of the offsets.
+    fn sizes(&self) -> (usize, usize, usize) {
+        if self.big_tiff {
+            (8, 20, 8)
+        } else {
+            (2, 12, 4)
+        }
+    }
+}
+
+pub(crate) fn read_header<R: Read + Seek>(reader: &mut R) -> anyhow::Result<TiffHeader> {
+    let mut header = [0u8; 16];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header[..8])?;
+
+    let big_endian = match &header[..2] {
+        b"II" => false,
+        b"MM" => true,
+        _ => return Err(anyhow::anyhow!("Invalid TIFF byte order")),
+    };
+    let big_tiff = match read_uint(&header[2..4], big_endian) {
+        42 => false,
+        43 => true,
+        version => return Err(anyhow::anyhow!("Invalid TIFF version: {}", version)),
+    };
+    let first_ifd = if big_tiff {
+        reader.read_exact(&mut header[8..16])?;
+        read_uint(&header[8..16], big_endian)
+    } else {
+        read_uint(&header[4..8], big_endian)
+    };
+
+    Ok(TiffHeader {
+        big_endian,
+        big_tiff,
+        first_ifd,
+    })
+}
+
+/// Reads the offset of the directory following the one at `offset`.
+pub(crate) fn read_next_ifd<R: Read + Seek>(
+    reader: &mut R,
+    header: &TiffHeader,
+    offset: u64,
+) -> anyhow::Result<u64> {
+    let (count_size, entry_size, offset_size) = header.sizes();
+
+    let mut bytes = [0u8; 8];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut bytes[..count_size])?;
+    let count = read_uint(&bytes[..count_size], header.big_endian);
+
+    let next_offset = count
+        .checked_mul(entry_size as u64)
+        .and_then(|size| size.checked_add(offset + count_size as u64))
+        .ok_or(anyhow::anyhow!(
+            "Invalid TIFF directory entry count: {}",
+            count
+        ))?;
+    reader.seek(SeekFrom::Start(next_offset))?;
+    reader.read_exact(&mut bytes[..offset_size])?;
+
+    Ok(read_uint(&bytes[..offset_size], header.big_endian))
+}
+
+/// Reads the tags of the directory at `offset`, skipping the ones of an unsupported type.
+pub(crate) fn read_ifd<R: Read + Seek>(
+    reader: &mut R,
+    header: &TiffHeader,
+    offset: u64,
+) -> anyhow::Result<BTreeMap<u16, TagValue>> {
+    let (count_size, entry_size, offset_size) = header.sizes();
+    let big_endian = header.big_endian;
+    // sizes read from the data are checked against its length before allocating
+    let length = reader.seek(SeekFrom::End(0))?;
+
+    let mut count = [0u8; 8];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut count[..count_size])?;
+    let count = read_uint(&count[..count_size], big_endian);
+
+    let entries_size = count
+        .checked_mul(entry_size as u64)
+        .filter(|&size| size <= length.saturating_sub(offset + count_size as u64))
+        .ok_or(anyhow::anyhow!(
+            "Invalid TIFF directory entry count: {}",
+            count
+        ))?;
+    let mut entries = vec![0u8; entries_size as usize];
+    reader.read_exact(&mut entries)?;
+
+    let mut tags = BTreeMap::new();
+    for entry in entries.chunks(entry_size) {
+        let tag = read_uint(&entry[0..2], big_endian) as u16;
+        let field_type = read_uint(&entry[2..4], big_endian) as u16;
+        let count = read_uint(&entry[4..4 + offset_size], big_endian);
+        let value_field = &entry[4 + offset_size..];
+
+        let Some(value_size) = field_type_size(field_type) else {
+            continue;
+        };
+        let size = count.checked_mul(value_size as u64).ok_or(anyhow::anyhow!(
+            "Invalid count {} of TIFF tag {}",
+            count,
+            tag
+        ))?;
+        let data = if size <= offset_size as u64 {
+            value_field[..size as usize].to_vec()
+        } else {
+            let value_offset = read_uint(value_field, big_endian);
+            if size > length.saturating_sub(value_offset) {
+                return Err(anyhow::anyhow!(
+                    "Value of TIFF tag {} lies outside of the data",
+                    tag
+                ));
+            }
+            let mut data = vec![0u8; size as usize];
+            reader.seek(SeekFrom::Start(value_offset))?;
+            reader.read_exact(&mut data)?;
+            data
+        };
+
+        let uints = |size| data.chunks(size).map(|v| read_uint(v, big_endian));
+        let value = match field_type {
+            1 => TagValue::Byte(data.clone()),
+            2 => TagValue::Ascii(
+                String::from_utf8_lossy(&data)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            3 => TagValue::Short(uints(2).map(|v| v as u16).collect()),
+            4 | 13 => TagValue::Long(uints(4).map(|v| v as u32).collect()),
+            5 => TagValue::Rational(
+                data.chunks(8)
+                    .map(|v| {
+                        [
+                            read_uint(&v[..4], big_endian) as u32,
+                            read_uint(&v[4..], big_endian) as u32,
+                        ]
+                    })
+                    .collect(),
+            ),
+            6 => TagValue::SignedByte(data.iter().map(|&v| v as i8).collect()),
+            7 => TagValue::Undefined(data.clone()),
+            8 => TagValue::SignedShort(uints(2).map(|v| v as u16 as i16).collect()),
+            9 => TagValue::SignedLong(uints(4).map(|v| v as u32 as i32).collect()),
+            10 => TagValue::SignedRational(
+                data.chunks(8)
+                    .map(|v| {
+                        [
+                            read_uint(&v[..4], big_endian) as u32 as i32,
+                            read_uint(&v[4..], big_endian) as u32 as i32,
+                        ]
+                    })
+                    .collect(),
+            ),
+            11 => TagValue::Float(uints(4).map(|v| f32::from_bits(v as u32)).collect()),
+            12 => TagValue::Double(uints(8).map(f64::from_bits).collect()),
+            _ => TagValue::Long8(uints(8).collect()),
+        };
+        tags.insert(tag, value);
+    }
+
+    Ok(tags)
+}
+
+fn field_type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        // @formatter:off
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 | 16 | 18 => Some(8),
+        _ => None,
+        // @formatter:on
+    }
+}
+
+/// Serializes a directory meant to be stored at `base`, followed by the values
+/// not fitting in its entries. The offset of the next directory is zero.
+pub(crate) fn write_ifd(
+    tags: &BTreeMap<u16, TagValue>,
+    base: u64,
+    big_endian: bool,
+    big_tiff: bool,
+) -> Vec<u8> {
+    let header = TiffHeader {
+        big_endian,
+        big_tiff,
+        first_ifd: 0,
+    };
+    let (count_size, entry_size, offset_size) = header.sizes();
+    let write_uint = |bytes: &mut Vec<u8>, value: u64, size: usize| {
+        let value = value.to_le_bytes();
+        if big_endian {
+            bytes.extend(value[..size].iter().rev());
+        } else {
+            bytes.extend_from_slice(&value[..size]);
+        }
+    };
+
+    let ifd_size = count_size + tags.len() * entry_size + offset_size;
+    let mut ifd = Vec::with_capacity(ifd_size);
+    let mut data = Vec::new();
+
+    write_uint(&mut ifd, tags.len() as u64, count_size);
+    for (&tag, value) in tags {
+        let (field_type, count, bytes) = value_bytes(value, big_endian);
+
+        write_uint(&mut ifd, tag as u64, 2);
+        write_uint(&mut ifd, field_type as u64, 2);
+        write_uint(&mut ifd, count as u64, offset_size);
+        if bytes.len() <= offset_size {
+            ifd.extend_from_slice(&bytes);
+            ifd.resize(ifd.len() + offset_size - bytes.len(), 0);
+        } else {
+            let offset = base + (ifd_size + data.len()) as u64;
+            write_uint(&mut ifd, offset, offset_size);
+            data.extend_from_slice(&bytes);
+            // values start on a word boundary
+            data.resize(data.len().next_multiple_of(2), 0);
+        }
+    }
+    write_uint(&mut ifd, 0, offset_size);
+
+    ifd.extend_from_slice(&data);
+    ifd
+}
+
+/// Field type, count and bytes of a value.
+fn value_bytes(value: &TagValue, big_endian: bool) -> (u16, usize, Vec<u8>) {
+    fn ordered<const N: usize>(values: impl Iterator<Item = [u8; N]>, big_endian: bool) -> Vec<u8> {
+        values
+            .flat_map(|mut bytes| {
+                // the values are given little endian
+                if big_endian {
+                    bytes.reverse();
+                }
+                bytes
+            })
+            .collect()
+    }
+
+    match value {
+        TagValue::Byte(values) => (1, values.len(), values.clone()),
+        TagValue::Ascii(text) => {
+            let mut bytes = text.as_bytes().to_vec();
+            bytes.push(0);
+            (2, bytes.len(), bytes)
+        }
+        TagValue::Short(values) => (
+            3,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::Long(values) => (
+            4,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::Rational(values) => (
+            5,
+            values.len(),
+            ordered(values.iter().flatten().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::SignedByte(values) => {
+            (6, values.len(), values.iter().map(|&v| v as u8).collect())
+        }
+        TagValue::Undefined(bytes) => (7, bytes.len(), bytes.clone()),
+        TagValue::SignedShort(values) => (
+            8,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::SignedLong(values) => (
+            9,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::SignedRational(values) => (
+            10,
+            values.len(),
+            ordered(values.iter().flatten().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::Float(values) => (
+            11,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::Double(values) => (
+            12,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+        TagValue::Long8(values) => (
+            16,
+            values.len(),
+            ordered(values.iter().map(|v| v.to_le_bytes()), big_endian),
+        ),
+    }
+}
+
+/// Reads an unsigned integer of up to 8 bytes.
+pub(crate) fn read_uint(bytes: &[u8], big_endian: bool) -> u64 {
+    let fold = |value: u64, &byte: &u8| (value << 8) | byte as u64;
+
+    if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    }
+}
@@ -1,12 +1,44 @@
+use std::borrow::Cow;
 use std::fs::File;
+use std::io::{BufReader, Read};
 use std::path::Path;
 
 use image as image_lib;
-use tiff::decoder::DecodingResult;
+use image_lib::ImageDecoder;
 
+use crate::bmp_extentions::save_bmp;
 use crate::color_format::*;
-use crate::image_conversion::convert_image;
-use crate::tiff_extentions::save_tiff;
+use crate::color_primaries::{convert_primaries, ColorPrimaries, GamutMapping, TransferFunction};
+use crate::color_space::{convert_color_space, ColorSpace};
+use crate::exif::{orient, Exif, Orientation};
+use crate::exr::{load_exr_layers, save_exr, save_exr_layers, ExrLayer};
+use crate::format_adaptation::adapt_for_format;
+use crate::gif_extentions::save_gif;
+use crate::hdr_extentions::{load_hdr, load_pfm, save_hdr, save_pfm};
+use crate::icc::{apply_icc_profile, profile_matches, IccProfile};
+use crate::ico::{load_icon, load_icon_entries, save_icon_entries, IconEntry};
+use crate::image_conversion::{convert_image, convert_layout};
+use crate::jpeg_extentions::save_jpeg;
+use crate::lut::{apply_lut, Lut};
+use crate::math::Rect;
+use crate::metadata::Metadata;
+use crate::png_extentions::{load_png, save_png};
+use crate::pnm_extentions::{load_pnm, save_pnm};
+use crate::pyramid::{resample, ResampleFilter};
+use crate::qoi_extentions::{load_qoi, save_qoi};
+use crate::read_options::ReadOptions;
+use crate::save_options::{
+    Adaptation, ExrOptions, GifOptions, JpegOptions, PngOptions, PnmOptions, SaveOptions,
+    TgaOptions, TiffOptions,
+};
+use crate::texture::Texture;
+use crate::tga_extentions::save_tga;
+use crate::tiff_extentions::{
+    load_tiff, load_tiff_level, load_tiff_region, open_tiff, save_tiff, save_tiff_pages,
+    save_tiff_pyramid,
+};
+use crate::tone_mapping::{tone_map, ToneMapping};
+use crate::webp_extentions::save_webp;
 
 fn align_stride(n: u32) -> u32 {
     // align to 4
@@ -22,54 +54,173 @@ fn align_stride(n: u32) -> u32 {
     n
 }
 
+/// Extensions [`Image::read_file`] reads, others are recognized by their first bytes.
+const READ_EXTENSIONS: [&str; 21] = [
+    "png", "jpeg", "jpg", "tiff", "exr", "hdr", "pfm", "pbm", "pgm", "ppm", "pnm", "pam", "bmp",
+    "tga", "ico", "cur", "webp", "gif", "qoi", "dds", "ktx2",
+];
+
+/// Returns the extension of the format whose signature starts the file.
+pub(crate) fn format_from_magic<P: AsRef<Path>>(filename: P) -> anyhow::Result<&'static str> {
+    let mut magic = Vec::with_capacity(16);
+    File::open(filename)?.take(16).read_to_end(&mut magic)?;
+
+    let format = match magic.as_slice() {
+        // @formatter:off
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xFF, 0xD8, 0xFF, ..] => "jpg",
+        [b'I', b'I', 42 | 43, 0, ..] | [b'M', b'M', 0, 42 | 43, ..] => "tiff",
+        [0x76, 0x2F, 0x31, 0x01, ..] => "exr",
+        [b'#', b'?', ..] if magic.starts_with(b"#?RADIANCE") || magic.starts_with(b"#?RGBE") => {
+            "hdr"
+        }
+        [b'P', b'F' | b'f', space, ..] if space.is_ascii_whitespace() => "pfm",
+        [b'P', b'1'..=b'7', space, ..] if space.is_ascii_whitespace() => "pnm",
+        [b'B', b'M', ..] => "bmp",
+        [0, 0, 1 | 2, 0, ..] => "ico",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+        [b'G', b'I', b'F', b'8', ..] => "gif",
+        [b'q', b'o', b'i', b'f', ..] => "qoi",
+        [b'D', b'D', b'S', b' ', ..] => "dds",
+        [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, ..] => "ktx2",
+        _ => return Err(anyhow::anyhow!("Unsupported file format")),
+        // @formatter:on
+    };
+
+    Ok(format)
+}
+
+/// Memory layout of the channels of an image.
+///
+/// `Interleaved` stores all channels of a pixel next to each other (HWC),
+/// `Planar` stores every channel in its own contiguous plane (CHW).
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Layout {
+    #[default]
+    Interleaved,
+    Planar,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct ImageDesc {
     width: u32,
     height: u32,
     stride: u32,
     color_format: ColorFormat,
+    layout: Layout,
+    color_space: ColorSpace,
+    color_primaries: ColorPrimaries,
+    transfer_function: TransferFunction,
 }
 
 #[derive(Clone)]
 pub struct Image {
     pub desc: ImageDesc,
     pub bytes: Vec<u8>,
+    /// Embedded ICC profile, read from and written to the files supporting it.
+    pub icc_profile: Option<Vec<u8>>,
+    pub metadata: Metadata,
 }
 
 impl Image {
     pub fn new_empty(desc: ImageDesc) -> anyhow::Result<Image> {
         desc.color_format().validate()?;
 
-        let bytes = vec![0; (desc.stride * desc.height) as usize];
+        let bytes = vec![0; desc.size_in_bytes() as usize];
 
-        Ok(Image { desc, bytes })
+        Ok(Image {
+            desc,
+            bytes,
+            icc_profile: None,
+            metadata: Metadata::default(),
+        })
     }
 
     pub fn new_with_data(desc: ImageDesc, bytes: Vec<u8>) -> anyhow::Result<Image> {
         desc.color_format().validate()?;
 
-        Ok(Image { desc, bytes })
+        Ok(Image {
+            desc,
+            bytes,
+            icc_profile: None,
+            metadata: Metadata::default(),
+        })
     }
 
+    /// Reads an image in the format of its extension, or of its first bytes when the
+    /// extension is missing or unknown.
     pub fn read_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
         let extension = filename
             .as_ref()
             .extension()
-            .and_then(|os_str| os_str.to_str())
-            .ok_or(anyhow::anyhow!("Failed to get file extension"))?;
+            .and_then(|os_str| os_str.to_str());
+        let format = match extension {
+            Some(extension) if READ_EXTENSIONS.contains(&extension) => extension,
+            _ => format_from_magic(&filename)?,
+        };
 
-        let image = match extension {
-            "png" | "jpeg" | "jpg" => Image::load_png_jpeg(filename)?,
+        let image = match format {
+            "png" => load_png(filename)?,
+            "jpeg" | "jpg" => Image::load_with_format(filename, image_lib::ImageFormat::Jpeg)?,
             "tiff" => Image::load_tiff(filename)?,
+            "exr" => {
+                load_exr_layers(filename)?
+                    .into_iter()
+                    .next()
+                    .ok_or(anyhow::anyhow!("EXR without channels"))?
+                    .image
+            }
+            "hdr" => load_hdr(filename)?,
+            "pfm" => load_pfm(filename)?,
+            "pbm" | "pgm" | "ppm" | "pnm" | "pam" => load_pnm(filename)?,
+            "bmp" => Image::load_with_format(filename, image_lib::ImageFormat::Bmp)?,
+            "tga" => Image::load_with_format(filename, image_lib::ImageFormat::Tga)?,
+            "ico" | "cur" => load_icon(filename)?,
+            // lossy, lossless, palette and alpha variants alike
+            "webp" => Image::load_with_format(filename, image_lib::ImageFormat::WebP)?
+                .convert(ColorFormat::RGBA_U8)?,
+            "gif" => Image::load_with_format(filename, image_lib::ImageFormat::Gif)?
+                .convert(ColorFormat::RGBA_U8)?,
+            "qoi" => load_qoi(filename)?,
+            "dds" | "ktx2" => Texture::read_file(filename)?.into_image()?,
 
-            _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
+            _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", format)),
         };
 
         Ok(image)
     }
 
-    fn load_png_jpeg<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
-        let img = image_lib::open(filename)?;
+    pub fn read_file_with_options<P: AsRef<Path>>(
+        filename: P,
+        options: &ReadOptions,
+    ) -> anyhow::Result<Image> {
+        let image = Image::read_file(filename)?;
+
+        if options.auto_orient {
+            image.auto_orient()
+        } else {
+            Ok(image)
+        }
+    }
+
+    /// Decodes a file with the `image` crate, in the given format whatever its extension.
+    fn load_with_format<P: AsRef<Path>>(
+        filename: P,
+        format: image_lib::ImageFormat,
+    ) -> anyhow::Result<Image> {
+        let reader = BufReader::new(File::open(filename)?);
+        let decoder = image_lib::ImageReader::with_format(reader, format).into_decoder()?;
+
+        Image::from_image_decoder(decoder)
+    }
+    /// Converts the image of an `image` crate decoder, with its ICC profile and EXIF metadata.
+    pub(crate) fn from_image_decoder(mut decoder: impl ImageDecoder) -> anyhow::Result<Image> {
+        let icc_profile = decoder.icc_profile()?;
+        // a malformed EXIF block does not prevent reading the pixels
+        let exif = decoder
+            .exif_metadata()?
+            .and_then(|exif| Exif::parse(&exif).ok());
+        let img = image_lib::DynamicImage::from_decoder(decoder)?;
 
         let (channel_count, channel_size, channel_type) = match img.color() {
             // @formatter:off
@@ -105,7 +256,7 @@ impl Image {
             image_lib::ColorType::Rgba32F => {
                 (ChannelCount::Rgba, ChannelSize::_32bit, ChannelType::Float)
             }
-            _ => panic!("Unsupported color type: {:?}", img.color()),
+            color_type => return Err(anyhow::anyhow!("Unsupported color type: {:?}", color_type)),
             // @formatter:on
         };
 
@@ -118,187 +269,620 @@ impl Image {
                 ColorFormat::from((channel_count, channel_size, channel_type)),
             ),
             bytes,
+            icc_profile,
+            metadata: Metadata {
+                exif,
+                ..Metadata::default()
+            },
         };
 
         Ok(image)
     }
     fn load_tiff<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
-        let mut decoder = tiff::decoder::Decoder::new(File::open(filename)?)?;
+        let (mut decoder, mut reader) = open_tiff(filename)?;
 
-        let (channel_bits, channel_count) = match decoder.colortype()? {
-            // @formatter:off
-            tiff::ColorType::Gray(b) => (b, ChannelCount::Gray),
-            tiff::ColorType::GrayA(b) => (b, ChannelCount::GrayAlpha),
-            tiff::ColorType::RGB(b) => (b, ChannelCount::Rgb),
-            tiff::ColorType::RGBA(b) => (b, ChannelCount::Rgba),
-            _ => panic!("Unsupported color type: {:?}", decoder.colortype()?),
-            // @formatter:on
-        };
+        load_tiff(&mut decoder, &mut reader, 0)
+    }
 
-        let img = decoder.read_image()?;
-        let bytes: Vec<u8> = match &img {
-            // @formatter:off
-            DecodingResult::U8(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::I8(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::U16(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::I16(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::U32(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::I32(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::U64(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::I64(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::F32(buf) => bytemuck::cast_slice(buf).to_vec(),
-            DecodingResult::F64(buf) => bytemuck::cast_slice(buf).to_vec(),
-            // @formatter:on
-        };
+    /// Reads a region of a TIFF, decoding only the strips or tiles it overlaps.
+    pub fn read_region<P: AsRef<Path>>(filename: P, rect: Rect) -> anyhow::Result<Image> {
+        let (mut decoder, mut reader) = open_tiff(filename)?;
 
-        let channel_type = match &img {
-            // @formatter:off
-            DecodingResult::U8(_) => ChannelType::UInt,
-            DecodingResult::U16(_) => ChannelType::UInt,
-            DecodingResult::U32(_) => ChannelType::UInt,
-            DecodingResult::U64(_) => ChannelType::UInt,
-            DecodingResult::I8(_) => ChannelType::Int,
-            DecodingResult::I16(_) => ChannelType::Int,
-            DecodingResult::I32(_) => ChannelType::Int,
-            DecodingResult::I64(_) => ChannelType::Int,
-            DecodingResult::F32(_) => ChannelType::Float,
-            DecodingResult::F64(_) => ChannelType::Float,
-            // @formatter:on
-        };
+        load_tiff_region(&mut decoder, &mut reader, 0, rect)
+    }
+    /// Reads every page of a multi-page TIFF.
+    pub fn read_all_pages<P: AsRef<Path>>(filename: P) -> anyhow::Result<Vec<Image>> {
+        Image::read_pages(filename)?.collect()
+    }
+    /// Reads every layer of an EXR, see [`ExrLayer`] for how channels are grouped.
+    pub fn read_exr_layers<P: AsRef<Path>>(filename: P) -> anyhow::Result<Vec<ExrLayer>> {
+        load_exr_layers(filename)
+    }
+    /// Reads every resolution of an ICO or CUR, [`Image::read_file`] reads the largest.
+    pub fn read_icon_entries<P: AsRef<Path>>(filename: P) -> anyhow::Result<Vec<IconEntry>> {
+        load_icon_entries(filename)
+    }
+    /// Returns an iterator decoding the pages of a multi-page TIFF one at a time.
+    pub fn read_pages<P: AsRef<Path>>(filename: P) -> anyhow::Result<TiffPages> {
+        let (decoder, reader) = open_tiff(filename)?;
 
-        let (w, h) = decoder.dimensions()?;
+        Ok(TiffPages {
+            decoder,
+            reader,
+            page: 0,
+            finished: false,
+        })
+    }
+    /// Reads the level of a pyramidal TIFF best suited to display it at `scale` times
+    /// its full size: the smallest level at least as large as requested.
+    /// Only the pages following the first one are searched, levels stored in SubIFDs are not read.
+    pub fn read_pyramid_level<P: AsRef<Path>>(filename: P, scale: f64) -> anyhow::Result<Image> {
+        let (mut decoder, mut reader) = open_tiff(filename)?;
 
-        let channel_size = ChannelSize::from_bit_count(channel_bits as u32);
-        let color_format = ColorFormat::from((channel_count, channel_size, channel_type));
+        load_tiff_level(&mut decoder, &mut reader, scale)
+    }
+    /// Counts the pages of a TIFF without decoding them.
+    pub fn page_count<P: AsRef<Path>>(filename: P) -> anyhow::Result<usize> {
+        let mut decoder = tiff::decoder::Decoder::new(File::open(&filename)?)?;
 
-        let image = Image {
-            desc: ImageDesc {
-                width: w,
-                height: h,
-                stride: bytes.len() as u32 / h,
-                color_format,
-            },
-            bytes,
-        };
+        let mut count = 1;
+        while decoder.more_images() {
+            decoder.next_image()?;
+            count += 1;
+        }
 
-        Ok(image)
+        Ok(count)
     }
 
     pub fn save_file<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
+        self.save_with_options(filename, &SaveOptions::default())?;
+
+        Ok(())
+    }
+    /// Saves the image with the options of the format matching the file extension.
+    ///
+    /// With [`SaveOptions::auto_adapt`], an image the format cannot hold is converted
+    /// to the nearest supported color format and the changes made are returned.
+    pub fn save_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &SaveOptions,
+    ) -> anyhow::Result<Vec<Adaptation>> {
         let extension = filename
             .as_ref()
             .extension()
             .and_then(|os_str| os_str.to_str())
             .ok_or(anyhow::anyhow!("Failed to get file extension"))?;
 
+        let (image, options, adaptations) = if options.auto_adapt {
+            adapt_for_format(self, extension, options)?
+        } else {
+            (Cow::Borrowed(self), *options, Vec::new())
+        };
+
         match extension {
-            "png" => self.save_png(filename)?,
-            "jpeg" | "jpg" => self.save_jpg(filename)?,
-            "tiff" => self.save_tiff(filename)?,
+            "png" => save_png(&image, filename, &options.png)?,
+            "jpeg" | "jpg" => save_jpeg(&image, filename, &options.jpeg)?,
+            "tiff" => save_tiff(&image, filename, &options.tiff)?,
+            "exr" => save_exr(&image, filename, &options.exr)?,
+            "hdr" => save_hdr(&image, filename)?,
+            "pfm" => save_pfm(&image, filename)?,
+            "pbm" | "pgm" | "ppm" | "pnm" | "pam" => {
+                save_pnm(&image, &filename, extension, &options.pnm)?
+            }
+            "bmp" => save_bmp(&image, filename)?,
+            "tga" => save_tga(&image, filename, &options.tga)?,
+            "ico" | "cur" => save_icon_entries(
+                &[IconEntry::new(image.into_owned())],
+                &filename,
+                extension == "cur",
+            )?,
+            "webp" => save_webp(&image, filename)?,
+            "gif" => save_gif(&image, filename, &options.gif)?,
+            "qoi" => save_qoi(&image, filename)?,
+            "dds" | "ktx2" => Texture::from_image(image.into_owned()).save_file(filename)?,
 
             _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
         };
 
-        Ok(())
+        Ok(adaptations)
     }
 
-    fn save_jpg<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
-        if self.desc.color_format().channel_type != ChannelType::UInt {
-            return Err(anyhow::anyhow!(
-                "Unsupported JPEG channel type: {:?}",
-                self.desc.color_format().channel_type
-            ));
+    /// Saves the image as a JPEG with the given quality, subsampling and encoding options.
+    pub fn save_jpg_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &JpegOptions,
+    ) -> anyhow::Result<()> {
+        save_jpeg(self, filename, options)
+    }
+    /// Saves the image as a PNG with the given compression level and filter.
+    pub fn save_png_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &PngOptions,
+    ) -> anyhow::Result<()> {
+        save_png(self, filename, options)
+    }
+    /// Saves the image as a TIFF with the given compression and predictor.
+    pub fn save_tiff_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &TiffOptions,
+    ) -> anyhow::Result<()> {
+        save_tiff(self, filename, options)
+    }
+    /// Saves the image as an EXR with the given compression and tiling.
+    pub fn save_exr_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &ExrOptions,
+    ) -> anyhow::Result<()> {
+        save_exr(self, filename, options)
+    }
+    /// Saves the image as a PBM, PGM, PPM or PAM, as the extension says, in binary or ASCII.
+    pub fn save_pnm_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &PnmOptions,
+    ) -> anyhow::Result<()> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            .ok_or(anyhow::anyhow!("Failed to get file extension"))?
+            .to_string();
+
+        save_pnm(self, filename, &extension, options)
+    }
+    /// Saves the layers as the parts of a single EXR, each layer keeping its color format.
+    pub fn save_exr_layers<P: AsRef<Path>>(
+        layers: &[ExrLayer],
+        filename: P,
+        options: &ExrOptions,
+    ) -> anyhow::Result<()> {
+        save_exr_layers(layers, filename, options)
+    }
+    /// Saves the image as a TGA, run-length encoded or not.
+    pub fn save_tga_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &TgaOptions,
+    ) -> anyhow::Result<()> {
+        save_tga(self, filename, options)
+    }
+    /// Saves the image as a GIF, quantized at the given speed.
+    pub fn save_gif_with_options<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: &GifOptions,
+    ) -> anyhow::Result<()> {
+        save_gif(self, filename, options)
+    }
+    /// Saves the entries as the resolutions of a single ICO, or of a CUR with their
+    /// hotspots when the extension is `cur`.
+    pub fn save_icon_entries<P: AsRef<Path>>(
+        entries: &[IconEntry],
+        filename: P,
+    ) -> anyhow::Result<()> {
+        let cursor = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            == Some("cur");
+
+        save_icon_entries(entries, filename, cursor)
+    }
+    /// Saves the images as the pages of a single TIFF, each page keeping its color format.
+    pub fn save_all_pages<P: AsRef<Path>>(
+        images: &[Image],
+        filename: P,
+        options: &TiffOptions,
+    ) -> anyhow::Result<()> {
+        save_tiff_pages(images, filename, options)
+    }
+    /// Saves pyramid levels as a tiled TIFF, the first level as the main page and
+    /// the others as reduced-resolution pages. Tiles default to 256x256.
+    pub fn save_pyramid<P: AsRef<Path>>(
+        levels: &[Image],
+        filename: P,
+        options: &TiffOptions,
+    ) -> anyhow::Result<()> {
+        save_tiff_pyramid(levels, filename, options)
+    }
+
+    /// Converts the image to another color format, keeping its layout.
+    pub fn convert(self, color_format: ColorFormat) -> anyhow::Result<Image> {
+        color_format.validate()?;
+
+        if self.desc.color_format == color_format {
+            return Ok(self);
         }
 
-        let color_format = match self.desc.color_format().channel_size {
-            ChannelSize::_8bit => match self.desc.color_format().channel_count {
-                ChannelCount::Gray => image_lib::ColorType::L8,
-                ChannelCount::Rgb => image_lib::ColorType::Rgb8,
-
-                _ => {
-                    return Err(anyhow::anyhow!(
-                        "Unsupported JPEG color format: {:?}",
-                        self.desc.color_format().channel_count
-                    ))
-                }
-            },
+        // only float RGB(A) can hold the other color spaces, so the rest get RGB values
+        let keeps_color_space = color_format.channel_type == ChannelType::Float
+            && matches!(
+                color_format.channel_count,
+                ChannelCount::Rgb | ChannelCount::Rgba
+            );
+        let image = if self.desc.color_space != ColorSpace::Rgb && !keeps_color_space {
+            self.convert_color_space(ColorSpace::Rgb)?
+        } else {
+            self
+        };
+
+        let layout = image.desc.layout;
+        let image = image.convert_layout(Layout::Interleaved)?;
+
+        let desc = ImageDesc::new(image.desc.width, image.desc.height, color_format)
+            .with_color_space(image.desc.color_space)
+            .with_color_primaries(image.desc.color_primaries)
+            .with_transfer_function(image.desc.transfer_function);
+
+        let mut result = Image::new_empty(desc)?;
+        // a profile of the other channel layout no longer describes the pixels
+        result.icc_profile = image
+            .icc_profile
+            .clone()
+            .filter(|icc_profile| profile_matches(icc_profile, color_format.channel_count));
+        result.metadata = image.metadata.clone();
+
+        convert_image(&image, &mut result)?;
+
+        result.convert_layout(layout)
+    }
+
+    /// Converts the color channels of an RGB(A) float image into another color space.
+    /// Alpha is left untouched.
+    pub fn convert_color_space(self, color_space: ColorSpace) -> anyhow::Result<Image> {
+        if self.desc.color_space == color_space {
+            return Ok(self);
+        }
 
+        match self.desc.color_format.channel_count {
+            ChannelCount::Rgb | ChannelCount::Rgba => {}
             _ => {
                 return Err(anyhow::anyhow!(
-                    "Unsupported JPEG channel size: {:?}",
-                    self.desc.color_format().channel_size
+                    "Unsupported channel count for color space conversion: {:?}",
+                    self.desc.color_format.channel_count
                 ))
             }
-        };
+        }
 
-        image_lib::save_buffer_with_format(
-            filename,
-            &self.bytes,
-            self.desc.width,
-            self.desc.height,
-            color_format,
-            image_lib::ImageFormat::Jpeg,
-        )?;
+        let layout = self.desc.layout;
+        let image = self.convert_layout(Layout::Interleaved)?;
 
-        Ok(())
+        let mut result = image.clone();
+        result.desc.color_space = color_space;
+
+        convert_color_space(&image, &mut result)?;
+
+        result.convert_layout(layout)
     }
-    fn save_png<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
-        if self.desc.color_format().channel_type != ChannelType::UInt {
+
+    /// Converts the RGB channels of a float image into other primaries,
+    /// the conversion is done in linear light and keeps the transfer function.
+    pub fn convert_primaries(
+        self,
+        color_primaries: ColorPrimaries,
+        gamut_mapping: GamutMapping,
+    ) -> anyhow::Result<Image> {
+        let transfer_function = self.desc.transfer_function;
+
+        self.convert_rgb(color_primaries, transfer_function, gamut_mapping)
+    }
+
+    /// Re-encodes the RGB channels of a float image with another transfer function.
+    pub fn convert_transfer_function(
+        self,
+        transfer_function: TransferFunction,
+    ) -> anyhow::Result<Image> {
+        let color_primaries = self.desc.color_primaries;
+
+        self.convert_rgb(color_primaries, transfer_function, GamutMapping::None)
+    }
+
+    fn convert_rgb(
+        self,
+        color_primaries: ColorPrimaries,
+        transfer_function: TransferFunction,
+        gamut_mapping: GamutMapping,
+    ) -> anyhow::Result<Image> {
+        if self.desc.color_primaries == color_primaries
+            && self.desc.transfer_function == transfer_function
+            && gamut_mapping == GamutMapping::None
+        {
+            return Ok(self);
+        }
+
+        if self.desc.color_space != ColorSpace::Rgb {
             return Err(anyhow::anyhow!(
-                "Unsupported PNG channel type: {:?}",
-                self.desc.color_format().channel_type
+                "RGB conversion requires an RGB color space, got {:?}",
+                self.desc.color_space
             ));
         }
+        match self.desc.color_format.channel_count {
+            ChannelCount::Rgb | ChannelCount::Rgba => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported channel count for RGB conversion: {:?}",
+                    self.desc.color_format.channel_count
+                ))
+            }
+        }
 
-        let color_format = match self.desc.color_format().channel_size {
-            ChannelSize::_8bit => match self.desc.color_format().channel_count {
-                ChannelCount::Gray => image_lib::ColorType::L8,
-                ChannelCount::GrayAlpha => image_lib::ColorType::La8,
-                ChannelCount::Rgb => image_lib::ColorType::Rgb8,
-                ChannelCount::Rgba => image_lib::ColorType::Rgba8,
-            },
-            ChannelSize::_16bit => match self.desc.color_format().channel_count {
-                ChannelCount::Gray => image_lib::ColorType::L16,
-                ChannelCount::GrayAlpha => image_lib::ColorType::La16,
-                ChannelCount::Rgb => image_lib::ColorType::Rgb16,
-                ChannelCount::Rgba => image_lib::ColorType::Rgba16,
-            },
+        let layout = self.desc.layout;
+        let image = self.convert_layout(Layout::Interleaved)?;
+
+        let mut result = image.clone();
+        result.desc.color_primaries = color_primaries;
+        result.desc.transfer_function = transfer_function;
+
+        convert_primaries(&image, &mut result, gamut_mapping)?;
 
+        result.convert_layout(layout)
+    }
+
+    /// Converts the RGB channels into the `target` profile and embeds it.
+    ///
+    /// The source profile is the embedded ICC profile or, without one,
+    /// the profile of the image primaries and transfer function. The primaries and
+    /// transfer function of the result are the closest ones to the target profile.
+    pub fn convert_icc_profile(self, target: &IccProfile) -> anyhow::Result<Image> {
+        let source = match &self.icc_profile {
+            Some(icc_profile) => IccProfile::parse(icc_profile)?,
+            None => {
+                IccProfile::from_primaries(self.desc.color_primaries, self.desc.transfer_function)
+            }
+        };
+
+        if self.desc.color_space != ColorSpace::Rgb {
+            return Err(anyhow::anyhow!(
+                "ICC profile conversion requires an RGB color space, got {:?}",
+                self.desc.color_space
+            ));
+        }
+        let color_format = self.desc.color_format;
+        match color_format.channel_count {
+            ChannelCount::Rgb | ChannelCount::Rgba => {}
             _ => {
                 return Err(anyhow::anyhow!(
-                    "Unsupported PNG channel size: {:?}",
-                    self.desc.color_format().channel_size
+                    "Unsupported channel count for ICC profile conversion: {:?}",
+                    color_format.channel_count
                 ))
             }
-        };
+        }
 
-        image_lib::save_buffer_with_format(
-            filename,
-            &self.bytes,
-            self.desc.width,
-            self.desc.height,
-            color_format,
-            image_lib::ImageFormat::Png,
-        )?;
+        let layout = self.desc.layout;
+        let image = self
+            .convert(float_working_format(color_format))?
+            .convert_layout(Layout::Interleaved)?;
 
-        Ok(())
+        let (color_primaries, transfer_function) = target.closest_primaries();
+        let mut result = image.clone();
+        result.icc_profile = Some(target.to_bytes());
+        result.desc.color_primaries = color_primaries;
+        result.desc.transfer_function = transfer_function;
+
+        apply_icc_profile(&image, &mut result, &source, target)?;
+
+        result.convert(color_format)?.convert_layout(layout)
     }
-    fn save_tiff<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
-        save_tiff(self, filename)
+
+    /// Converts the RGB channels into sRGB, see [`Image::convert_icc_profile`].
+    pub fn convert_to_srgb(self) -> anyhow::Result<Image> {
+        self.convert_icc_profile(&IccProfile::srgb())
     }
 
-    pub fn convert(self, color_format: ColorFormat) -> anyhow::Result<Image> {
-        color_format.validate()?;
+    /// Applies a 1D or 3D LUT to the RGB channels, alpha is left untouched.
+    ///
+    /// Integer images are normalized to `[0, 1]` floats for the lookup and converted back.
+    pub fn apply_lut(self, lut: &impl Lut) -> anyhow::Result<Image> {
+        let color_format = self.desc.color_format;
+        match color_format.channel_count {
+            ChannelCount::Rgb | ChannelCount::Rgba => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported channel count for LUT application: {:?}",
+                    color_format.channel_count
+                ))
+            }
+        }
+
+        let layout = self.desc.layout;
+        let image = self
+            .convert(float_working_format(color_format))?
+            .convert_layout(Layout::Interleaved)?;
+
+        let mut result = image.clone();
+
+        apply_lut(&image, &mut result, lut)?;
+
+        result.convert(color_format)?.convert_layout(layout)
+    }
+
+    /// Maps the scene-referred RGB channels of a float image into display-referred
+    /// sRGB encoded values in `[0, 1]`, ready to be converted into an 8 bit format.
+    /// Alpha is left untouched.
+    pub fn tone_map(self, tone_mapping: impl Into<ToneMapping>) -> anyhow::Result<Image> {
+        if self.desc.color_space != ColorSpace::Rgb {
+            return Err(anyhow::anyhow!(
+                "Tone mapping requires an RGB color space, got {:?}",
+                self.desc.color_space
+            ));
+        }
+        match self.desc.color_format.channel_count {
+            ChannelCount::Rgb | ChannelCount::Rgba => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported channel count for tone mapping: {:?}",
+                    self.desc.color_format.channel_count
+                ))
+            }
+        }
+
+        let layout = self.desc.layout;
+        let image = self.convert_layout(Layout::Interleaved)?;
+
+        let mut result = image.clone();
+        result.desc.transfer_function = TransferFunction::Srgb;
+
+        tone_map(&image, &mut result, &tone_mapping.into())?;
+
+        result.convert_layout(layout)
+    }
+
+    /// Builds an image pyramid of up to `levels` levels, starting with the image itself,
+    /// each level being half the size of the previous one, rounded up.
+    /// Stops early once a level is a single pixel, `levels` must be at least one.
+    pub fn build_pyramid(self, levels: u32, filter: ResampleFilter) -> anyhow::Result<Vec<Image>> {
+        if levels == 0 {
+            return Err(anyhow::anyhow!("A pyramid needs at least one level"));
+        }
+
+        let color_format = self.desc.color_format;
+        let layout = self.desc.layout;
 
+        // the levels are resampled from the float copy of the previous one, or the image itself
+        let mut working = self.working_copy()?;
+        let mut pyramid = vec![self];
+
+        loop {
+            let current = working.as_ref().unwrap_or(&pyramid[0]);
+            if pyramid.len() as u32 >= levels
+                || (current.desc.width == 1 && current.desc.height == 1)
+            {
+                break;
+            }
+
+            let desc = ImageDesc::new(
+                current.desc.width.div_ceil(2),
+                current.desc.height.div_ceil(2),
+                current.desc.color_format,
+            )
+            .with_color_space(current.desc.color_space)
+            .with_color_primaries(current.desc.color_primaries)
+            .with_transfer_function(current.desc.transfer_function);
+            let mut level = Image::new_empty(desc)?;
+            level.icc_profile = current.icc_profile.clone();
+            level.metadata = current.metadata.clone();
+            // the physical size is kept
+            if let Some(resolution) = &mut level.metadata.resolution {
+                resolution.x *= level.desc.width as f64 / current.desc.width as f64;
+                resolution.y *= level.desc.height as f64 / current.desc.height as f64;
+            }
+
+            resample(current, &mut level, filter)?;
+
+            pyramid.push(
+                level
+                    .clone()
+                    .convert(color_format)?
+                    .convert_layout(layout)?,
+            );
+            working = Some(level);
+        }
+
+        Ok(pyramid)
+    }
+
+    /// Interleaved float copy of the image to resample pyramid levels from,
+    /// `None` when the image already is in that format.
+    fn working_copy(&self) -> anyhow::Result<Option<Image>> {
+        let color_format = float_working_format(self.desc.color_format);
+        if self.desc.layout == Layout::Planar {
+            let mut interleaved =
+                Image::new_empty(self.desc.clone().with_layout(Layout::Interleaved))?;
+            interleaved.icc_profile = self.icc_profile.clone();
+            interleaved.metadata = self.metadata.clone();
+            convert_layout(self, &mut interleaved);
+
+            return interleaved.convert(color_format).map(Some);
+        }
         if self.desc.color_format == color_format {
+            return Ok(None);
+        }
+
+        // float RGB(A) keeps the color space, gray images are always RGB
+        let desc = ImageDesc::new(self.desc.width, self.desc.height, color_format)
+            .with_color_space(self.desc.color_space)
+            .with_color_primaries(self.desc.color_primaries)
+            .with_transfer_function(self.desc.transfer_function);
+        let mut result = Image::new_empty(desc)?;
+        result.icc_profile = self.icc_profile.clone();
+        result.metadata = self.metadata.clone();
+
+        convert_image(self, &mut result)?;
+
+        Ok(Some(result))
+    }
+
+    /// Transforms the pixels by `orientation`, exchanging the horizontal and
+    /// vertical resolutions when the image is rotated by a quarter turn.
+    pub fn orient(self, orientation: Orientation) -> anyhow::Result<Image> {
+        let layout = self.desc.layout;
+        let image = self.convert_layout(Layout::Interleaved)?;
+
+        let (width, height) = if orientation.swaps_dimensions() {
+            (image.desc.height, image.desc.width)
+        } else {
+            (image.desc.width, image.desc.height)
+        };
+        let desc = ImageDesc::new(width, height, image.desc.color_format)
+            .with_color_space(image.desc.color_space)
+            .with_color_primaries(image.desc.color_primaries)
+            .with_transfer_function(image.desc.transfer_function);
+        let mut result = Image::new_empty(desc)?;
+        result.icc_profile = image.icc_profile.clone();
+        result.metadata = image.metadata.clone();
+        if let (true, Some(resolution)) = (
+            orientation.swaps_dimensions(),
+            &mut result.metadata.resolution,
+        ) {
+            std::mem::swap(&mut resolution.x, &mut resolution.y);
+        }
+
+        orient(&image, &mut result, orientation);
+
+        result.convert_layout(layout)
+    }
+    /// Applies the EXIF orientation so the pixels are stored upright,
+    /// then resets the orientation tag.
+    pub fn auto_orient(self) -> anyhow::Result<Image> {
+        let orientation = match &self.metadata.exif {
+            Some(exif) => exif.orientation(),
+            None => return Ok(self),
+        };
+
+        let mut result = self.orient(orientation)?;
+        if let Some(exif) = &mut result.metadata.exif {
+            exif.set_orientation(Orientation::Normal);
+        }
+
+        Ok(result)
+    }
+
+    /// Rearranges the image bytes into another memory layout, keeping the color format.
+    pub fn convert_layout(self, layout: Layout) -> anyhow::Result<Image> {
+        if self.desc.layout == layout {
             return Ok(self);
         }
 
-        let desc = ImageDesc::new(self.desc.width, self.desc.height, color_format);
+        if (self.bytes.len() as u32) < self.desc.size_in_bytes() {
+            return Err(anyhow::anyhow!(
+                "Image data is too short: {} bytes, expected {}",
+                self.bytes.len(),
+                self.desc.size_in_bytes()
+            ));
+        }
+
+        let desc = self.desc.clone().with_layout(layout);
 
         let mut result = Image::new_empty(desc)?;
+        result.icc_profile = self.icc_profile.clone();
+        result.metadata = self.metadata.clone();
 
-        convert_image(&self, &mut result)?;
+        convert_layout(&self, &mut result);
 
         Ok(result)
     }
@@ -308,6 +892,64 @@ impl Image {
     }
 }
 
+/// Lazy iterator over the pages of a TIFF, created by [`Image::read_pages`].
+pub struct TiffPages {
+    decoder: tiff::decoder::Decoder<File>,
+    reader: File,
+    page: usize,
+    finished: bool,
+}
+
+impl Iterator for TiffPages {
+    type Item = anyhow::Result<Image>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.page > 0 {
+            if !self.decoder.more_images() {
+                self.finished = true;
+                return None;
+            }
+            if let Err(e) = self.decoder.next_image() {
+                self.finished = true;
+                return Some(Err(e.into()));
+            }
+        }
+
+        let page = load_tiff(&mut self.decoder, &mut self.reader, self.page);
+        self.page += 1;
+        if page.is_err() {
+            self.finished = true;
+        }
+
+        Some(page)
+    }
+}
+
+/// Float format pixel operations work in, integer formats are converted
+/// through normalized 32 bit floats.
+pub(crate) fn float_working_format(color_format: ColorFormat) -> ColorFormat {
+    match (color_format.channel_type, color_format.channel_size) {
+        (ChannelType::Float, ChannelSize::_32bit | ChannelSize::_64bit) => color_format,
+        _ => ColorFormat::from((
+            color_format.channel_count,
+            ChannelSize::_32bit,
+            ChannelType::Float,
+        )),
+    }
+}
+
+/// Byte size of an image read from a file, `None` when it is empty or larger than
+/// the u32 sizes of [`ImageDesc`], so the loaders reject it before allocating its pixels.
+pub(crate) fn checked_image_size(width: u32, height: u32, pixel_size: usize) -> Option<usize> {
+    (width as usize)
+        .checked_mul(height as usize)?
+        .checked_mul(pixel_size)
+        .filter(|&size| size > 0 && size <= u32::MAX as usize)
+}
+
 impl ImageDesc {
     pub fn new(width: u32, height: u32, color_format: ColorFormat) -> Self {
         let stride = align_stride(
@@ -319,11 +961,57 @@ impl ImageDesc {
             height,
             stride,
             color_format,
+            layout: Layout::Interleaved,
+            color_space: ColorSpace::Rgb,
+            color_primaries: ColorPrimaries::Srgb,
+            transfer_function: TransferFunction::Srgb,
         }
     }
+    /// Returns the same description with another memory layout.
+    ///
+    /// For `Layout::Planar` the stride is the size of a single row of one plane.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        let channel_count = match layout {
+            Layout::Interleaved => self.color_format.channel_count as u32,
+            Layout::Planar => 1,
+        };
+
+        self.stride =
+            align_stride(self.width * channel_count * self.color_format.channel_size as u32);
+        self.layout = layout;
+
+        self
+    }
+    /// Returns the same description with another color space, the data is not converted.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+
+        self
+    }
+    /// Returns the same description with other primaries, the data is not converted.
+    pub fn with_color_primaries(mut self, color_primaries: ColorPrimaries) -> Self {
+        self.color_primaries = color_primaries;
+
+        self
+    }
+    /// Returns the same description with another transfer function, the data is not converted.
+    pub fn with_transfer_function(mut self, transfer_function: TransferFunction) -> Self {
+        self.transfer_function = transfer_function;
+
+        self
+    }
     pub fn size_in_bytes(&self) -> u32 {
+        self.plane_size_in_bytes() * self.plane_count()
+    }
+    pub fn plane_size_in_bytes(&self) -> u32 {
         self.height * self.stride
     }
+    pub fn plane_count(&self) -> u32 {
+        match self.layout {
+            Layout::Interleaved => 1,
+            Layout::Planar => self.color_format.channel_count as u32,
+        }
+    }
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -336,4 +1024,16 @@ impl ImageDesc {
     pub fn color_format(&self) -> ColorFormat {
         self.color_format
     }
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+    pub fn color_primaries(&self) -> ColorPrimaries {
+        self.color_primaries
+    }
+    pub fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
 }
@@ -4,7 +4,7 @@ use bytemuck::Pod;
 use num_traits::Bounded;
 
 use crate::color_format::*;
-use crate::image::Image;
+use crate::image::{Image, Layout};
 
 pub(crate) fn convert_image(from: &Image, to: &mut Image) -> anyhow::Result<()> {
     match (
@@ -545,6 +545,43 @@ fn convert_pixels<From, To>(
     }
 }
 
+pub(crate) fn convert_layout(from: &Image, to: &mut Image) {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let channel_count = from.desc.color_format().channel_count.channel_count();
+    let channel_size = from.desc.color_format().channel_size.byte_count();
+
+    // byte offset of a single channel sample of a pixel
+    let offset = |image: &Image, channel: u32, i: u32, j: u32| -> usize {
+        let offset = match image.desc.layout() {
+            Layout::Interleaved => {
+                i * image.desc.stride() + (j * channel_count + channel) * channel_size
+            }
+            Layout::Planar => {
+                channel * image.desc.plane_size_in_bytes()
+                    + i * image.desc.stride()
+                    + j * channel_size
+            }
+        };
+
+        offset as usize
+    };
+
+    for channel in 0..channel_count {
+        for i in 0..from.desc.height() {
+            for j in 0..from.desc.width() {
+                let from_offset = offset(from, channel, i, j);
+                let to_offset = offset(to, channel, i, j);
+
+                to.bytes[to_offset..to_offset + channel_size as usize]
+                    .copy_from_slice(&from.bytes[from_offset..from_offset + channel_size as usize]);
+            }
+        }
+    }
+}
+
 // @formatter:off
 #[inline]
 pub(crate) fn i8_to_i8(value: i8) -> i8 {
@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::save_options::{ChromaSubsampling, JpegOptions};
+
+pub(crate) fn save_jpeg<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &JpegOptions,
+) -> anyhow::Result<()> {
+    if image.desc.layout() != Layout::Interleaved {
+        return save_jpeg(
+            &image.clone().convert_layout(Layout::Interleaved)?,
+            filename,
+            options,
+        );
+    }
+
+    let color_format = image.desc.color_format();
+    if color_format.channel_type != ChannelType::UInt {
+        return Err(anyhow::anyhow!(
+            "Unsupported JPEG channel type: {:?}",
+            color_format.channel_type
+        ));
+    }
+    if color_format.channel_size != ChannelSize::_8bit {
+        return Err(anyhow::anyhow!(
+            "Unsupported JPEG channel size: {:?}",
+            color_format.channel_size
+        ));
+    }
+    let color_type = match color_format.channel_count {
+        ChannelCount::Gray => ColorType::Luma,
+        ChannelCount::Rgb => ColorType::Rgb,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported JPEG color format: {:?}",
+                color_format.channel_count
+            ))
+        }
+    };
+
+    if !(1..=100).contains(&options.quality) {
+        return Err(anyhow::anyhow!(
+            "JPEG quality must be between 1 and 100, got {}",
+            options.quality
+        ));
+    }
+    let (width, height) = match (
+        u16::try_from(image.desc.width()),
+        u16::try_from(image.desc.height()),
+    ) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "JPEG dimensions are limited to 65535, got {}x{}",
+                image.desc.width(),
+                image.desc.height()
+            ))
+        }
+    };
+
+    let file = BufWriter::new(File::create(filename)?);
+    let mut encoder = Encoder::new(file, options.quality);
+    encoder.set_sampling_factor(match options.subsampling {
+        // @formatter:off
+        ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+        ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+        ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+        // @formatter:on
+    });
+    encoder.set_progressive(options.progressive);
+    // the optimized tables miss the DC differences reset at restart markers
+    encoder.set_optimized_huffman_tables(
+        options.optimize_huffman && options.restart_interval.is_none(),
+    );
+    if let Some(restart_interval) = options.restart_interval {
+        encoder.set_restart_interval(restart_interval);
+    }
+    if let Some(icc_profile) = &image.icc_profile {
+        encoder.add_icc_profile(icc_profile)?;
+    }
+    if let Some(exif) = &image.metadata.exif {
+        encoder.add_exif_metadata(&exif.to_bytes())?;
+    }
+
+    let row_size = (image.desc.width() * image.bytes_per_pixel()) as usize;
+    let mut data = Vec::with_capacity(row_size * image.desc.height() as usize);
+    for row in image.bytes.chunks(image.desc.stride() as usize) {
+        data.extend_from_slice(&row[..row_size]);
+    }
+    encoder.encode(&data, width, height, color_type)?;
+
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::texture::{
+    format_code, format_from_code, image_from_texels, mip_chain_size, mip_size, texel_bytes,
+    texel_size, FormatTable, TexelEncoding, Texture, TextureShape,
+};
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+/// Identifier, header and index.
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+const SUPERCOMPRESSION_NONE: u32 = 0;
+
+const DFD_BASIC_BLOCK_SIZE: usize = 24;
+const DFD_SAMPLE_SIZE: usize = 16;
+const DFD_VERSION: u16 = 2;
+const DFD_MODEL_RGBSDA: u8 = 1;
+const DFD_PRIMARIES_BT709: u8 = 1;
+const DFD_TRANSFER_LINEAR: u8 = 1;
+const DFD_TRANSFER_SRGB: u8 = 2;
+const DFD_CHANNEL_ALPHA: u8 = 15;
+const DFD_SAMPLE_LINEAR: u8 = 0x10;
+const DFD_SAMPLE_SIGNED: u8 = 0x40;
+const DFD_SAMPLE_FLOAT: u8 = 0x80;
+
+/// Uncompressed Vulkan formats, the gray ones being red and red green.
+const VK_FORMATS: &FormatTable = &[
+    (9, ColorFormat::GRAY_U8, TexelEncoding::Plain),
+    (10, ColorFormat::GRAY_I8, TexelEncoding::Plain),
+    (15, ColorFormat::GRAY_U8, TexelEncoding::Srgb),
+    (16, ColorFormat::GRAY_ALPHA_U8, TexelEncoding::Plain),
+    (17, ColorFormat::GRAY_ALPHA_I8, TexelEncoding::Plain),
+    (22, ColorFormat::GRAY_ALPHA_U8, TexelEncoding::Srgb),
+    (23, ColorFormat::RGB_U8, TexelEncoding::Plain),
+    (24, ColorFormat::RGB_I8, TexelEncoding::Plain),
+    (29, ColorFormat::RGB_U8, TexelEncoding::Srgb),
+    (37, ColorFormat::RGBA_U8, TexelEncoding::Plain),
+    (38, ColorFormat::RGBA_I8, TexelEncoding::Plain),
+    (43, ColorFormat::RGBA_U8, TexelEncoding::Srgb),
+    (70, ColorFormat::GRAY_U16, TexelEncoding::Plain),
+    (71, ColorFormat::GRAY_I16, TexelEncoding::Plain),
+    (76, ColorFormat::GRAY_F32, TexelEncoding::Half),
+    (77, ColorFormat::GRAY_ALPHA_U16, TexelEncoding::Plain),
+    (78, ColorFormat::GRAY_ALPHA_I16, TexelEncoding::Plain),
+    (83, ColorFormat::GRAY_ALPHA_F32, TexelEncoding::Half),
+    (84, ColorFormat::RGB_U16, TexelEncoding::Plain),
+    (85, ColorFormat::RGB_I16, TexelEncoding::Plain),
+    (90, ColorFormat::RGB_F32, TexelEncoding::Half),
+    (91, ColorFormat::RGBA_U16, TexelEncoding::Plain),
+    (92, ColorFormat::RGBA_I16, TexelEncoding::Plain),
+    (97, ColorFormat::RGBA_F32, TexelEncoding::Half),
+    (98, ColorFormat::GRAY_U32, TexelEncoding::Plain),
+    (99, ColorFormat::GRAY_I32, TexelEncoding::Plain),
+    (100, ColorFormat::GRAY_F32, TexelEncoding::Plain),
+    (101, ColorFormat::GRAY_ALPHA_U32, TexelEncoding::Plain),
+    (102, ColorFormat::GRAY_ALPHA_I32, TexelEncoding::Plain),
+    (103, ColorFormat::GRAY_ALPHA_F32, TexelEncoding::Plain),
+    (104, ColorFormat::RGB_U32, TexelEncoding::Plain),
+    (105, ColorFormat::RGB_I32, TexelEncoding::Plain),
+    (106, ColorFormat::RGB_F32, TexelEncoding::Plain),
+    (107, ColorFormat::RGBA_U32, TexelEncoding::Plain),
+    (108, ColorFormat::RGBA_I32, TexelEncoding::Plain),
+    (109, ColorFormat::RGBA_F32, TexelEncoding::Plain),
+];
+
+/// Reads a 2D KTX2 without supercompression, its array layers, cube faces and mip levels.
+pub(crate) fn load_ktx2<P: AsRef<Path>>(filename: P) -> anyhow::Result<Texture> {
+    let data = std::fs::read(filename)?;
+    if data.len() < HEADER_SIZE || data[..12] != IDENTIFIER {
+        return Err(anyhow::anyhow!("Not a KTX2 file"));
+    }
+    let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+    let vk_format = u32_at(12);
+    let (color_format, encoding, transfer_function) = format_from_code(VK_FORMATS, vk_format)
+        .ok_or(anyhow::anyhow!("Unsupported Vulkan format: {}", vk_format))?;
+    // one dimensional textures have no height
+    let (width, height) = (u32_at(20), u32_at(24).max(1));
+    if u32_at(28) != 0 {
+        return Err(anyhow::anyhow!("3D KTX2 textures are not supported"));
+    }
+    let element_count = u32_at(32).max(1) as usize;
+    let face_count = match u32_at(36) {
+        // @formatter:off
+        1 => 1,
+        6 => 6,
+        // @formatter:on
+        count => return Err(anyhow::anyhow!("Invalid KTX2 face count: {}", count)),
+    };
+    // no levels asks for them to be generated
+    let level_count = u32_at(40).max(1) as usize;
+    if u32_at(44) != SUPERCOMPRESSION_NONE {
+        return Err(anyhow::anyhow!(
+            "Unsupported KTX2 supercompression: {}",
+            u32_at(44)
+        ));
+    }
+    if data.len() < HEADER_SIZE + level_count * LEVEL_INDEX_ENTRY_SIZE {
+        return Err(anyhow::anyhow!("Truncated KTX2 level index"));
+    }
+
+    let texel_size = texel_size(color_format, encoding);
+    let layer_count = element_count * face_count;
+    let chain_size = mip_chain_size(width, height, level_count, texel_size)?;
+    if chain_size
+        .checked_mul(layer_count)
+        .is_none_or(|size| size > data.len())
+    {
+        return Err(anyhow::anyhow!("Truncated KTX2 data"));
+    }
+
+    let mut layers = vec![Vec::with_capacity(level_count); layer_count];
+    for level in 0..level_count {
+        let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY_SIZE;
+        let offset = usize::try_from(u64_at(entry))?;
+        let size = mip_size(width, height, level);
+        let image_length = size.0 as usize * size.1 as usize * texel_size;
+        let level_data = offset
+            .checked_add(image_length * layer_count)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(anyhow::anyhow!("Truncated KTX2 level {}", level))?;
+
+        // layers, then faces, follow each other within a level
+        for (chain, texels) in layers.iter_mut().zip(level_data.chunks_exact(image_length)) {
+            chain.push(image_from_texels(
+                texels,
+                size,
+                color_format,
+                encoding,
+                transfer_function,
+            )?);
+        }
+    }
+
+    Ok(Texture::new(layers, face_count == 6))
+}
+
+/// Writes a texture as a KTX2 without supercompression.
+pub(crate) fn save_ktx2<P: AsRef<Path>>(texture: &Texture, filename: P) -> anyhow::Result<()> {
+    let shape = texture.shape()?;
+    let color_format = shape.desc.color_format();
+    let vk_format = format_code(VK_FORMATS, &shape.desc).ok_or(anyhow::anyhow!(
+        "Unsupported KTX2 color format: {}",
+        color_format
+    ))?;
+    let srgb = format_from_code(VK_FORMATS, vk_format)
+        .is_some_and(|(_, encoding, _)| encoding == TexelEncoding::Srgb);
+    let dfd = data_format_descriptor(color_format, srgb);
+
+    // levels are stored from the smallest, each aligned to its texels and to 4 bytes
+    let texel_size = color_format.byte_count() as usize;
+    let alignment = texel_size * 4 / gcd(texel_size, 4);
+    let level_lengths: Vec<usize> = (0..shape.level_count)
+        .map(|level| {
+            let (width, height) = mip_size(shape.desc.width(), shape.desc.height(), level);
+            width as usize * height as usize * texel_size * texture.layers.len()
+        })
+        .collect();
+    let dfd_offset = HEADER_SIZE + shape.level_count * LEVEL_INDEX_ENTRY_SIZE;
+    let mut level_offsets = vec![0; shape.level_count];
+    let mut end = dfd_offset + dfd.len();
+    for level in (0..shape.level_count).rev() {
+        level_offsets[level] = end.next_multiple_of(alignment);
+        end = level_offsets[level] + level_lengths[level];
+    }
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(&IDENTIFIER)?;
+    write_header(&mut writer, &shape, vk_format)?;
+    for value in [dfd_offset, dfd.len(), 0, 0] {
+        writer.write_all(&(value as u32).to_le_bytes())?;
+    }
+    // no supercompression global data
+    writer.write_all(&[0; 16])?;
+    for (offset, length) in level_offsets.iter().zip(&level_lengths) {
+        for value in [*offset, *length, *length] {
+            writer.write_all(&(value as u64).to_le_bytes())?;
+        }
+    }
+    writer.write_all(&dfd)?;
+
+    let mut position = dfd_offset + dfd.len();
+    for level in (0..shape.level_count).rev() {
+        writer.write_all(&vec![0; level_offsets[level] - position])?;
+        for chain in &texture.layers {
+            writer.write_all(&texel_bytes(&chain[level])?)?;
+        }
+        position = level_offsets[level] + level_lengths[level];
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_header(
+    writer: &mut impl Write,
+    shape: &TextureShape,
+    vk_format: u32,
+) -> anyhow::Result<()> {
+    // a single layer is not an array
+    let layer_count = match shape.element_count {
+        1 => 0,
+        count => u32::try_from(count)?,
+    };
+    for value in [
+        vk_format,
+        shape.desc.color_format().channel_size.byte_count(),
+        shape.desc.width(),
+        shape.desc.height(),
+        0,
+        layer_count,
+        shape.face_count as u32,
+        shape.level_count as u32,
+        SUPERCOMPRESSION_NONE,
+    ] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Builds the basic data format descriptor of an uncompressed format, one sample per
+/// channel.
+fn data_format_descriptor(color_format: ColorFormat, srgb: bool) -> Vec<u8> {
+    let channel_ids: &[u8] = match color_format.channel_count {
+        // @formatter:off
+        ChannelCount::Gray => &[0],
+        ChannelCount::GrayAlpha => &[0, 1],
+        ChannelCount::Rgb => &[0, 1, 2],
+        ChannelCount::Rgba => &[0, 1, 2, DFD_CHANNEL_ALPHA],
+        // @formatter:on
+    };
+    let bits = color_format.channel_size.byte_count() * 8;
+    let (qualifiers, lower, upper) = match (color_format.channel_type, bits) {
+        (ChannelType::Float, _) => (
+            DFD_SAMPLE_FLOAT | DFD_SAMPLE_SIGNED,
+            (-1.0f32).to_bits(),
+            1.0f32.to_bits(),
+        ),
+        // 32 bit integers are not normalized, 1 is one
+        (ChannelType::UInt, 32) => (0, 0, 1),
+        (ChannelType::Int, 32) => (DFD_SAMPLE_SIGNED, -1i32 as u32, 1),
+        (ChannelType::UInt, _) => (0, 0, (1u32 << bits) - 1),
+        (ChannelType::Int, _) => {
+            let max = (1i32 << (bits - 1)) - 1;
+            (DFD_SAMPLE_SIGNED, -max as u32, max as u32)
+        }
+    };
+    let block_size = DFD_BASIC_BLOCK_SIZE + channel_ids.len() * DFD_SAMPLE_SIZE;
+
+    let mut dfd = Vec::with_capacity(4 + block_size);
+    dfd.extend_from_slice(&((4 + block_size) as u32).to_le_bytes());
+    // Khronos vendor and basic descriptor type
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    dfd.extend_from_slice(&DFD_VERSION.to_le_bytes());
+    dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+    dfd.extend_from_slice(&[
+        DFD_MODEL_RGBSDA,
+        DFD_PRIMARIES_BT709,
+        if srgb {
+            DFD_TRANSFER_SRGB
+        } else {
+            DFD_TRANSFER_LINEAR
+        },
+        0,
+    ]);
+    // texel block dimensions and bytes per plane
+    dfd.extend_from_slice(&[0; 4]);
+    dfd.extend_from_slice(&[color_format.byte_count() as u8, 0, 0, 0, 0, 0, 0, 0]);
+
+    for (index, &channel_id) in channel_ids.iter().enumerate() {
+        // alpha stays linear in sRGB formats
+        let linear = if srgb && channel_id == DFD_CHANNEL_ALPHA {
+            DFD_SAMPLE_LINEAR
+        } else {
+            0
+        };
+        dfd.extend_from_slice(&((index as u32 * bits) as u16).to_le_bytes());
+        dfd.extend_from_slice(&[(bits - 1) as u8, channel_id | qualifiers | linear]);
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.extend_from_slice(&lower.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+
+    dfd
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
@@ -1,12 +1,39 @@
 #![allow(dead_code)]
 
 
+pub mod animation;
 pub mod color_format;
+pub mod color_primaries;
+pub mod color_space;
+pub mod exif;
+pub mod exr;
+pub mod icc;
+pub mod ico;
 pub mod image;
+pub mod lut;
 pub mod math;
+pub mod metadata;
+pub mod pyramid;
+pub mod read_options;
+pub mod save_options;
+pub mod texture;
+pub mod tone_mapping;
 
+mod bmp_extentions;
+mod dds_extentions;
+mod format_adaptation;
+mod gif_extentions;
+mod hdr_extentions;
+mod ifd;
 mod image_conversion;
+mod jpeg_extentions;
+mod ktx2_extentions;
+mod png_extentions;
+mod pnm_extentions;
+mod qoi_extentions;
+mod tga_extentions;
 mod tiff_extentions;
+mod webp_extentions;
 
 
 #[cfg(test)]
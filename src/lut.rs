@@ -0,0 +1,425 @@
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::color_space::convert_pixels;
+use crate::image::Image;
+
+/// Color transform applied to the RGB channels of an image by [`Image::apply_lut`].
+pub trait Lut {
+    /// Maps an RGB color, values of the LUT domain are in `[0, 1]` by default.
+    fn apply(&self, rgb: [f64; 3]) -> [f64; 3];
+}
+
+/// Interpolation between the entries of a 3D LUT.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum LutInterpolation {
+    #[default]
+    Trilinear,
+    Tetrahedral,
+}
+
+/// Largest supported 3D LUT size, far above the 65 points of common `.cube` files.
+const MAX_LUT_3D_SIZE: usize = 256;
+
+/// Per channel curve, uniformly sampled over its domain.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lut1D {
+    data: Vec<[f64; 3]>,
+    domain_min: [f64; 3],
+    domain_max: [f64; 3],
+}
+
+/// RGB lattice of `size`³ entries, red varying fastest.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lut3D {
+    size: usize,
+    data: Vec<[f64; 3]>,
+    domain_min: [f64; 3],
+    domain_max: [f64; 3],
+    interpolation: LutInterpolation,
+    title: Option<String>,
+}
+
+impl Lut1D {
+    pub fn new(data: Vec<[f64; 3]>) -> anyhow::Result<Lut1D> {
+        if data.len() < 2 {
+            return Err(anyhow::anyhow!("1D LUT requires at least 2 entries"));
+        }
+
+        Ok(Lut1D {
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+        })
+    }
+    /// Samples the same `curve` for the three channels.
+    pub fn from_curve(size: usize, curve: impl Fn(f64) -> f64) -> anyhow::Result<Lut1D> {
+        let data = (0..size)
+            .map(|i| [curve(i as f64 / (size.max(2) - 1) as f64); 3])
+            .collect();
+
+        Lut1D::new(data)
+    }
+    /// Parses a `.cube` file holding a `LUT_1D_SIZE` table.
+    pub fn parse_cube(text: &str) -> anyhow::Result<Lut1D> {
+        let cube = parse_cube(text)?;
+        if cube.size_1d == 0 {
+            return Err(anyhow::anyhow!("Cube file has no LUT_1D_SIZE"));
+        }
+        if cube.data.len() != cube.size_1d {
+            return Err(anyhow::anyhow!(
+                "Cube file has {} entries, expected {}",
+                cube.data.len(),
+                cube.size_1d
+            ));
+        }
+
+        Ok(Lut1D::new(cube.data)?.with_domain(cube.domain_min, cube.domain_max))
+    }
+    pub fn read_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Lut1D> {
+        Lut1D::parse_cube(&std::fs::read_to_string(filename)?)
+    }
+    pub fn with_domain(mut self, domain_min: [f64; 3], domain_max: [f64; 3]) -> Self {
+        self.domain_min = domain_min;
+        self.domain_max = domain_max;
+
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl Lut for Lut1D {
+    fn apply(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let last = self.data.len() - 1;
+
+        std::array::from_fn(|c| {
+            let pos = normalize(rgb[c], self.domain_min[c], self.domain_max[c]) * last as f64;
+            let i = (pos as usize).min(last - 1);
+            let t = pos - i as f64;
+
+            self.data[i][c] + (self.data[i + 1][c] - self.data[i][c]) * t
+        })
+    }
+}
+
+impl Lut3D {
+    /// Creates a LUT from `size`³ entries, red varying fastest.
+    pub fn new(size: usize, data: Vec<[f64; 3]>) -> anyhow::Result<Lut3D> {
+        let entry_count = lut_3d_entry_count(size)?;
+        if data.len() != entry_count {
+            return Err(anyhow::anyhow!(
+                "3D LUT has {} entries, expected {}",
+                data.len(),
+                entry_count
+            ));
+        }
+
+        Ok(Lut3D {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            interpolation: LutInterpolation::default(),
+            title: None,
+        })
+    }
+    /// Identity LUT of the given size.
+    pub fn identity(size: usize) -> anyhow::Result<Lut3D> {
+        let step = 1.0 / (size.max(2) - 1) as f64;
+        let data = (0..lut_3d_entry_count(size)?)
+            .map(|i| {
+                [
+                    (i % size) as f64 * step,
+                    (i / size % size) as f64 * step,
+                    (i / (size * size)) as f64 * step,
+                ]
+            })
+            .collect();
+
+        Lut3D::new(size, data)
+    }
+    /// Parses a `.cube` file holding a `LUT_3D_SIZE` lattice.
+    pub fn parse_cube(text: &str) -> anyhow::Result<Lut3D> {
+        let cube = parse_cube(text)?;
+        if cube.size_3d == 0 {
+            return Err(anyhow::anyhow!("Cube file has no LUT_3D_SIZE"));
+        }
+
+        let mut lut =
+            Lut3D::new(cube.size_3d, cube.data)?.with_domain(cube.domain_min, cube.domain_max);
+        lut.title = cube.title;
+
+        Ok(lut)
+    }
+    /// Parses a `.3dl` file, the output bit depth is deduced from the largest value.
+    pub fn parse_3dl(text: &str) -> anyhow::Result<Lut3D> {
+        let mut size = 0;
+        let mut values: Vec<[f64; 3]> = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            // comments and Lustre headers such as `3DMESH` or `Mesh 4 12`
+            if line.is_empty() || !line.starts_with(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+
+            let numbers = line
+                .split_whitespace()
+                .map(|n| n.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow::anyhow!("Invalid 3dl line '{}': {}", line, e))?;
+
+            // the first line lists the input values of the mesh
+            match numbers.len() {
+                n if size == 0 && n >= 2 => size = n,
+                3 => values.push([numbers[0], numbers[1], numbers[2]]),
+                _ => return Err(anyhow::anyhow!("Invalid 3dl line '{}'", line)),
+            }
+        }
+
+        let max = values.iter().flatten().fold(0.0_f64, |a, &b| a.max(b));
+        let scale = [10, 12, 14, 16]
+            .into_iter()
+            .map(|bits| ((1_u32 << bits) - 1) as f64)
+            .find(|&scale| max <= scale)
+            .ok_or(anyhow::anyhow!("Unsupported 3dl bit depth"))?;
+
+        lut_3d_entry_count(size)?;
+
+        // blue varies fastest in 3dl files
+        let mut data = vec![[0.0; 3]; values.len()];
+        for (i, value) in values.iter().enumerate() {
+            let (r, g, b) = (i / (size * size), i / size % size, i % size);
+            if let Some(entry) = data.get_mut(r + g * size + b * size * size) {
+                *entry = value.map(|v| v / scale);
+            }
+        }
+
+        Lut3D::new(size, data)
+    }
+    /// Reads a `.cube` or `.3dl` file.
+    pub fn read_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Lut3D> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            .ok_or(anyhow::anyhow!("Failed to get file extension"))?;
+        let text = std::fs::read_to_string(&filename)?;
+
+        match extension {
+            "cube" => Lut3D::parse_cube(&text),
+            "3dl" => Lut3D::parse_3dl(&text),
+
+            _ => Err(anyhow::anyhow!("Unsupported LUT extension: {}", extension)),
+        }
+    }
+    pub fn with_domain(mut self, domain_min: [f64; 3], domain_max: [f64; 3]) -> Self {
+        self.domain_min = domain_min;
+        self.domain_max = domain_max;
+
+        self
+    }
+    pub fn with_interpolation(mut self, interpolation: LutInterpolation) -> Self {
+        self.interpolation = interpolation;
+
+        self
+    }
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    pub fn interpolation(&self) -> LutInterpolation {
+        self.interpolation
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f64; 3] {
+        self.data[r + g * self.size + b * self.size * self.size]
+    }
+}
+
+impl Lut for Lut3D {
+    fn apply(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let last = self.size - 1;
+
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for c in 0..3 {
+            let pos = normalize(rgb[c], self.domain_min[c], self.domain_max[c]) * last as f64;
+            base[c] = (pos as usize).min(last - 1);
+            frac[c] = pos - base[c] as f64;
+        }
+
+        let [r, g, b] = base;
+        let [fr, fg, fb] = frac;
+        let corner = |dr: usize, dg: usize, db: usize| self.entry(r + dr, g + dg, b + db);
+
+        match self.interpolation {
+            LutInterpolation::Trilinear => {
+                let lerp = |a: [f64; 3], b: [f64; 3], t: f64| {
+                    std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t)
+                };
+
+                let c00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fr);
+                let c10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fr);
+                let c01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fr);
+                let c11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fr);
+
+                lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+            }
+            LutInterpolation::Tetrahedral => {
+                let c000 = corner(0, 0, 0);
+                let c111 = corner(1, 1, 1);
+
+                // the cube is split into six tetrahedra along its main diagonal
+                let (w, a, b) = if fr > fg {
+                    if fg > fb {
+                        ([fr, fg, fb], corner(1, 0, 0), corner(1, 1, 0))
+                    } else if fr > fb {
+                        ([fr, fb, fg], corner(1, 0, 0), corner(1, 0, 1))
+                    } else {
+                        ([fb, fr, fg], corner(0, 0, 1), corner(1, 0, 1))
+                    }
+                } else if fb > fg {
+                    ([fb, fg, fr], corner(0, 0, 1), corner(0, 1, 1))
+                } else if fb > fr {
+                    ([fg, fb, fr], corner(0, 1, 0), corner(0, 1, 1))
+                } else {
+                    ([fg, fr, fb], corner(0, 1, 0), corner(1, 1, 0))
+                };
+
+                std::array::from_fn(|c| {
+                    (1.0 - w[0]) * c000[c]
+                        + (w[0] - w[1]) * a[c]
+                        + (w[1] - w[2]) * b[c]
+                        + w[2] * c111[c]
+                })
+            }
+        }
+    }
+}
+
+fn normalize(value: f64, min: f64, max: f64) -> f64 {
+    ((value - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+struct CubeFile {
+    title: Option<String>,
+    size_1d: usize,
+    size_3d: usize,
+    domain_min: [f64; 3],
+    domain_max: [f64; 3],
+    data: Vec<[f64; 3]>,
+}
+
+fn parse_cube(text: &str) -> anyhow::Result<CubeFile> {
+    let mut cube = CubeFile {
+        title: None,
+        size_1d: 0,
+        size_3d: 0,
+        domain_min: [0.0; 3],
+        domain_max: [1.0; 3],
+        data: Vec::new(),
+    };
+
+    let parse_numbers = |values: &[&str], line: &str| -> anyhow::Result<Vec<f64>> {
+        values
+            .iter()
+            .map(|v| v.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!("Invalid cube line '{}': {}", line, e))
+    };
+    let parse_triple = |values: &[&str], line: &str| -> anyhow::Result<[f64; 3]> {
+        parse_numbers(values, line)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid cube line '{}'", line))
+    };
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens[0] {
+            "TITLE" => {
+                cube.title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string())
+            }
+            "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                let size = tokens
+                    .get(1)
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .ok_or(anyhow::anyhow!("Invalid cube line '{}'", line))?;
+                match tokens[0] {
+                    "LUT_1D_SIZE" => cube.size_1d = size,
+                    _ => cube.size_3d = size,
+                }
+            }
+            "DOMAIN_MIN" => cube.domain_min = parse_triple(&tokens[1..], line)?,
+            "DOMAIN_MAX" => cube.domain_max = parse_triple(&tokens[1..], line)?,
+            "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                let range = parse_numbers(&tokens[1..], line)?;
+                if range.len() != 2 {
+                    return Err(anyhow::anyhow!("Invalid cube line '{}'", line));
+                }
+                cube.domain_min = [range[0]; 3];
+                cube.domain_max = [range[1]; 3];
+            }
+            token if token.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                // unknown keywords are ignored, as the format allows
+            }
+            _ => cube.data.push(parse_triple(&tokens, line)?),
+        }
+    }
+
+    Ok(cube)
+}
+
+/// Entry count of a 3D LUT of the given size, rejecting sizes outside `2..=MAX_LUT_3D_SIZE`.
+fn lut_3d_entry_count(size: usize) -> anyhow::Result<usize> {
+    if !(2..=MAX_LUT_3D_SIZE).contains(&size) {
+        return Err(anyhow::anyhow!(
+            "3D LUT size must be between 2 and {}, got {}",
+            MAX_LUT_3D_SIZE,
+            size
+        ));
+    }
+
+    size.checked_mul(size)
+        .and_then(|count| count.checked_mul(size))
+        .ok_or(anyhow::anyhow!("3D LUT size {} is too large", size))
+}
+
+/// Applies `lut` to the first three channels of a float image.
+pub(crate) fn apply_lut(from: &Image, to: &mut Image, lut: &impl Lut) -> anyhow::Result<()> {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let convert = |rgb: [f64; 3]| lut.apply(rgb);
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            convert_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, convert)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            convert_pixels::<f64>(from, to, |v| v, |v| v, convert)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "LUT application requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
@@ -75,7 +75,7 @@ impl Transform2D {
     }
 }
 
-impl<'a, 'b> Mul<&'b Transform2D> for &'a Transform2D {
+impl<'b> Mul<&'b Transform2D> for &Transform2D {
     type Output = Transform2D;
 
     fn mul(self, rhs: &'b Transform2D) -> Self::Output {
@@ -111,6 +111,32 @@ impl PartialEq for Transform2D {
 
 impl Eq for Transform2D {}
 
+/// Pixel rectangle, with its origin at the top left corner.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub(crate) struct Vert2D(pub [f32; 2], pub [f32; 2]);
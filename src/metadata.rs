@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+
+use crate::exif::Exif;
+
+/// Unit of the pixel density of an image.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ResolutionUnit {
+    /// Only the aspect ratio of the pixels is known.
+    None,
+    #[default]
+    Inch,
+    Centimeter,
+}
+
+/// Pixel density along each axis, in pixels per unit.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Resolution {
+    pub x: f64,
+    pub y: f64,
+    pub unit: ResolutionUnit,
+}
+
+impl Resolution {
+    pub fn new(x: f64, y: f64, unit: ResolutionUnit) -> Self {
+        Self { x, y, unit }
+    }
+    /// Same density along both axes, in pixels per inch.
+    pub fn dpi(dpi: f64) -> Self {
+        Self::new(dpi, dpi, ResolutionUnit::Inch)
+    }
+}
+
+/// Value of a TIFF tag, named after its field type.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TagValue {
+    Byte(Vec<u8>),
+    /// Must be ASCII without NUL characters to be saved.
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    /// Numerator and denominator pairs.
+    Rational(Vec<[u32; 2]>),
+    SignedByte(Vec<i8>),
+    /// Opaque bytes, e.g. UTF-8 text or serialized data.
+    Undefined(Vec<u8>),
+    SignedShort(Vec<i16>),
+    SignedLong(Vec<i32>),
+    /// Signed numerator and denominator pairs.
+    SignedRational(Vec<[i32; 2]>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    /// 64 bit integers and offsets, BigTIFF only.
+    Long8(Vec<u64>),
+}
+
+/// CIE xy chromaticities of the white point and primaries, as stored in a PNG.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Chromaticities {
+    pub white: [f64; 2],
+    pub red: [f64; 2],
+    pub green: [f64; 2],
+    pub blue: [f64; 2],
+}
+
+/// Rendering intent of an sRGB PNG.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum RenderingIntent {
+    #[default]
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+/// Language of an international text chunk.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct InternationalText {
+    /// e.g. `en-US`, empty when unknown.
+    pub language_tag: String,
+    /// Keyword translated into the language, UTF-8.
+    pub translated_keyword: String,
+}
+
+/// Keyword and text pair of a PNG.
+///
+/// Saved as `tEXt`, or `zTXt` when compressed, which only hold Latin-1 text,
+/// and as UTF-8 `iTXt` when international.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextChunk {
+    /// 1 to 79 Latin-1 characters.
+    pub keyword: String,
+    pub text: String,
+    pub compressed: bool,
+    pub international: Option<InternationalText>,
+}
+
+impl TextChunk {
+    pub fn new(keyword: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            keyword: keyword.into(),
+            text: text.into(),
+            compressed: false,
+            international: None,
+        }
+    }
+    pub fn with_compression(mut self, compressed: bool) -> Self {
+        self.compressed = compressed;
+
+        self
+    }
+    pub fn with_international(mut self, international: InternationalText) -> Self {
+        self.international = Some(international);
+
+        self
+    }
+}
+
+/// Descriptive metadata carried along with the pixels of an image.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metadata {
+    pub resolution: Option<Resolution>,
+    pub software: Option<String>,
+    /// Creation date, formatted as `YYYY:MM:DD HH:MM:SS`.
+    pub date_time: Option<String>,
+    pub image_description: Option<String>,
+    pub artist: Option<String>,
+    /// Encoding gamma of a PNG, 1/2.2 for an image meant for a 2.2 display gamma.
+    pub gamma: Option<f64>,
+    pub chromaticities: Option<Chromaticities>,
+    /// Marks a PNG as sRGB, the gamma and chromaticities are then only written
+    /// if they are the ones of sRGB.
+    pub srgb_intent: Option<RenderingIntent>,
+    /// PNG text chunks, apart from the ones holding the software, description,
+    /// artist and date fields.
+    pub text_chunks: Vec<TextChunk>,
+    /// Camera metadata, read from and written to JPEG, PNG and TIFF.
+    pub exif: Option<Exif>,
+    /// TIFF tags not described by the other fields, by tag number.
+    pub custom_tags: BTreeMap<u16, TagValue>,
+}
+
+impl Metadata {
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+
+        self
+    }
+    pub fn with_software(mut self, software: impl Into<String>) -> Self {
+        self.software = Some(software.into());
+
+        self
+    }
+    pub fn with_date_time(mut self, date_time: impl Into<String>) -> Self {
+        self.date_time = Some(date_time.into());
+
+        self
+    }
+    pub fn with_image_description(mut self, image_description: impl Into<String>) -> Self {
+        self.image_description = Some(image_description.into());
+
+        self
+    }
+    pub fn with_artist(mut self, artist: impl Into<String>) -> Self {
+        self.artist = Some(artist.into());
+
+        self
+    }
+    pub fn with_gamma(mut self, gamma: f64) -> Self {
+        self.gamma = Some(gamma);
+
+        self
+    }
+    pub fn with_chromaticities(mut self, chromaticities: Chromaticities) -> Self {
+        self.chromaticities = Some(chromaticities);
+
+        self
+    }
+    pub fn with_srgb_intent(mut self, srgb_intent: RenderingIntent) -> Self {
+        self.srgb_intent = Some(srgb_intent);
+
+        self
+    }
+    pub fn with_text_chunk(mut self, text_chunk: TextChunk) -> Self {
+        self.text_chunks.push(text_chunk);
+
+        self
+    }
+    pub fn with_exif(mut self, exif: Exif) -> Self {
+        self.exif = Some(exif);
+
+        self
+    }
+    /// Adds a custom tag, private tags being numbered from 65000.
+    pub fn with_custom_tag(mut self, tag: u16, value: TagValue) -> Self {
+        self.custom_tags.insert(tag, value);
+
+        self
+    }
+
+    pub fn custom_tag(&self, tag: u16) -> Option<&TagValue> {
+        self.custom_tags.get(&tag)
+    }
+    /// Text of the first text chunk with this keyword.
+    pub fn text(&self, keyword: &str) -> Option<&str> {
+        self.text_chunks
+            .iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.as_str())
+    }
+}
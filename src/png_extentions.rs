@@ -0,0 +1,673 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::Duration;
+
+use bytemuck::Pod;
+use num_traits::AsPrimitive;
+use png::text_metadata::{ITXtChunk, TEXtChunk, ZTXtChunk};
+use png::{
+    BitDepth, BlendOp, ColorType, Compression, DisposeOp, Filter, FrameControl, PixelDimensions,
+    ScaledFloat, SourceChromaticities, SrgbRenderingIntent, Transformations, Unit,
+};
+
+use crate::animation::{Animation, Blend, Compositor, Dispose, Frame, FrameRect};
+use crate::color_format::*;
+use crate::exif::Exif;
+use crate::image::{Image, ImageDesc, Layout};
+use crate::metadata::{
+    Chromaticities, InternationalText, Metadata, RenderingIntent, Resolution, ResolutionUnit,
+    TextChunk,
+};
+use crate::save_options::{PngCompression, PngFilter, PngOptions};
+
+/// Registered keywords holding the text fields of [`Metadata`].
+const SOFTWARE_KEYWORD: &str = "Software";
+const DESCRIPTION_KEYWORD: &str = "Description";
+const AUTHOR_KEYWORD: &str = "Author";
+const CREATION_TIME_KEYWORD: &str = "Creation Time";
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// Keyword of the text chunk holding the original format, scale and offset of a packed PNG.
+const PACKED_RANGE_KEYWORD: &str = "imaginarium:packed-range";
+
+pub(crate) fn load_png<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(filename)?));
+    // palettes, low bit depths and transparency chunks are expanded to 8 bit channels
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+
+    let mut bytes = vec![
+        0u8;
+        reader
+            .output_buffer_size()
+            .ok_or(anyhow::anyhow!("PNG is too large"))?
+    ];
+    let frame = reader.next_frame(&mut bytes)?;
+    bytes.truncate(frame.buffer_size());
+    // text chunks may follow the image data
+    reader.finish()?;
+
+    let (color_type, bit_depth) = reader.output_color_type();
+    let channel_count = match color_type {
+        // @formatter:off
+        ColorType::Grayscale => ChannelCount::Gray,
+        ColorType::GrayscaleAlpha => ChannelCount::GrayAlpha,
+        ColorType::Rgb => ChannelCount::Rgb,
+        ColorType::Rgba => ChannelCount::Rgba,
+        ColorType::Indexed => return Err(anyhow::anyhow!("Unexpanded PNG palette")),
+        // @formatter:on
+    };
+    let channel_size = match bit_depth {
+        BitDepth::Sixteen => {
+            swap_big_endian(&mut bytes);
+            ChannelSize::_16bit
+        }
+        BitDepth::Eight => ChannelSize::_8bit,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported PNG bit depth: {:?}",
+                bit_depth
+            ))
+        }
+    };
+
+    let info = reader.info();
+    let desc = ImageDesc::new(
+        frame.width,
+        frame.height,
+        ColorFormat::from((channel_count, channel_size, ChannelType::UInt)),
+    );
+
+    let image = Image {
+        desc,
+        bytes,
+        icc_profile: info.icc_profile.as_ref().map(|icc| icc.to_vec()),
+        metadata: read_metadata(info)?,
+    };
+
+    if channel_size == ChannelSize::_16bit && image.metadata.text(PACKED_RANGE_KEYWORD).is_some() {
+        unpack_range(image)
+    } else {
+        Ok(image)
+    }
+}
+
+fn read_metadata(info: &png::Info) -> anyhow::Result<Metadata> {
+    let mut metadata = Metadata {
+        // a malformed EXIF block does not prevent reading the pixels
+        exif: info
+            .exif_metadata
+            .as_ref()
+            .and_then(|exif| Exif::parse(exif).ok()),
+        gamma: info.gama_chunk.map(|gamma| gamma.into_value() as f64),
+        chromaticities: info.chrm_chunk.map(|chrm| {
+            let xy =
+                |(x, y): (ScaledFloat, ScaledFloat)| [x.into_value() as f64, y.into_value() as f64];
+            Chromaticities {
+                white: xy(chrm.white),
+                red: xy(chrm.red),
+                green: xy(chrm.green),
+                blue: xy(chrm.blue),
+            }
+        }),
+        srgb_intent: info.srgb.map(|intent| match intent {
+            // @formatter:off
+            SrgbRenderingIntent::Perceptual => RenderingIntent::Perceptual,
+            SrgbRenderingIntent::RelativeColorimetric => RenderingIntent::RelativeColorimetric,
+            SrgbRenderingIntent::Saturation => RenderingIntent::Saturation,
+            SrgbRenderingIntent::AbsoluteColorimetric => RenderingIntent::AbsoluteColorimetric,
+            // @formatter:on
+        }),
+        resolution: info.pixel_dims.map(|dims| match dims.unit {
+            Unit::Meter => Resolution::new(
+                dims.xppu as f64 / 100.0,
+                dims.yppu as f64 / 100.0,
+                ResolutionUnit::Centimeter,
+            ),
+            Unit::Unspecified => {
+                Resolution::new(dims.xppu as f64, dims.yppu as f64, ResolutionUnit::None)
+            }
+        }),
+        ..Metadata::default()
+    };
+
+    let mut text_chunks: Vec<TextChunk> = Vec::new();
+    for chunk in &info.uncompressed_latin1_text {
+        text_chunks.push(TextChunk::new(&chunk.keyword, &chunk.text));
+    }
+    for chunk in &info.compressed_latin1_text {
+        text_chunks.push(TextChunk::new(&chunk.keyword, chunk.get_text()?).with_compression(true));
+    }
+    for chunk in &info.utf8_text {
+        text_chunks.push(
+            TextChunk::new(&chunk.keyword, chunk.get_text()?)
+                .with_compression(chunk.compressed)
+                .with_international(InternationalText {
+                    language_tag: chunk.language_tag.clone(),
+                    translated_keyword: chunk.translated_keyword.clone(),
+                }),
+        );
+    }
+
+    for chunk in text_chunks {
+        let field = match chunk.keyword.as_str() {
+            // @formatter:off
+            SOFTWARE_KEYWORD => &mut metadata.software,
+            DESCRIPTION_KEYWORD => &mut metadata.image_description,
+            AUTHOR_KEYWORD => &mut metadata.artist,
+            CREATION_TIME_KEYWORD => &mut metadata.date_time,
+            // @formatter:on
+            _ => {
+                metadata.text_chunks.push(chunk);
+                continue;
+            }
+        };
+        field.get_or_insert(chunk.text);
+    }
+
+    Ok(metadata)
+}
+
+pub(crate) fn save_png<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &PngOptions,
+) -> anyhow::Result<()> {
+    if image.desc.layout() != Layout::Interleaved {
+        return save_png(
+            &image.clone().convert_layout(Layout::Interleaved)?,
+            filename,
+            options,
+        );
+    }
+
+    let color_format = image.desc.color_format();
+    let packable = color_format.channel_type != ChannelType::UInt
+        || color_format.channel_size.byte_count() > 2;
+    if options.pack_range && packable {
+        return save_png(&pack_range(image), filename, options);
+    }
+
+    if color_format.channel_type != ChannelType::UInt {
+        return Err(anyhow::anyhow!(
+            "Unsupported PNG channel type: {:?}",
+            color_format.channel_type
+        ));
+    }
+    let bit_depth = match color_format.channel_size {
+        ChannelSize::_8bit => BitDepth::Eight,
+        ChannelSize::_16bit => BitDepth::Sixteen,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported PNG channel size: {:?}",
+                color_format.channel_size
+            ))
+        }
+    };
+    let color_type = match color_format.channel_count {
+        // @formatter:off
+        ChannelCount::Gray => ColorType::Grayscale,
+        ChannelCount::GrayAlpha => ColorType::GrayscaleAlpha,
+        ChannelCount::Rgb => ColorType::Rgb,
+        ChannelCount::Rgba => ColorType::Rgba,
+        // @formatter:on
+    };
+
+    let mut info = png::Info::with_size(image.desc.width(), image.desc.height());
+    info.color_type = color_type;
+    info.bit_depth = bit_depth;
+    info.icc_profile = image.icc_profile.as_deref().map(Into::into);
+    info.exif_metadata = image
+        .metadata
+        .exif
+        .as_ref()
+        .map(|exif| exif.to_bytes().into());
+    write_metadata(&mut info, &image.metadata);
+
+    let file = BufWriter::new(File::create(filename)?);
+    let mut encoder = png::Encoder::with_info(file, info)?;
+    encoder.set_compression(compression(options.compression));
+    encoder.set_filter(filter(options.filter));
+
+    let mut writer = encoder.write_header()?;
+    let row_size = (image.desc.width() * image.bytes_per_pixel()) as usize;
+    let mut data = Vec::with_capacity(row_size * image.desc.height() as usize);
+    for row in image.bytes.chunks(image.desc.stride() as usize) {
+        data.extend_from_slice(&row[..row_size]);
+    }
+    if bit_depth == BitDepth::Sixteen {
+        swap_big_endian(&mut data);
+    }
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Reads the frames of an APNG, or a PNG as a single frame, composited on the canvas.
+/// The default image is skipped when it is not part of the animation.
+pub(crate) fn load_apng<P: AsRef<Path>>(filename: P) -> anyhow::Result<Animation> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(filename)?));
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+
+    let info = reader.info();
+    let (width, height) = (info.width, info.height);
+    let (frame_count, loop_count) = match info.animation_control {
+        Some(control) => (control.num_frames, control.num_plays),
+        None => (1, 1),
+    };
+    let default_is_frame = info.animation_control.is_none() || info.frame_control.is_some();
+
+    let mut bytes = vec![
+        0u8;
+        reader
+            .output_buffer_size()
+            .ok_or(anyhow::anyhow!("PNG is too large"))?
+    ];
+    if !default_is_frame {
+        reader.next_frame(&mut bytes)?;
+    }
+
+    // the frame count comes from the file, so the frames are only stored once decoded
+    let mut compositor = Compositor::new(width, height)?;
+    let mut frames = Vec::new();
+    for _ in 0..frame_count {
+        let output = reader.next_frame(&mut bytes)?;
+        let channel_count = match output.color_type {
+            // @formatter:off
+            ColorType::Grayscale => ChannelCount::Gray,
+            ColorType::GrayscaleAlpha => ChannelCount::GrayAlpha,
+            ColorType::Rgb => ChannelCount::Rgb,
+            ColorType::Rgba => ChannelCount::Rgba,
+            ColorType::Indexed => return Err(anyhow::anyhow!("Unexpanded PNG palette")),
+            // @formatter:on
+        };
+        let pixels = Image::new_with_data(
+            ImageDesc::new(
+                output.width,
+                output.height,
+                ColorFormat::from((channel_count, ChannelSize::_8bit, ChannelType::UInt)),
+            ),
+            bytes[..output.buffer_size()].to_vec(),
+        )?
+        .convert(ColorFormat::RGBA_U8)?;
+
+        let control = reader.info().frame_control.unwrap_or(FrameControl {
+            width,
+            height,
+            ..FrameControl::default()
+        });
+        let rect = FrameRect {
+            x: control.x_offset,
+            y: control.y_offset,
+            width: output.width,
+            height: output.height,
+        };
+        let blend = match control.blend_op {
+            BlendOp::Source => Blend::Source,
+            BlendOp::Over => Blend::Over,
+        };
+        let dispose = match control.dispose_op {
+            // @formatter:off
+            DisposeOp::None => Dispose::None,
+            DisposeOp::Background => Dispose::Background,
+            DisposeOp::Previous => Dispose::Previous,
+            // @formatter:on
+        };
+        // a zero denominator counts hundredths of a second
+        let denominator = match control.delay_den {
+            0 => 100,
+            denominator => denominator as u64,
+        };
+
+        frames.push(Frame::new(
+            compositor.compose(&pixels.bytes, rect, blend, dispose)?,
+            Duration::from_nanos(control.delay_num as u64 * 1_000_000_000 / denominator),
+        ));
+    }
+
+    Ok(Animation::new(frames, loop_count))
+}
+
+/// Writes the frames as an 8 bit RGBA APNG, every frame covering the canvas.
+pub(crate) fn save_apng<P: AsRef<Path>>(
+    animation: &Animation,
+    filename: P,
+    options: &PngOptions,
+) -> anyhow::Result<()> {
+    let ((width, height), images) = animation.rgba_frames()?;
+
+    let file = BufWriter::new(File::create(filename)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_compression(compression(options.compression));
+    encoder.set_filter(filter(options.filter));
+    encoder.set_animated(images.len() as u32, animation.loop_count)?;
+
+    let mut writer = encoder.write_header()?;
+    for (frame, image) in animation.frames.iter().zip(&images) {
+        // milliseconds, or seconds past the 16 bit range
+        let milliseconds = frame.delay.as_millis();
+        let (numerator, denominator) = match u16::try_from(milliseconds) {
+            Ok(milliseconds) => (milliseconds, 1000),
+            Err(_) => (u16::try_from(frame.delay.as_secs()).unwrap_or(u16::MAX), 1),
+        };
+        writer.set_frame_delay(numerator, denominator)?;
+        writer.set_blend_op(match frame.blend {
+            Blend::Source => BlendOp::Source,
+            Blend::Over => BlendOp::Over,
+        })?;
+        writer.set_dispose_op(match frame.dispose {
+            // @formatter:off
+            Dispose::None => DisposeOp::None,
+            Dispose::Background => DisposeOp::Background,
+            Dispose::Previous => DisposeOp::Previous,
+            // @formatter:on
+        })?;
+        writer.write_image_data(&image.bytes[..image.desc.size_in_bytes() as usize])?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+fn write_metadata(info: &mut png::Info, metadata: &Metadata) {
+    info.gama_chunk = metadata.gamma.map(|gamma| ScaledFloat::new(gamma as f32));
+    info.source_gamma = info.gama_chunk;
+    info.chrm_chunk = metadata.chromaticities.map(|chromaticities| {
+        let xy = |[x, y]: [f64; 2]| (x as f32, y as f32);
+        SourceChromaticities::new(
+            xy(chromaticities.white),
+            xy(chromaticities.red),
+            xy(chromaticities.green),
+            xy(chromaticities.blue),
+        )
+    });
+    info.source_chromaticities = info.chrm_chunk;
+    info.srgb = metadata.srgb_intent.map(|intent| match intent {
+        // @formatter:off
+        RenderingIntent::Perceptual => SrgbRenderingIntent::Perceptual,
+        RenderingIntent::RelativeColorimetric => SrgbRenderingIntent::RelativeColorimetric,
+        RenderingIntent::Saturation => SrgbRenderingIntent::Saturation,
+        RenderingIntent::AbsoluteColorimetric => SrgbRenderingIntent::AbsoluteColorimetric,
+        // @formatter:on
+    });
+    info.pixel_dims = metadata.resolution.map(|resolution| {
+        let (scale, unit) = match resolution.unit {
+            // @formatter:off
+            ResolutionUnit::None => (1.0, Unit::Unspecified),
+            ResolutionUnit::Inch => (1.0 / METERS_PER_INCH, Unit::Meter),
+            ResolutionUnit::Centimeter => (100.0, Unit::Meter),
+            // @formatter:on
+        };
+        PixelDimensions {
+            xppu: (resolution.x * scale).round() as u32,
+            yppu: (resolution.y * scale).round() as u32,
+            unit,
+        }
+    });
+
+    let fields = [
+        (SOFTWARE_KEYWORD, &metadata.software),
+        (DESCRIPTION_KEYWORD, &metadata.image_description),
+        (AUTHOR_KEYWORD, &metadata.artist),
+        (CREATION_TIME_KEYWORD, &metadata.date_time),
+    ];
+    for (keyword, text) in fields {
+        if let Some(text) = text {
+            let mut chunk = TextChunk::new(keyword, text);
+            // Latin-1 chunks cannot hold every character
+            if text.chars().any(|c| c as u32 > 255) {
+                chunk = chunk.with_international(InternationalText::default());
+            }
+            add_text_chunk(info, &chunk);
+        }
+    }
+    for chunk in &metadata.text_chunks {
+        add_text_chunk(info, chunk);
+    }
+}
+
+fn add_text_chunk(info: &mut png::Info, chunk: &TextChunk) {
+    match &chunk.international {
+        Some(international) => {
+            let mut itxt = ITXtChunk::new(&chunk.keyword, &chunk.text);
+            itxt.compressed = chunk.compressed;
+            itxt.language_tag = international.language_tag.clone();
+            itxt.translated_keyword = international.translated_keyword.clone();
+            info.utf8_text.push(itxt);
+        }
+        None if chunk.compressed => info
+            .compressed_latin1_text
+            .push(ZTXtChunk::new(&chunk.keyword, &chunk.text)),
+        None => info
+            .uncompressed_latin1_text
+            .push(TEXtChunk::new(&chunk.keyword, &chunk.text)),
+    }
+}
+
+/// Quantizes the samples of an interleaved image to 16 bit between their finite
+/// minimum and maximum, recording the original format, scale and offset.
+fn pack_range(image: &Image) -> Image {
+    let color_format = image.desc.color_format();
+    let values = samples_to_f64(image);
+
+    let (min, max) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    let offset = if min.is_finite() { min } else { 0.0 };
+    let mut scale = if max > min {
+        (max - min) / u16::MAX as f64
+    } else {
+        1.0
+    };
+    // integers spanning fewer than 65536 values are kept exactly
+    if color_format.channel_type != ChannelType::Float {
+        scale = scale.max(1.0);
+    }
+
+    let packed: Vec<u16> = values
+        .iter()
+        .map(|&v| ((v - offset) / scale).round() as u16)
+        .collect();
+
+    let desc = ImageDesc::new(
+        image.desc.width(),
+        image.desc.height(),
+        ColorFormat::from((
+            color_format.channel_count,
+            ChannelSize::_16bit,
+            ChannelType::UInt,
+        )),
+    );
+    let mut metadata = image.metadata.clone();
+    metadata.text_chunks.push(TextChunk::new(
+        PACKED_RANGE_KEYWORD,
+        format!(
+            "type={} scale={:?} offset={:?}",
+            sample_type_name(color_format),
+            scale,
+            offset
+        ),
+    ));
+
+    Image {
+        desc,
+        bytes: bytemuck::cast_slice(&packed).to_vec(),
+        icc_profile: image.icc_profile.clone(),
+        metadata,
+    }
+}
+
+/// Restores the format and range recorded by [`pack_range`].
+fn unpack_range(mut image: Image) -> anyhow::Result<Image> {
+    let text = image
+        .metadata
+        .text(PACKED_RANGE_KEYWORD)
+        .unwrap_or_default();
+    let field = |name: &str| {
+        text.split_whitespace()
+            .find_map(|field| field.strip_prefix(name)?.strip_prefix('='))
+            .ok_or(anyhow::anyhow!(
+                "Missing {} in packed PNG range: {}",
+                name,
+                text
+            ))
+    };
+
+    let (channel_type, channel_size) = sample_type_from_name(field("type")?)?;
+    let scale: f64 = field("scale")?.parse()?;
+    let offset: f64 = field("offset")?.parse()?;
+
+    let values: Vec<f64> = bytemuck::cast_slice::<u8, u16>(&image.bytes)
+        .iter()
+        .map(|&v| v as f64 * scale + offset)
+        .collect();
+    let color_format = ColorFormat::from((
+        image.desc.color_format().channel_count,
+        channel_size,
+        channel_type,
+    ));
+
+    image
+        .metadata
+        .text_chunks
+        .retain(|chunk| chunk.keyword != PACKED_RANGE_KEYWORD);
+    image.desc = ImageDesc::new(image.desc.width(), image.desc.height(), color_format);
+    image.bytes = samples_from_f64(&values, color_format);
+
+    Ok(image)
+}
+
+fn sample_type_name(color_format: ColorFormat) -> String {
+    let name = match color_format.channel_type {
+        // @formatter:off
+        ChannelType::UInt => "uint",
+        ChannelType::Int => "int",
+        ChannelType::Float => "float",
+        // @formatter:on
+    };
+
+    format!("{}{}", name, color_format.channel_size.byte_count() * 8)
+}
+
+fn sample_type_from_name(name: &str) -> anyhow::Result<(ChannelType, ChannelSize)> {
+    let bits = name.trim_start_matches(char::is_alphabetic);
+    let channel_type = match &name[..name.len() - bits.len()] {
+        // @formatter:off
+        "uint" => ChannelType::UInt,
+        "int" => ChannelType::Int,
+        "float" => ChannelType::Float,
+        // @formatter:on
+        _ => return Err(anyhow::anyhow!("Invalid packed PNG sample type: {}", name)),
+    };
+    let channel_size = match bits {
+        // @formatter:off
+        "8" => ChannelSize::_8bit,
+        "16" => ChannelSize::_16bit,
+        "32" => ChannelSize::_32bit,
+        "64" => ChannelSize::_64bit,
+        // @formatter:on
+        _ => return Err(anyhow::anyhow!("Invalid packed PNG sample type: {}", name)),
+    };
+    ColorFormat::from((ChannelCount::Gray, channel_size, channel_type)).validate()?;
+
+    Ok((channel_type, channel_size))
+}
+
+fn samples_to_f64(image: &Image) -> Vec<f64> {
+    fn cast<T: Pod + AsPrimitive<f64>>(bytes: &[u8]) -> Vec<f64> {
+        bytemuck::cast_slice::<u8, T>(bytes)
+            .iter()
+            .map(|v| v.as_())
+            .collect()
+    }
+
+    let bytes = &image.bytes[..image.desc.size_in_bytes() as usize];
+    let color_format = image.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::UInt, ChannelSize::_8bit) => cast::<u8>(bytes),
+        (ChannelType::UInt, ChannelSize::_16bit) => cast::<u16>(bytes),
+        (ChannelType::UInt, ChannelSize::_32bit) => cast::<u32>(bytes),
+        (ChannelType::UInt, ChannelSize::_64bit) => cast::<u64>(bytes),
+        (ChannelType::Int, ChannelSize::_8bit) => cast::<i8>(bytes),
+        (ChannelType::Int, ChannelSize::_16bit) => cast::<i16>(bytes),
+        (ChannelType::Int, ChannelSize::_32bit) => cast::<i32>(bytes),
+        (ChannelType::Int, ChannelSize::_64bit) => cast::<i64>(bytes),
+        (ChannelType::Float, ChannelSize::_32bit) => cast::<f32>(bytes),
+        (ChannelType::Float, _) => cast::<f64>(bytes),
+        // @formatter:on
+    }
+}
+
+/// Integer samples are rounded and saturated.
+fn samples_from_f64(values: &[f64], color_format: ColorFormat) -> Vec<u8> {
+    fn cast<T: Pod>(values: &[f64], round: bool) -> Vec<u8>
+    where
+        f64: AsPrimitive<T>,
+    {
+        let values: Vec<T> = values
+            .iter()
+            .map(|&v| if round { v.round() } else { v }.as_())
+            .collect();
+        bytemuck::cast_slice(&values).to_vec()
+    }
+
+    let round = color_format.channel_type != ChannelType::Float;
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::UInt, ChannelSize::_8bit) => cast::<u8>(values, round),
+        (ChannelType::UInt, ChannelSize::_16bit) => cast::<u16>(values, round),
+        (ChannelType::UInt, ChannelSize::_32bit) => cast::<u32>(values, round),
+        (ChannelType::UInt, ChannelSize::_64bit) => cast::<u64>(values, round),
+        (ChannelType::Int, ChannelSize::_8bit) => cast::<i8>(values, round),
+        (ChannelType::Int, ChannelSize::_16bit) => cast::<i16>(values, round),
+        (ChannelType::Int, ChannelSize::_32bit) => cast::<i32>(values, round),
+        (ChannelType::Int, ChannelSize::_64bit) => cast::<i64>(values, round),
+        (ChannelType::Float, ChannelSize::_32bit) => cast::<f32>(values, round),
+        (ChannelType::Float, _) => cast::<f64>(values, round),
+        // @formatter:on
+    }
+}
+
+/// Converts 16 bit samples between the big endian order of PNG and the native one.
+fn swap_big_endian(bytes: &mut [u8]) {
+    if cfg!(target_endian = "little") {
+        bytes
+            .chunks_exact_mut(2)
+            .for_each(|sample| sample.swap(0, 1));
+    }
+}
+
+fn compression(compression: PngCompression) -> Compression {
+    match compression {
+        // @formatter:off
+        PngCompression::None => Compression::NoCompression,
+        PngCompression::Fastest => Compression::Fastest,
+        PngCompression::Fast => Compression::Fast,
+        PngCompression::Balanced => Compression::Balanced,
+        PngCompression::High => Compression::High,
+        // @formatter:on
+    }
+}
+
+fn filter(filter: PngFilter) -> Filter {
+    match filter {
+        // @formatter:off
+        PngFilter::None => Filter::NoFilter,
+        PngFilter::Sub => Filter::Sub,
+        PngFilter::Up => Filter::Up,
+        PngFilter::Average => Filter::Avg,
+        PngFilter::Paeth => Filter::Paeth,
+        PngFilter::Adaptive => Filter::Adaptive,
+        // @formatter:on
+    }
+}
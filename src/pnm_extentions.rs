@@ -0,0 +1,436 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::image::{checked_image_size, Image, ImageDesc, Layout};
+use crate::save_options::PnmOptions;
+
+/// Longest line of the ASCII formats.
+const ASCII_LINE_LENGTH: usize = 70;
+
+/// Header tokens of a Netpbm file, skipping whitespace and `#` comments.
+struct Tokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&byte) = self.data.get(self.position) {
+            if byte == b'#' {
+                while self
+                    .data
+                    .get(self.position)
+                    .is_some_and(|&byte| byte != b'\n')
+                {
+                    self.position += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    fn next(&mut self) -> anyhow::Result<&'a str> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self
+            .data
+            .get(self.position)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(anyhow::anyhow!("Truncated Netpbm header"));
+        }
+
+        Ok(std::str::from_utf8(&self.data[start..self.position])?)
+    }
+    fn number(&mut self) -> anyhow::Result<u32> {
+        Ok(self.next()?.parse()?)
+    }
+    /// Skips the single whitespace ending the header of the binary formats.
+    fn raster(&self) -> &'a [u8] {
+        &self.data[(self.position + 1).min(self.data.len())..]
+    }
+    /// Reads the next ASCII PBM bit, which may not be separated from the next one.
+    fn bit(&mut self) -> anyhow::Result<u32> {
+        self.skip_whitespace();
+        let bit = match self.data.get(self.position) {
+            // @formatter:off
+            Some(b'0') => 0,
+            Some(b'1') => 1,
+            _ => return Err(anyhow::anyhow!("Invalid PBM pixel")),
+            // @formatter:on
+        };
+        self.position += 1;
+
+        Ok(bit)
+    }
+}
+
+/// Layout of the samples of a Netpbm file.
+struct Header {
+    width: u32,
+    height: u32,
+    channel_count: ChannelCount,
+    max_value: u32,
+    encoding: Encoding,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum Encoding {
+    /// Packed bits, 1 for black, P4.
+    Bits,
+    /// Decimal bits, 1 for black, P1.
+    AsciiBits,
+    /// Big endian samples of 1 or 2 bytes, P5, P6 and P7.
+    Binary,
+    /// Decimal samples, P2 and P3.
+    Ascii,
+}
+
+/// Reads a PBM, PGM, PPM or PAM file as 8 bit, or 16 bit when its maximum value
+/// exceeds 255. Samples are scaled to the full range of the channel size and
+/// PBM black and white to 0 and 255.
+pub(crate) fn load_pnm<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    let data = std::fs::read(filename)?;
+    let mut tokens = Tokens {
+        data: &data,
+        position: 0,
+    };
+
+    let magic = tokens.next()?;
+    let header = match magic {
+        "P1" | "P4" => Header {
+            width: tokens.number()?,
+            height: tokens.number()?,
+            channel_count: ChannelCount::Gray,
+            max_value: 1,
+            encoding: if magic == "P1" {
+                Encoding::AsciiBits
+            } else {
+                Encoding::Bits
+            },
+        },
+        "P2" | "P3" | "P5" | "P6" => Header {
+            width: tokens.number()?,
+            height: tokens.number()?,
+            channel_count: if magic == "P2" || magic == "P5" {
+                ChannelCount::Gray
+            } else {
+                ChannelCount::Rgb
+            },
+            max_value: tokens.number()?,
+            encoding: if magic == "P2" || magic == "P3" {
+                Encoding::Ascii
+            } else {
+                Encoding::Binary
+            },
+        },
+        "P7" => read_pam_header(&mut tokens)?,
+        _ => return Err(anyhow::anyhow!("Not a Netpbm file")),
+    };
+    if !(1..=u16::MAX as u32).contains(&header.max_value) {
+        return Err(anyhow::anyhow!(
+            "Invalid Netpbm maximum value: {}",
+            header.max_value
+        ));
+    }
+    let channel_size = if header.max_value > u8::MAX as u32 {
+        ChannelSize::_16bit
+    } else {
+        ChannelSize::_8bit
+    };
+    let color_format = ColorFormat::from((header.channel_count, channel_size, ChannelType::UInt));
+    let size = checked_image_size(
+        header.width,
+        header.height,
+        color_format.byte_count() as usize,
+    )
+    .ok_or(anyhow::anyhow!(
+        "Invalid Netpbm size: {}x{}",
+        header.width,
+        header.height
+    ))?;
+    let sample_count = size / channel_size.byte_count() as usize;
+    let desc = ImageDesc::new(header.width, header.height, color_format);
+
+    let samples: Vec<u32> = match header.encoding {
+        Encoding::Bits => {
+            let row_size = (header.width as usize).div_ceil(8);
+            let raster = row_size
+                .checked_mul(header.height as usize)
+                .and_then(|size| tokens.raster().get(..size))
+                .ok_or(anyhow::anyhow!("Truncated Netpbm pixels"))?;
+            raster
+                .chunks_exact(row_size)
+                .flat_map(|row| {
+                    (0..header.width as usize).map(|x| (row[x / 8] >> (7 - x % 8)) as u32 & 1)
+                })
+                .collect()
+        }
+        Encoding::AsciiBits => (0..sample_count)
+            .map(|_| tokens.bit())
+            .collect::<anyhow::Result<_>>()?,
+        Encoding::Binary => {
+            let sample_size = channel_size.byte_count() as usize;
+            let raster = tokens
+                .raster()
+                .get(..size)
+                .ok_or(anyhow::anyhow!("Truncated Netpbm pixels"))?;
+            raster
+                .chunks_exact(sample_size)
+                .map(|sample| match sample {
+                    [value] => *value as u32,
+                    _ => u16::from_be_bytes([sample[0], sample[1]]) as u32,
+                })
+                .collect()
+        }
+        Encoding::Ascii => (0..sample_count)
+            .map(|_| tokens.number())
+            .collect::<anyhow::Result<_>>()?,
+    };
+
+    let bits = matches!(header.encoding, Encoding::Bits | Encoding::AsciiBits);
+    let max = match channel_size {
+        ChannelSize::_8bit => u8::MAX as u32,
+        _ => u16::MAX as u32,
+    };
+    let scale = |value: u32| {
+        let value = value.min(header.max_value);
+        (value * max + header.max_value / 2) / header.max_value
+    };
+
+    let bytes = match channel_size {
+        ChannelSize::_8bit => samples
+            .iter()
+            .map(|&value| {
+                // PBM 1 is black
+                let value = if bits { 1 - value } else { value };
+                scale(value) as u8
+            })
+            .collect(),
+        _ => samples
+            .iter()
+            .flat_map(|&value| (scale(value) as u16).to_ne_bytes())
+            .collect(),
+    };
+
+    Image::new_with_data(desc, bytes)
+}
+
+fn read_pam_header(tokens: &mut Tokens) -> anyhow::Result<Header> {
+    let (mut width, mut height, mut depth, mut max_value) = (None, None, None, None);
+    let mut tuple_type = String::new();
+    loop {
+        match tokens.next()? {
+            "WIDTH" => width = Some(tokens.number()?),
+            "HEIGHT" => height = Some(tokens.number()?),
+            "DEPTH" => depth = Some(tokens.number()?),
+            "MAXVAL" => max_value = Some(tokens.number()?),
+            // the tuple type may span several lines, joined by spaces
+            "TUPLTYPE" => {
+                if !tuple_type.is_empty() {
+                    tuple_type.push(' ');
+                }
+                tuple_type.push_str(tokens.next()?);
+            }
+            "ENDHDR" => break,
+            field => return Err(anyhow::anyhow!("Unknown PAM header field: {}", field)),
+        }
+    }
+    let missing = |field| anyhow::anyhow!("Missing PAM header field: {}", field);
+    let depth = depth.ok_or_else(|| missing("DEPTH"))?;
+
+    // unknown tuple types are told apart by their depth
+    let channel_count = match (tuple_type.as_str(), depth) {
+        // @formatter:off
+        ("BLACKANDWHITE" | "GRAYSCALE", 1) => ChannelCount::Gray,
+        ("BLACKANDWHITE_ALPHA" | "GRAYSCALE_ALPHA", 2) => ChannelCount::GrayAlpha,
+        ("RGB", 3) => ChannelCount::Rgb,
+        ("RGB_ALPHA", 4) => ChannelCount::Rgba,
+        (
+            "BLACKANDWHITE"
+            | "GRAYSCALE"
+            | "BLACKANDWHITE_ALPHA"
+            | "GRAYSCALE_ALPHA"
+            | "RGB"
+            | "RGB_ALPHA",
+            _,
+        ) => {
+            return Err(anyhow::anyhow!(
+                "PAM depth {} does not match {}",
+                depth,
+                tuple_type
+            ))
+        }
+        (_, 1) => ChannelCount::Gray,
+        (_, 2) => ChannelCount::GrayAlpha,
+        (_, 3) => ChannelCount::Rgb,
+        (_, 4) => ChannelCount::Rgba,
+        _ => return Err(anyhow::anyhow!("Unsupported PAM depth: {}", depth)),
+        // @formatter:on
+    };
+
+    Ok(Header {
+        width: width.ok_or_else(|| missing("WIDTH"))?,
+        height: height.ok_or_else(|| missing("HEIGHT"))?,
+        channel_count,
+        max_value: max_value.ok_or_else(|| missing("MAXVAL"))?,
+        encoding: Encoding::Binary,
+    })
+}
+
+/// Writes the image in the Netpbm format of the extension: `pbm` for 8 bit gray,
+/// thresholded at half intensity, `pgm` for gray, `ppm` for RGB, `pnm` for either
+/// and `pam` for every channel count, all with 8 or 16 bit unsigned channels.
+/// PAM has no ASCII variant.
+pub(crate) fn save_pnm<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    extension: &str,
+    options: &PnmOptions,
+) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format.channel_type != ChannelType::UInt
+        || !matches!(
+            color_format.channel_size,
+            ChannelSize::_8bit | ChannelSize::_16bit
+        )
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported Netpbm color format: {}",
+            color_format
+        ));
+    }
+    let unsupported = || {
+        anyhow::anyhow!(
+            "Unsupported {} color format: {}",
+            extension.to_uppercase(),
+            color_format
+        )
+    };
+
+    let (width, height) = (image.desc.width(), image.desc.height());
+    let header = match (extension, color_format.channel_count) {
+        ("pbm", ChannelCount::Gray) if color_format.channel_size == ChannelSize::_8bit => {
+            format!(
+                "{}\n{} {}\n",
+                if options.ascii { "P1" } else { "P4" },
+                width,
+                height
+            )
+        }
+        ("pgm" | "pnm", ChannelCount::Gray) | ("ppm" | "pnm", ChannelCount::Rgb) => {
+            let magic = match (color_format.channel_count, options.ascii) {
+                // @formatter:off
+                (ChannelCount::Gray, true) => "P2",
+                (ChannelCount::Gray, false) => "P5",
+                (_, true) => "P3",
+                (_, false) => "P6",
+                // @formatter:on
+            };
+            format!(
+                "{}\n{} {}\n{}\n",
+                magic,
+                width,
+                height,
+                max_value(color_format)
+            )
+        }
+        ("pam", channel_count) => {
+            let tuple_type = match channel_count {
+                // @formatter:off
+                ChannelCount::Gray => "GRAYSCALE",
+                ChannelCount::GrayAlpha => "GRAYSCALE_ALPHA",
+                ChannelCount::Rgb => "RGB",
+                ChannelCount::Rgba => "RGB_ALPHA",
+                // @formatter:on
+            };
+            format!(
+                "P7\nWIDTH {}\nHEIGHT {}\nDEPTH {}\nMAXVAL {}\nTUPLTYPE {}\nENDHDR\n",
+                width,
+                height,
+                channel_count.channel_count(),
+                max_value(color_format),
+                tuple_type
+            )
+        }
+        _ => return Err(unsupported()),
+    };
+
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    let samples: Vec<u32> = match color_format.channel_size {
+        ChannelSize::_8bit => image.bytes[..image.desc.size_in_bytes() as usize]
+            .iter()
+            .map(|&value| value as u32)
+            .collect(),
+        _ => bytemuck::pod_collect_to_vec::<u8, u16>(
+            &image.bytes[..image.desc.size_in_bytes() as usize],
+        )
+        .into_iter()
+        .map(|value| value as u32)
+        .collect(),
+    };
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(header.as_bytes())?;
+    let ascii = options.ascii && extension != "pam";
+    if extension == "pbm" {
+        // 1 is black
+        let bits = samples.iter().map(|&value| (value < 128) as u8);
+        if ascii {
+            let bits: Vec<u8> = bits.map(|bit| b'0' + bit).collect();
+            for line in bits.chunks(ASCII_LINE_LENGTH) {
+                writer.write_all(line)?;
+                writer.write_all(b"\n")?;
+            }
+        } else {
+            let bits: Vec<u8> = bits.collect();
+            for row in bits.chunks_exact(width as usize) {
+                for byte in row.chunks(8) {
+                    let packed = byte
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |packed, (i, &bit)| packed | bit << (7 - i));
+                    writer.write_all(&[packed])?;
+                }
+            }
+        }
+    } else if ascii {
+        let mut line = String::new();
+        for value in samples {
+            let text = value.to_string();
+            if line.len() + text.len() + 1 > ASCII_LINE_LENGTH {
+                writeln!(writer, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&text);
+        }
+        writeln!(writer, "{}", line)?;
+    } else {
+        for value in samples {
+            match color_format.channel_size {
+                ChannelSize::_8bit => writer.write_all(&[value as u8])?,
+                _ => writer.write_all(&(value as u16).to_be_bytes())?,
+            }
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn max_value(color_format: ColorFormat) -> u32 {
+    match color_format.channel_size {
+        ChannelSize::_8bit => u8::MAX as u32,
+        _ => u16::MAX as u32,
+    }
+}
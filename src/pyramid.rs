@@ -0,0 +1,164 @@
+use std::f64::consts::PI;
+
+use bytemuck::Pod;
+
+use crate::color_format::*;
+use crate::image::Image;
+
+/// Filter used to downsample the levels of an image pyramid.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ResampleFilter {
+    /// Average of the covered pixels.
+    #[default]
+    Box,
+    /// Tent filter, linear interpolation when upsampling.
+    Triangle,
+    /// Windowed sinc with three lobes, the sharpest but may ring on edges.
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    fn radius(&self) -> f64 {
+        match self {
+            ResampleFilter::Box => 0.5,
+            ResampleFilter::Triangle => 1.0,
+            ResampleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, t: f64) -> f64 {
+        let t = t.abs();
+
+        match self {
+            ResampleFilter::Box => {
+                if t < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResampleFilter::Triangle => (1.0 - t).max(0.0),
+            ResampleFilter::Lanczos3 => {
+                if t < 3.0 {
+                    sinc(t) * sinc(t / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// First source sample and normalized weights of each destination sample along an axis.
+fn axis_weights(src_len: u32, dst_len: u32, filter: ResampleFilter) -> Vec<(usize, Vec<f64>)> {
+    let scale = src_len as f64 / dst_len as f64;
+    // the filter is stretched when downsampling
+    let filter_scale = scale.max(1.0);
+    let support = filter.radius() * filter_scale;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(src_len as usize);
+
+            let mut weights: Vec<f64> = (start..end)
+                .map(|j| filter.weight((j as f64 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f64 = weights.iter().sum();
+            if sum > 0.0 {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+
+            (start, weights)
+        })
+        .collect()
+}
+
+/// Resamples an interleaved float image into the dimensions of `to`.
+pub(crate) fn resample(from: &Image, to: &mut Image, filter: ResampleFilter) -> anyhow::Result<()> {
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            resample_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, filter)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            resample_pixels::<f64>(from, to, |v| v, |v| v, filter)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Resampling requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn resample_pixels<T>(
+    from: &Image,
+    to: &mut Image,
+    to_f64: fn(T) -> f64,
+    from_f64: fn(f64) -> T,
+    filter: ResampleFilter,
+) where
+    T: Copy + Pod,
+{
+    let channels = from.desc.color_format().channel_count.channel_count() as usize;
+    let (from_w, from_h) = (from.desc.width() as usize, from.desc.height() as usize);
+    let to_w = to.desc.width() as usize;
+
+    let columns = axis_weights(from.desc.width(), to.desc.width(), filter);
+    let rows = axis_weights(from.desc.height(), to.desc.height(), filter);
+
+    // horizontal pass, keeping the source rows
+    let mut horizontal = vec![0.0f64; to_w * from_h * channels];
+    for y in 0..from_h {
+        let row_offset = y * from.desc.stride() as usize;
+        let row: &[T] = bytemuck::cast_slice(
+            &from.bytes[row_offset..row_offset + from_w * channels * size_of::<T>()],
+        );
+
+        for (x, (start, weights)) in columns.iter().enumerate() {
+            let out = &mut horizontal[(y * to_w + x) * channels..][..channels];
+            for (i, weight) in weights.iter().enumerate() {
+                let pixel = &row[(start + i) * channels..][..channels];
+                for c in 0..channels {
+                    out[c] += to_f64(pixel[c]) * weight;
+                }
+            }
+        }
+    }
+
+    // vertical pass
+    for (y, (start, weights)) in rows.iter().enumerate() {
+        let row_offset = y * to.desc.stride() as usize;
+        let row: &mut [T] = bytemuck::cast_slice_mut(
+            &mut to.bytes[row_offset..row_offset + to_w * channels * size_of::<T>()],
+        );
+
+        for x in 0..to_w {
+            for c in 0..channels {
+                let value: f64 = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, weight)| horizontal[((start + i) * to_w + x) * channels + c] * weight)
+                    .sum();
+                row[x * channels + c] = from_f64(value);
+            }
+        }
+    }
+}
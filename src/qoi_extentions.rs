@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{Image, ImageDesc, Layout};
+
+const MAGIC: &[u8; 4] = b"qoif";
+const HEADER_SIZE: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// Largest pixel count decoders are required to handle.
+const MAX_PIXELS: u64 = 400_000_000;
+
+const SRGB: u8 = 0;
+const LINEAR: u8 = 1;
+
+const OP_INDEX: u8 = 0b0000_0000;
+const OP_DIFF: u8 = 0b0100_0000;
+const OP_LUMA: u8 = 0b1000_0000;
+const OP_RUN: u8 = 0b1100_0000;
+const OP_RGB: u8 = 0b1111_1110;
+const OP_RGBA: u8 = 0b1111_1111;
+const OP_MASK: u8 = 0b1100_0000;
+/// Longest run, 63 and 64 being the RGB and RGBA tags.
+const MAX_RUN: u8 = 62;
+
+fn hash([r, g, b, a]: [u8; 4]) -> usize {
+    (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+}
+
+/// Reads a QOI as 8 bit RGB or RGBA, linear or sRGB as its colorspace says.
+pub(crate) fn load_qoi<P: AsRef<Path>>(filename: P) -> anyhow::Result<Image> {
+    let data = std::fs::read(filename)?;
+    if data.len() < HEADER_SIZE + END_MARKER.len() || &data[..4] != MAGIC {
+        return Err(anyhow::anyhow!("Not a QOI file"));
+    }
+    let width = u32::from_be_bytes(data[4..8].try_into()?);
+    let height = u32::from_be_bytes(data[8..12].try_into()?);
+    let color_format = match data[12] {
+        // @formatter:off
+        3 => ColorFormat::RGB_U8,
+        4 => ColorFormat::RGBA_U8,
+        // @formatter:on
+        channels => {
+            return Err(anyhow::anyhow!(
+                "Unsupported QOI channel count: {}",
+                channels
+            ))
+        }
+    };
+    let transfer_function = match data[13] {
+        // @formatter:off
+        SRGB => TransferFunction::Srgb,
+        LINEAR => TransferFunction::Linear,
+        // @formatter:on
+        colorspace => {
+            return Err(anyhow::anyhow!(
+                "Unsupported QOI colorspace: {}",
+                colorspace
+            ))
+        }
+    };
+    let pixel_count = width as u64 * height as u64;
+    if width == 0 || height == 0 || pixel_count > MAX_PIXELS {
+        return Err(anyhow::anyhow!(
+            "Unsupported QOI dimensions: {}x{}",
+            width,
+            height
+        ));
+    }
+
+    // the end marker is never read as chunks
+    let chunks = &data[HEADER_SIZE..data.len() - END_MARKER.len()];
+    // each chunk byte yields at most a full run, so the data bounds what is reserved up front
+    let channels = color_format.channel_count.channel_count() as usize;
+    let capacity = pixel_count.min(chunks.len() as u64 * MAX_RUN as u64) as usize * channels;
+    let mut bytes = Vec::with_capacity(capacity);
+    let mut index = [[0u8; 4]; 64];
+    let mut pixel = [0, 0, 0, 255];
+    let mut run = 0;
+    let mut position = 0;
+    let mut next = || {
+        let byte = chunks
+            .get(position)
+            .copied()
+            .ok_or(anyhow::anyhow!("Truncated QOI data"));
+        position += 1;
+        byte
+    };
+
+    for _ in 0..pixel_count {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let tag = next()?;
+            match tag {
+                OP_RGB => {
+                    pixel = [next()?, next()?, next()?, pixel[3]];
+                }
+                OP_RGBA => {
+                    pixel = [next()?, next()?, next()?, next()?];
+                }
+                _ => match tag & OP_MASK {
+                    OP_INDEX => pixel = index[tag as usize],
+                    OP_DIFF => {
+                        for (channel, shift) in [4, 2, 0].into_iter().enumerate() {
+                            let difference = ((tag >> shift) & 0b11).wrapping_sub(2);
+                            pixel[channel] = pixel[channel].wrapping_add(difference);
+                        }
+                    }
+                    OP_LUMA => {
+                        let green = (tag & 0b11_1111).wrapping_sub(32);
+                        let red_blue = next()?;
+                        let red = (red_blue >> 4).wrapping_sub(8).wrapping_add(green);
+                        let blue = (red_blue & 0b1111).wrapping_sub(8).wrapping_add(green);
+                        pixel[0] = pixel[0].wrapping_add(red);
+                        pixel[1] = pixel[1].wrapping_add(green);
+                        pixel[2] = pixel[2].wrapping_add(blue);
+                    }
+                    _ => run = tag & 0b11_1111,
+                },
+            }
+            index[hash(pixel)] = pixel;
+        }
+        bytes.extend_from_slice(&pixel[..channels]);
+    }
+
+    let desc =
+        ImageDesc::new(width, height, color_format).with_transfer_function(transfer_function);
+
+    Image::new_with_data(desc, bytes)
+}
+
+/// Writes an 8 bit RGB or RGBA image as a QOI, flagged linear for linear images and
+/// sRGB otherwise.
+pub(crate) fn save_qoi<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format != ColorFormat::RGB_U8 && color_format != ColorFormat::RGBA_U8 {
+        return Err(anyhow::anyhow!(
+            "Unsupported QOI color format: {}",
+            color_format
+        ));
+    }
+    let (width, height) = (image.desc.width(), image.desc.height());
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(anyhow::anyhow!(
+            "QOI is limited to {} pixels, got {}x{}",
+            MAX_PIXELS,
+            width,
+            height
+        ));
+    }
+    let colorspace = match image.desc.transfer_function() {
+        TransferFunction::Linear => LINEAR,
+        _ => SRGB,
+    };
+
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    let channels = color_format.channel_count.channel_count() as usize;
+    let bytes = &image.bytes[..image.desc.size_in_bytes() as usize];
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&width.to_be_bytes())?;
+    writer.write_all(&height.to_be_bytes())?;
+    writer.write_all(&[channels as u8, colorspace])?;
+
+    let mut index = [[0u8; 4]; 64];
+    let mut previous = [0, 0, 0, 255];
+    let mut run = 0u8;
+    let pixel_count = bytes.len() / channels;
+    for (i, chunk) in bytes.chunks_exact(channels).enumerate() {
+        let mut pixel = previous;
+        pixel[..channels].copy_from_slice(chunk);
+
+        if pixel == previous {
+            run += 1;
+            if run == MAX_RUN || i + 1 == pixel_count {
+                writer.write_all(&[OP_RUN | (run - 1)])?;
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            writer.write_all(&[OP_RUN | (run - 1)])?;
+            run = 0;
+        }
+
+        let position = hash(pixel);
+        if index[position] == pixel {
+            writer.write_all(&[OP_INDEX | position as u8])?;
+        } else if pixel[3] != previous[3] {
+            writer.write_all(&[OP_RGBA, pixel[0], pixel[1], pixel[2], pixel[3]])?;
+        } else {
+            let red = pixel[0].wrapping_sub(previous[0]) as i8;
+            let green = pixel[1].wrapping_sub(previous[1]) as i8;
+            let blue = pixel[2].wrapping_sub(previous[2]) as i8;
+            let red_green = red.wrapping_sub(green);
+            let blue_green = blue.wrapping_sub(green);
+
+            if [red, green, blue].iter().all(|d| (-2..=1).contains(d)) {
+                writer.write_all(&[OP_DIFF
+                    | ((red + 2) as u8) << 4
+                    | ((green + 2) as u8) << 2
+                    | (blue + 2) as u8])?;
+            } else if (-32..=31).contains(&green)
+                && (-8..=7).contains(&red_green)
+                && (-8..=7).contains(&blue_green)
+            {
+                writer.write_all(&[
+                    OP_LUMA | (green + 32) as u8,
+                    ((red_green + 8) as u8) << 4 | (blue_green + 8) as u8,
+                ])?;
+            } else {
+                writer.write_all(&[OP_RGB, pixel[0], pixel[1], pixel[2]])?;
+            }
+        }
+        index[position] = pixel;
+        previous = pixel;
+    }
+    writer.write_all(&END_MARKER)?;
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,14 @@
+/// Options applied while reading an image file.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct ReadOptions {
+    /// Applies the EXIF orientation to the pixels, see [`crate::image::Image::auto_orient`].
+    pub auto_orient: bool,
+}
+
+impl ReadOptions {
+    pub fn with_auto_orient(mut self, auto_orient: bool) -> Self {
+        self.auto_orient = auto_orient;
+
+        self
+    }
+}
@@ -0,0 +1,348 @@
+use crate::color_format::ChannelSize;
+
+/// Compression of the strips of a saved TIFF.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum TiffCompression {
+    #[default]
+    None,
+    Lzw,
+    /// Adobe Deflate (zlib).
+    Deflate,
+    PackBits,
+    Zstd,
+}
+
+/// Predictor applied to the rows of a saved TIFF before compression.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum TiffPredictor {
+    #[default]
+    None,
+    /// Horizontal differencing, for integer samples.
+    Horizontal,
+    /// Byte-shuffled differencing, for float samples.
+    FloatingPoint,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct TiffOptions {
+    pub compression: TiffCompression,
+    pub predictor: TiffPredictor,
+    /// Width and height of the tiles, strips are written when `None`.
+    pub tile_size: Option<(u32, u32)>,
+    /// Writes a BigTIFF, with 64 bit offsets, for files over 4 GB.
+    pub big_tiff: bool,
+}
+
+impl TiffOptions {
+    pub fn new(compression: TiffCompression, predictor: TiffPredictor) -> Self {
+        Self {
+            compression,
+            predictor,
+            tile_size: None,
+            big_tiff: false,
+        }
+    }
+    /// Tile dimensions must be multiples of 16.
+    pub fn with_tiles(mut self, width: u32, height: u32) -> Self {
+        self.tile_size = Some((width, height));
+
+        self
+    }
+    pub fn with_big_tiff(mut self, big_tiff: bool) -> Self {
+        self.big_tiff = big_tiff;
+
+        self
+    }
+}
+
+/// Compression level of a saved PNG, trading speed for size.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum PngCompression {
+    /// Stored deflate blocks.
+    None,
+    Fastest,
+    #[default]
+    Fast,
+    Balanced,
+    High,
+}
+
+/// Filter applied to the rows of a saved PNG before compression.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+    /// Picks a filter for every row.
+    #[default]
+    Adaptive,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct PngOptions {
+    pub compression: PngCompression,
+    pub filter: PngFilter,
+    /// Saves float, signed and 32/64 bit images as 16 bit, quantized between their
+    /// minimum and maximum. The scale and offset are kept in a text chunk and
+    /// applied when reading to restore the original format and range.
+    pub pack_range: bool,
+}
+
+impl PngOptions {
+    pub fn new(compression: PngCompression, filter: PngFilter) -> Self {
+        Self {
+            compression,
+            filter,
+            pack_range: false,
+        }
+    }
+    pub fn with_pack_range(mut self, pack_range: bool) -> Self {
+        self.pack_range = pack_range;
+
+        self
+    }
+}
+
+/// Resolution of the chroma channels of a saved JPEG relative to the luma.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ChromaSubsampling {
+    /// Full resolution chroma.
+    Yuv444,
+    /// Half horizontal resolution.
+    Yuv422,
+    /// Half horizontal and vertical resolution.
+    #[default]
+    Yuv420,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct JpegOptions {
+    /// From 1 to 100.
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    pub progressive: bool,
+    /// Computes Huffman tables for the image instead of the standard ones,
+    /// making smaller files at the cost of a second pass. Ignored with restart markers.
+    pub optimize_huffman: bool,
+    /// Number of MCUs between restart markers, none when `None`.
+    pub restart_interval: Option<u16>,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self::new(75)
+    }
+}
+
+impl JpegOptions {
+    pub fn new(quality: u8) -> Self {
+        Self {
+            quality,
+            subsampling: ChromaSubsampling::default(),
+            progressive: false,
+            optimize_huffman: false,
+            restart_interval: None,
+        }
+    }
+    pub fn with_subsampling(mut self, subsampling: ChromaSubsampling) -> Self {
+        self.subsampling = subsampling;
+
+        self
+    }
+    pub fn with_progressive(mut self, progressive: bool) -> Self {
+        self.progressive = progressive;
+
+        self
+    }
+    pub fn with_optimize_huffman(mut self, optimize_huffman: bool) -> Self {
+        self.optimize_huffman = optimize_huffman;
+
+        self
+    }
+    pub fn with_restart_interval(mut self, restart_interval: u16) -> Self {
+        self.restart_interval = Some(restart_interval);
+
+        self
+    }
+}
+
+/// Lossless compression of the blocks of a saved EXR.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ExrCompression {
+    None,
+    /// Run length encoding, fast but only effective on flat areas.
+    Rle,
+    /// Deflate over blocks of 16 scanlines.
+    #[default]
+    Zip,
+    /// Wavelet based, the smallest for noisy and photographic images.
+    Piz,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct ExrOptions {
+    pub compression: ExrCompression,
+    /// Width and height of the tiles, scanlines are written when `None`.
+    pub tile_size: Option<(u32, u32)>,
+    /// Writes 32 bit float channels as 16 bit half floats.
+    pub half: bool,
+}
+
+impl ExrOptions {
+    pub fn new(compression: ExrCompression) -> Self {
+        Self {
+            compression,
+            tile_size: None,
+            half: false,
+        }
+    }
+    pub fn with_tiles(mut self, width: u32, height: u32) -> Self {
+        self.tile_size = Some((width, height));
+
+        self
+    }
+    pub fn with_half(mut self, half: bool) -> Self {
+        self.half = half;
+
+        self
+    }
+}
+
+/// Options of the PBM, PGM and PPM formats.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct PnmOptions {
+    /// Writes the samples as decimal text instead of binary.
+    pub ascii: bool,
+}
+
+impl PnmOptions {
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+
+        self
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct TgaOptions {
+    /// Run-length encodes the pixels.
+    pub rle: bool,
+}
+
+impl Default for TgaOptions {
+    fn default() -> Self {
+        Self { rle: true }
+    }
+}
+
+impl TgaOptions {
+    pub fn with_rle(mut self, rle: bool) -> Self {
+        self.rle = rle;
+
+        self
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct GifOptions {
+    /// Speed of the palette quantization, from 1 for the best colors to 30 for the fastest.
+    pub speed: u8,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self { speed: 10 }
+    }
+}
+
+impl GifOptions {
+    pub fn with_speed(mut self, speed: u8) -> Self {
+        self.speed = speed;
+
+        self
+    }
+}
+
+/// Options of every format, the one matching the file extension is used.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
+pub struct SaveOptions {
+    pub jpeg: JpegOptions,
+    pub png: PngOptions,
+    pub tiff: TiffOptions,
+    pub exr: ExrOptions,
+    pub pnm: PnmOptions,
+    pub tga: TgaOptions,
+    pub gif: GifOptions,
+    /// Converts the image to the nearest format the file can hold instead of failing,
+    /// the changes are returned by the save.
+    pub auto_adapt: bool,
+}
+
+impl SaveOptions {
+    pub fn with_jpeg(mut self, jpeg: JpegOptions) -> Self {
+        self.jpeg = jpeg;
+
+        self
+    }
+    pub fn with_png(mut self, png: PngOptions) -> Self {
+        self.png = png;
+
+        self
+    }
+    pub fn with_tiff(mut self, tiff: TiffOptions) -> Self {
+        self.tiff = tiff;
+
+        self
+    }
+    pub fn with_exr(mut self, exr: ExrOptions) -> Self {
+        self.exr = exr;
+
+        self
+    }
+    pub fn with_pnm(mut self, pnm: PnmOptions) -> Self {
+        self.pnm = pnm;
+
+        self
+    }
+    pub fn with_tga(mut self, tga: TgaOptions) -> Self {
+        self.tga = tga;
+
+        self
+    }
+    pub fn with_gif(mut self, gif: GifOptions) -> Self {
+        self.gif = gif;
+
+        self
+    }
+    pub fn with_auto_adapt(mut self, auto_adapt: bool) -> Self {
+        self.auto_adapt = auto_adapt;
+
+        self
+    }
+}
+
+/// Change made by [`SaveOptions::auto_adapt`] for the image to fit its file format.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum Adaptation {
+    /// The colors were composited over white and the alpha channel removed.
+    FlattenedAlpha,
+    /// Channels were converted to fewer bits.
+    ReducedDepth { from: ChannelSize, to: ChannelSize },
+    /// Float channels were clamped to `[0, 1]` and quantized to integers.
+    NormalizedFloat { to: ChannelSize },
+    /// Integer channels were normalized to `[0, 1]` 32 bit floats.
+    ConvertedToFloat,
+    /// Signed channels were offset by half their range to unsigned ones.
+    Unsigned,
+    /// The gray channel was replicated to RGB.
+    ExpandedGray,
+    /// An opaque alpha channel was added.
+    AddedAlpha,
+    /// The TIFF predictor was replaced by the one matching the sample type.
+    ChangedPredictor {
+        from: TiffPredictor,
+        to: TiffPredictor,
+    },
+}
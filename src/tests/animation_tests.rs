@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use crate::animation::*;
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+
+fn solid(width: u32, height: u32, pixel: [u8; 4]) -> Image {
+    let desc = ImageDesc::new(width, height, ColorFormat::RGBA_U8);
+    let bytes = pixel.repeat((width * height) as usize);
+
+    Image::new_with_data(desc, bytes).unwrap()
+}
+
+fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * image.desc.width() + x) * 4) as usize;
+
+    image.bytes[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn apng_round_trips_frames_delays_and_loop_count() {
+    let animation = Animation::new(
+        vec![
+            Frame::new(solid(8, 6, [255, 0, 0, 255]), Duration::from_millis(40)),
+            Frame::new(solid(8, 6, [0, 255, 0, 128]), Duration::from_millis(250)),
+            Frame::new(solid(8, 6, [0, 0, 255, 0]), Duration::from_secs(70)),
+        ],
+        3,
+    );
+
+    let filename = "./test_output/animation.png";
+    animation.save_file(filename).unwrap();
+    let loaded = Animation::read_file(filename).unwrap();
+
+    assert_eq!(loaded.loop_count, 3);
+    assert_eq!(loaded.frames.len(), 3);
+    for (loaded, frame) in loaded.frames.iter().zip(&animation.frames) {
+        assert_eq!(loaded.delay, frame.delay);
+        assert_eq!(loaded.image.bytes, frame.image.bytes);
+    }
+}
+
+#[test]
+fn apng_frames_are_composited() {
+    let background = Frame::new(solid(4, 4, [255, 0, 0, 255]), Duration::from_millis(100));
+    let half = Frame::new(solid(4, 4, [0, 0, 255, 128]), Duration::from_millis(100));
+    let clear = Frame::new(solid(4, 4, [0, 255, 0, 255]), Duration::from_millis(100));
+    let transparent = Frame::new(solid(4, 4, [0, 0, 0, 0]), Duration::from_millis(100));
+
+    let animation = Animation::new(
+        vec![
+            background,
+            half.with_blend(Blend::Over).with_dispose(Dispose::Previous),
+            clear.with_dispose(Dispose::Background),
+            transparent.with_blend(Blend::Over),
+        ],
+        0,
+    );
+
+    let filename = "./test_output/animation-composited.apng";
+    animation.save_file(filename).unwrap();
+    let loaded = Animation::read_file(filename).unwrap();
+
+    assert_eq!(loaded.loop_count, 0);
+    let pixels: Vec<[u8; 4]> = loaded
+        .frames
+        .iter()
+        .map(|frame| pixel(&frame.image, 1, 2))
+        .collect();
+    assert_eq!(pixels[0], [255, 0, 0, 255]);
+    assert_eq!(pixels[1], [127, 0, 128, 255]);
+    // the blended frame is disposed of back to red, then replaced by green
+    assert_eq!(pixels[2], [0, 255, 0, 255]);
+    // green is cleared, nothing is drawn over
+    assert_eq!(pixels[3], [0, 0, 0, 0]);
+}
+
+#[test]
+fn gif_round_trips_frames_and_loop_count() {
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+    for loop_count in [0, 1, 5] {
+        let frames = colors
+            .iter()
+            .map(|color| Frame::new(solid(10, 7, *color), Duration::from_millis(120)))
+            .collect();
+        let animation = Animation::new(frames, loop_count);
+
+        let filename = format!("./test_output/animation-{}.gif", loop_count);
+        animation.save_file(&filename).unwrap();
+        let loaded = Animation::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.loop_count, loop_count);
+        assert_eq!(loaded.frames.len(), 3);
+        for (frame, color) in loaded.frames.iter().zip(colors) {
+            assert_eq!(frame.delay, Duration::from_millis(120));
+            assert_eq!(frame.image.desc.color_format(), ColorFormat::RGBA_U8);
+            assert_eq!(pixel(&frame.image, 9, 6), color);
+        }
+    }
+}
+
+#[test]
+fn still_images_are_single_frames() {
+    let image = solid(5, 3, [10, 20, 30, 255]);
+    image
+        .save_file("./test_output/animation-still.png")
+        .unwrap();
+    image
+        .save_file("./test_output/animation-still.webp")
+        .unwrap();
+
+    for filename in [
+        "./test_output/animation-still.png",
+        "./test_output/animation-still.webp",
+    ] {
+        let loaded = Animation::read_file(filename).unwrap();
+
+        assert_eq!(loaded.loop_count, 1);
+        assert_eq!(loaded.frames.len(), 1);
+        assert_eq!(loaded.frames[0].image.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn invalid_animations_are_rejected() {
+    let mismatched = Animation::new(
+        vec![
+            Frame::new(solid(4, 4, [0; 4]), Duration::ZERO),
+            Frame::new(solid(5, 4, [0; 4]), Duration::ZERO),
+        ],
+        0,
+    );
+    assert!(mismatched
+        .save_file("./test_output/animation-mismatched.png")
+        .is_err());
+
+    let float = Animation::new(
+        vec![Frame::new(
+            Image::new_empty(ImageDesc::new(4, 4, ColorFormat::RGBA_F32)).unwrap(),
+            Duration::ZERO,
+        )],
+        0,
+    );
+    assert!(float
+        .save_file("./test_output/animation-float.gif")
+        .is_err());
+
+    assert!(Animation::default()
+        .save_file("./test_output/animation-empty.png")
+        .is_err());
+
+    let single = Animation::new(vec![Frame::new(solid(4, 4, [0; 4]), Duration::ZERO)], 0);
+    assert!(single.save_file("./test_output/animation.webp").is_err());
+}
+
+#[test]
+fn compositor_checks_sizes_from_the_file() {
+    assert!(Compositor::new(u32::MAX, u32::MAX).is_err());
+    assert!(Compositor::new(0, 4).is_err());
+
+    let mut compositor = Compositor::new(4, 4).unwrap();
+    let rect = FrameRect {
+        x: u32::MAX - 1,
+        y: 1,
+        width: 4,
+        height: 1,
+    };
+    let image = compositor
+        .compose(&[255; 16], rect, Blend::Source, Dispose::Background)
+        .unwrap();
+    assert!(image.bytes.iter().all(|&v| v == 0));
+}
@@ -0,0 +1,124 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn auto_adapt() -> SaveOptions {
+    SaveOptions::default().with_auto_adapt(true)
+}
+
+#[test]
+fn rgba_16bit_is_flattened_for_jpeg() {
+    let desc = ImageDesc::new(16, 16, ColorFormat::RGBA_U16);
+    // opaque black on the left half, transparent on the right half
+    let pixels: Vec<u16> = (0..16 * 16)
+        .flat_map(|i| {
+            let alpha = if i % 16 < 8 { u16::MAX } else { 0 };
+            [0, 0, 0, alpha]
+        })
+        .collect();
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/auto-adapt-rgba.jpg";
+    assert!(image.save_file(filename).is_err());
+
+    let adaptations = image.save_with_options(filename, &auto_adapt()).unwrap();
+    assert_eq!(
+        adaptations,
+        vec![
+            Adaptation::ReducedDepth {
+                from: ChannelSize::_16bit,
+                to: ChannelSize::_8bit
+            },
+            Adaptation::FlattenedAlpha,
+        ]
+    );
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_U8);
+    let row = &loaded.bytes[8 * 16 * 3..9 * 16 * 3];
+    assert!(row[..3].iter().all(|&v| v < 10));
+    assert!(row[row.len() - 3..].iter().all(|&v| v > 245));
+}
+
+#[test]
+fn float_is_normalized_for_png() {
+    let desc = ImageDesc::new(4, 1, ColorFormat::GRAY_F32);
+    let pixels = [-0.5f32, 0.0, 0.5, 2.0];
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/auto-adapt-float.png";
+    let adaptations = image.save_with_options(filename, &auto_adapt()).unwrap();
+    assert_eq!(
+        adaptations,
+        vec![Adaptation::NormalizedFloat {
+            to: ChannelSize::_16bit
+        }]
+    );
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_U16);
+    let values: &[u16] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(values, &[0, 0, 32767, 65535]);
+}
+
+#[test]
+fn signed_and_wide_integers_for_png() {
+    let desc = ImageDesc::new(2, 1, ColorFormat::GRAY_ALPHA_I32);
+    let pixels = [i32::MIN, i32::MAX, 0, i32::MAX];
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/auto-adapt-signed.png";
+    let adaptations = image.save_with_options(filename, &auto_adapt()).unwrap();
+    assert_eq!(
+        adaptations,
+        vec![
+            Adaptation::Unsigned,
+            Adaptation::ReducedDepth {
+                from: ChannelSize::_32bit,
+                to: ChannelSize::_16bit
+            },
+        ]
+    );
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_ALPHA_U16);
+    let values: &[u16] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(values, &[0, 65535, 32768, 65535]);
+}
+
+#[test]
+fn tiff_predictor_follows_sample_type() {
+    let desc = ImageDesc::new(8, 8, ColorFormat::RGB_F32);
+    let pixels: Vec<f32> = (0..8 * 8 * 3).map(|i| i as f32 / 192.0).collect();
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let options = auto_adapt().with_tiff(TiffOptions::new(
+        TiffCompression::Deflate,
+        TiffPredictor::Horizontal,
+    ));
+    let filename = "./test_output/auto-adapt-predictor.tiff";
+    let adaptations = image.save_with_options(filename, &options).unwrap();
+    assert_eq!(
+        adaptations,
+        vec![Adaptation::ChangedPredictor {
+            from: TiffPredictor::Horizontal,
+            to: TiffPredictor::FloatingPoint
+        }]
+    );
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.bytes, image.bytes);
+}
+
+#[test]
+fn supported_images_are_not_adapted() {
+    let desc = ImageDesc::new(4, 4, ColorFormat::RGBA_U8);
+    let image = Image::new_with_data(desc, vec![200; 64]).unwrap();
+
+    for extension in ["png", "tiff"] {
+        let filename = format!("./test_output/auto-adapt-none.{}", extension);
+        let adaptations = image.save_with_options(&filename, &auto_adapt()).unwrap();
+        assert!(adaptations.is_empty());
+        assert_eq!(Image::read_file(&filename).unwrap().bytes, image.bytes);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::color_format::*;
+use crate::color_primaries::*;
+use crate::color_space::mul;
+use crate::image::{Image, ImageDesc};
+
+fn assert_close(a: [f64; 3], b: [f64; 3], eps: f64) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < eps, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn conversion_matrices() {
+    let srgb_to_p3 = ColorPrimaries::Srgb.conversion_matrix(ColorPrimaries::DisplayP3);
+    assert_close(
+        mul(&srgb_to_p3, [1.0, 0.0, 0.0]),
+        [0.8225, 0.0332, 0.0171],
+        1e-3,
+    );
+
+    // white stays white, including the D60 based ACEScg
+    let all = [
+        ColorPrimaries::Srgb,
+        ColorPrimaries::DisplayP3,
+        ColorPrimaries::Rec2020,
+        ColorPrimaries::AdobeRgb,
+        ColorPrimaries::AcesCg,
+    ];
+    for from in all {
+        for to in all {
+            let matrix = from.conversion_matrix(to);
+            assert_close(mul(&matrix, [1.0, 1.0, 1.0]), [1.0, 1.0, 1.0], 1e-6);
+
+            let back = to.conversion_matrix(from);
+            assert_close(
+                mul(&back, mul(&matrix, [0.2, 0.5, 0.7])),
+                [0.2, 0.5, 0.7],
+                1e-9,
+            );
+        }
+    }
+}
+
+#[test]
+fn gamut_mapping() {
+    let p3_green = mul(
+        &ColorPrimaries::DisplayP3.conversion_matrix(ColorPrimaries::Srgb),
+        [0.0, 1.0, 0.0],
+    );
+    assert!(p3_green[0] < 0.0);
+
+    let luminance = ColorPrimaries::Srgb.to_xyz()[1];
+
+    let clipped = GamutMapping::Clip.apply(p3_green, luminance);
+    assert!(clipped.iter().all(|v| (0.0..=1.0).contains(v)));
+
+    let compressed = GamutMapping::Compress.apply(p3_green, luminance);
+    assert!(compressed.iter().all(|v| *v >= -1e-12));
+    let y = |c: [f64; 3]| c[0] * luminance[0] + c[1] * luminance[1] + c[2] * luminance[2];
+    assert!((y(compressed) - y(p3_green)).abs() < 1e-9);
+}
+
+#[test]
+fn convert_image_primaries() {
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_F32)
+        .with_color_primaries(ColorPrimaries::AcesCg)
+        .with_transfer_function(TransferFunction::Linear);
+    let data: Vec<u8> = bytemuck::cast_slice(&[0.0f32, 1.0, 0.0]).to_vec();
+    let src = Image::new_with_data(desc, data).unwrap();
+
+    let srgb = src
+        .clone()
+        .convert_primaries(ColorPrimaries::Srgb, GamutMapping::Clip)
+        .unwrap()
+        .convert_transfer_function(TransferFunction::Srgb)
+        .unwrap();
+    assert_eq!(srgb.desc.color_primaries(), ColorPrimaries::Srgb);
+    assert_eq!(srgb.desc.transfer_function(), TransferFunction::Srgb);
+    let pixel: &[f32] = bytemuck::cast_slice(&srgb.bytes);
+    assert!(pixel.iter().all(|v| (0.0..=1.0).contains(v)));
+    assert!(pixel[1] > 0.99);
+
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_U8);
+    let src = Image::new_with_data(desc, vec![0, 255, 0]).unwrap();
+    assert!(src
+        .convert_primaries(ColorPrimaries::Rec2020, GamutMapping::None)
+        .is_err());
+}
@@ -0,0 +1,117 @@
+use crate::color_format::*;
+use crate::color_space::*;
+use crate::image::{Image, ImageDesc};
+
+fn assert_close(a: [f64; 3], b: [f64; 3], eps: f64) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < eps, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn white_and_primaries() {
+    let white = [1.0, 1.0, 1.0];
+    assert_close(
+        ColorSpace::Lab(WhitePoint::D65).convert_from_rgb(white),
+        [100.0, 0.0, 0.0],
+        1e-2,
+    );
+    assert_close(
+        ColorSpace::Lab(WhitePoint::D50).convert_from_rgb(white),
+        [100.0, 0.0, 0.0],
+        1e-2,
+    );
+    assert_close(
+        ColorSpace::Oklab.convert_from_rgb(white),
+        [1.0, 0.0, 0.0],
+        1e-4,
+    );
+    assert_close(
+        ColorSpace::Xyz(WhitePoint::D50).convert_from_rgb(white),
+        WhitePoint::D50.xyz(),
+        1e-3,
+    );
+
+    let red = [1.0, 0.0, 0.0];
+    assert_close(ColorSpace::Hsv.convert_from_rgb(red), [0.0, 1.0, 1.0], 1e-9);
+    assert_close(ColorSpace::Hsl.convert_from_rgb(red), [0.0, 1.0, 0.5], 1e-9);
+    assert_close(
+        ColorSpace::Lab(WhitePoint::D65).convert_from_rgb(red),
+        [53.24, 80.09, 67.20],
+        1e-1,
+    );
+}
+
+#[test]
+fn round_trips() {
+    let spaces = [
+        ColorSpace::Hsv,
+        ColorSpace::Hsl,
+        ColorSpace::Xyz(WhitePoint::D65),
+        ColorSpace::Xyz(WhitePoint::D50),
+        ColorSpace::Lab(WhitePoint::D65),
+        ColorSpace::Lab(WhitePoint::D50),
+        ColorSpace::Lch(WhitePoint::D50),
+        ColorSpace::Oklab,
+    ];
+    let colors = [
+        [0.2, 0.4, 0.6],
+        [0.9, 0.1, 0.3],
+        [0.5, 0.5, 0.5],
+        [0.0, 0.7, 0.2],
+    ];
+
+    for space in spaces {
+        for color in colors {
+            let converted = space.convert_from_rgb(color);
+            assert_close(space.convert_to_rgb(converted), color, 1e-6);
+        }
+    }
+}
+
+#[test]
+fn convert_image_color_space() {
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGBA_F32);
+    let data: Vec<u8> = bytemuck::cast_slice(&[0.0f32, 0.0, 1.0, 0.5]).to_vec();
+    let src = Image::new_with_data(desc, data).unwrap();
+
+    let hsv = src.clone().convert_color_space(ColorSpace::Hsv).unwrap();
+    assert_eq!(hsv.desc.color_space(), ColorSpace::Hsv);
+    let pixel: &[f32] = bytemuck::cast_slice(&hsv.bytes);
+    assert_eq!(pixel, &[240.0, 1.0, 1.0, 0.5]);
+
+    let lab = hsv
+        .convert(ColorFormat::RGBA_F64)
+        .unwrap()
+        .convert_color_space(ColorSpace::Lab(WhitePoint::D50))
+        .unwrap()
+        .convert_color_space(ColorSpace::Rgb)
+        .unwrap();
+    let pixel: &[f64] = bytemuck::cast_slice(&lab.bytes);
+    assert_close([pixel[0], pixel[1], pixel[2]], [0.0, 0.0, 1.0], 1e-5);
+    assert_eq!(pixel[3], 0.5);
+
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_U8);
+    let src = Image::new_with_data(desc, vec![0, 0, 255]).unwrap();
+    assert!(src.convert_color_space(ColorSpace::Hsv).is_err());
+}
+
+#[test]
+fn convert_to_integer_or_gray_leaves_rgb() {
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_F32);
+    let data: Vec<u8> = bytemuck::cast_slice(&[1.0f32, 0.0, 0.0]).to_vec();
+    let lab = Image::new_with_data(desc, data)
+        .unwrap()
+        .convert_color_space(ColorSpace::Lab(WhitePoint::D65))
+        .unwrap();
+
+    let rgb = lab.clone().convert(ColorFormat::RGB_U8).unwrap();
+    assert_eq!(rgb.desc.color_space(), ColorSpace::Rgb);
+    assert!(rgb.bytes[0] >= 254 && rgb.bytes[1] == 0 && rgb.bytes[2] == 0);
+
+    let gray = lab.clone().convert(ColorFormat::GRAY_F32).unwrap();
+    assert_eq!(gray.desc.color_space(), ColorSpace::Rgb);
+
+    let rgba = lab.convert(ColorFormat::RGBA_F32).unwrap();
+    assert_eq!(rgba.desc.color_space(), ColorSpace::Lab(WhitePoint::D65));
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use crate::color_format::*;
+use crate::exif::*;
+use crate::ifd::write_ifd;
+use crate::image::{Image, ImageDesc, Layout};
+use crate::metadata::*;
+use crate::read_options::ReadOptions;
+use crate::save_options::*;
+
+fn camera_exif(orientation: Orientation) -> Exif {
+    let mut exif = Exif::default();
+    exif.image_tags
+        .insert(271, TagValue::Ascii("Imaginarium".to_string()));
+    exif.image_tags
+        .insert(272, TagValue::Ascii("Camera One".to_string()));
+    exif.set_orientation(orientation);
+    exif.exif_tags
+        .insert(33434, TagValue::Rational(vec![[1, 125]]));
+    exif.exif_tags
+        .insert(33437, TagValue::Rational(vec![[28, 10]]));
+    exif.exif_tags.insert(34855, TagValue::Short(vec![200]));
+    exif.exif_tags
+        .insert(36867, TagValue::Ascii("2024:05:17 10:42:00".to_string()));
+    exif.exif_tags
+        .insert(37380, TagValue::SignedRational(vec![[-2, 3]]));
+    exif.exif_tags
+        .insert(37386, TagValue::Rational(vec![[50, 1]]));
+    exif.gps_tags.insert(1, TagValue::Ascii("S".to_string()));
+    exif.gps_tags
+        .insert(2, TagValue::Rational(vec![[33, 1], [52, 1], [3600, 100]]));
+    exif.gps_tags.insert(3, TagValue::Ascii("E".to_string()));
+    exif.gps_tags
+        .insert(4, TagValue::Rational(vec![[151, 1], [12, 1], [0, 1]]));
+    exif.gps_tags.insert(6, TagValue::Rational(vec![[58, 1]]));
+
+    exif
+}
+
+fn numbered(width: u32, height: u32) -> Image {
+    let desc = ImageDesc::new(width, height, ColorFormat::GRAY_U8);
+    Image::new_with_data(desc, (0..width * height).map(|i| i as u8).collect()).unwrap()
+}
+
+#[test]
+fn exif_getters() {
+    let exif = camera_exif(Orientation::Rotate90);
+
+    assert_eq!(exif.orientation(), Orientation::Rotate90);
+    assert_eq!(exif.make(), Some("Imaginarium"));
+    assert_eq!(exif.model(), Some("Camera One"));
+    assert_eq!(exif.exposure_time(), Some(0.008));
+    assert_eq!(exif.f_number(), Some(2.8));
+    assert_eq!(exif.iso(), Some(200));
+    assert_eq!(exif.focal_length(), Some(50.0));
+    assert_eq!(exif.date_time_original(), Some("2024:05:17 10:42:00"));
+
+    let gps = exif.gps().unwrap();
+    assert!((gps.latitude + 33.8766667).abs() < 1e-6);
+    assert!((gps.longitude - 151.2).abs() < 1e-6);
+    assert_eq!(gps.altitude, Some(58.0));
+}
+
+#[test]
+fn exif_block_round_trip() {
+    let exif = camera_exif(Orientation::Transverse);
+
+    assert_eq!(Exif::parse(&exif.to_bytes()).unwrap(), exif);
+
+    let mut marked = b"Exif\0\0".to_vec();
+    marked.extend(exif.to_bytes());
+    assert_eq!(Exif::parse(&marked).unwrap(), exif);
+
+    // big endian block without sub directories
+    let mut bytes = b"MM\x00\x2a\x00\x00\x00\x08".to_vec();
+    bytes.extend(write_ifd(&exif.image_tags, 8, true, false));
+    let parsed = Exif::parse(&bytes).unwrap();
+    assert_eq!(parsed.image_tags, exif.image_tags);
+    assert!(parsed.exif_tags.is_empty());
+    assert_eq!(parsed.gps_tags, BTreeMap::new());
+
+    assert!(Exif::parse(b"not an exif block").is_err());
+}
+
+#[test]
+fn exif_preserved_on_save() {
+    let mut image = numbered(16, 8).convert(ColorFormat::RGB_U8).unwrap();
+    image.metadata.exif = Some(camera_exif(Orientation::Rotate90));
+
+    for filename in [
+        "./test_output/exif.jpg",
+        "./test_output/exif.png",
+        "./test_output/exif.tiff",
+    ] {
+        image.save_file(filename).unwrap();
+
+        let loaded = Image::read_file(filename).unwrap();
+        assert_eq!(loaded.metadata.exif, image.metadata.exif, "{}", filename);
+    }
+
+    let options = TiffOptions::default().with_big_tiff(true);
+    image
+        .save_tiff_with_options("./test_output/exif-big.tiff", &options)
+        .unwrap();
+    let loaded = Image::read_file("./test_output/exif-big.tiff").unwrap();
+    assert_eq!(loaded.metadata.exif, image.metadata.exif);
+    assert_eq!(loaded.bytes, image.bytes);
+}
+
+#[test]
+fn orientation_transforms() {
+    let image = numbered(3, 2);
+
+    let cases = [
+        (Orientation::Normal, (3, 2), vec![0, 1, 2, 3, 4, 5]),
+        (Orientation::FlipHorizontal, (3, 2), vec![2, 1, 0, 5, 4, 3]),
+        (Orientation::Rotate180, (3, 2), vec![5, 4, 3, 2, 1, 0]),
+        (Orientation::FlipVertical, (3, 2), vec![3, 4, 5, 0, 1, 2]),
+        (Orientation::Transpose, (2, 3), vec![0, 3, 1, 4, 2, 5]),
+        (Orientation::Rotate90, (2, 3), vec![3, 0, 4, 1, 5, 2]),
+        (Orientation::Transverse, (2, 3), vec![5, 2, 4, 1, 3, 0]),
+        (Orientation::Rotate270, (2, 3), vec![2, 5, 1, 4, 0, 3]),
+    ];
+    for (orientation, (width, height), bytes) in cases {
+        assert_eq!(
+            Orientation::from_u16(orientation.to_u16()),
+            Some(orientation)
+        );
+
+        let oriented = image.clone().orient(orientation).unwrap();
+        assert_eq!(oriented.desc.width(), width, "{:?}", orientation);
+        assert_eq!(oriented.desc.height(), height, "{:?}", orientation);
+        assert_eq!(oriented.bytes, bytes, "{:?}", orientation);
+    }
+
+    // planar images are transformed plane by plane
+    let planar = numbered(3, 2)
+        .convert(ColorFormat::RGB_U8)
+        .unwrap()
+        .convert_layout(Layout::Planar)
+        .unwrap();
+    let oriented = planar.orient(Orientation::Rotate90).unwrap();
+    assert_eq!(oriented.desc.layout(), Layout::Planar);
+    assert_eq!(&oriented.bytes[..6], &[3, 0, 4, 1, 5, 2]);
+}
+
+#[test]
+fn auto_orient_on_read() {
+    let mut image = numbered(16, 8);
+    image.metadata.exif = Some(camera_exif(Orientation::Rotate90));
+    image.metadata.resolution = Some(Resolution::new(300.0, 150.0, ResolutionUnit::Inch));
+    image.save_file("./test_output/exif-orient.tiff").unwrap();
+
+    let options = ReadOptions::default().with_auto_orient(true);
+    let loaded = Image::read_file_with_options("./test_output/exif-orient.tiff", &options).unwrap();
+    assert_eq!((loaded.desc.width(), loaded.desc.height()), (8, 16));
+    assert_eq!(
+        loaded.bytes,
+        image.clone().orient(Orientation::Rotate90).unwrap().bytes
+    );
+    assert_eq!(
+        loaded.metadata.exif.as_ref().unwrap().orientation(),
+        Orientation::Normal
+    );
+    assert_eq!(loaded.metadata.resolution.unwrap().x, 150.0);
+
+    let loaded = Image::read_file("./test_output/exif-orient.tiff").unwrap();
+    assert_eq!((loaded.desc.width(), loaded.desc.height()), (16, 8));
+}
+
+#[test]
+fn oversized_counts_return_error() {
+    // directory claiming 0xFFFF entries
+    let bytes = b"II\x2a\x00\x08\x00\x00\x00\xff\xff".to_vec();
+    assert!(Exif::parse(&bytes).is_err());
+
+    // an ASCII tag with a count of 0xFFFFFFFF stored after the directory
+    let mut bytes = b"II\x2a\x00\x08\x00\x00\x00\x01\x00".to_vec();
+    bytes.extend([
+        0x0f, 0x01, 0x02, 0x00, 0xff, 0xff, 0xff, 0xff, 0x1a, 0x00, 0x00, 0x00,
+    ]);
+    bytes.extend([0u8; 4]);
+    assert!(Exif::parse(&bytes).is_err());
+
+    // BigTIFF entry count overflowing the entry size
+    let mut bytes = b"II\x2b\x00\x08\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    bytes.extend(u64::MAX.to_le_bytes());
+    assert!(Exif::parse(&bytes).is_err());
+}
@@ -0,0 +1,116 @@
+use crate::color_format::*;
+use crate::exr::ExrLayer;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn float_image(width: u32, height: u32, color_format: ColorFormat) -> Image {
+    let desc = ImageDesc::new(width, height, color_format);
+    let count = desc.size_in_bytes() as usize / 4;
+    let pixels: Vec<f32> = (0..count).map(|i| i as f32 / 64.0 - 2.0).collect();
+
+    Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap()
+}
+
+#[test]
+fn lossless_compressions_round_trip() {
+    let image = float_image(37, 21, ColorFormat::RGBA_F32);
+
+    for (compression, tiles) in [
+        (ExrCompression::None, None),
+        (ExrCompression::Rle, None),
+        (ExrCompression::Zip, None),
+        (ExrCompression::Piz, None),
+        (ExrCompression::Zip, Some((16, 8))),
+        (ExrCompression::Piz, Some((32, 32))),
+    ] {
+        let mut options = ExrOptions::new(compression);
+        options.tile_size = tiles;
+        let filename = format!(
+            "./test_output/exr-{:?}-{}.exr",
+            compression,
+            tiles.is_some()
+        );
+        image.save_exr_with_options(&filename, &options).unwrap();
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_F32);
+        assert_eq!((loaded.desc.width(), loaded.desc.height()), (37, 21));
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn half_floats_are_rounded() {
+    let image = float_image(8, 8, ColorFormat::GRAY_F32);
+
+    let filename = "./test_output/exr-half.exr";
+    image
+        .save_exr_with_options(filename, &ExrOptions::default().with_half(true))
+        .unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_F32);
+
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    let expected: &[f32] = bytemuck::cast_slice(&image.bytes);
+    for (value, expected) in values.iter().zip(expected) {
+        assert!((value - expected).abs() <= expected.abs() / 1024.0);
+    }
+}
+
+#[test]
+fn uint_channels_stay_integers() {
+    let desc = ImageDesc::new(9, 5, ColorFormat::RGB_U32);
+    let pixels: Vec<u32> = (0..9 * 5 * 3).map(|i| i * 100_000).collect();
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/exr-u32.exr";
+    image.save_file(filename).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_U32);
+    assert_eq!(loaded.bytes, image.bytes);
+}
+
+#[test]
+fn layers_are_separate_images() {
+    let mut layers = [
+        ExrLayer::new(None, float_image(16, 16, ColorFormat::RGB_F32)),
+        ExrLayer::new(
+            Some("depth".to_string()),
+            float_image(8, 4, ColorFormat::GRAY_F32),
+        ),
+    ];
+
+    let filename = "./test_output/exr-layers.exr";
+    assert!(Image::save_exr_layers(&layers, filename, &ExrOptions::default()).is_err());
+    layers[0].name = Some("beauty".to_string());
+    Image::save_exr_layers(&layers, filename, &ExrOptions::default()).unwrap();
+    let loaded = Image::read_exr_layers(filename).unwrap();
+
+    assert_eq!(loaded.len(), 2);
+    for (loaded, layer) in loaded.iter().zip(&layers) {
+        assert_eq!(loaded.name, layer.name);
+        assert_eq!(
+            loaded.image.desc.color_format(),
+            layer.image.desc.color_format()
+        );
+        assert_eq!(loaded.image.bytes, layer.image.bytes);
+    }
+}
+
+#[test]
+fn integers_are_adapted_to_floats() {
+    let desc = ImageDesc::new(4, 4, ColorFormat::RGB_U8);
+    let image = Image::new_with_data(desc, vec![255; 4 * 4 * 3]).unwrap();
+
+    let filename = "./test_output/exr-adapted.exr";
+    assert!(image.save_file(filename).is_err());
+    let adaptations = image
+        .save_with_options(filename, &SaveOptions::default().with_auto_adapt(true))
+        .unwrap();
+    assert_eq!(adaptations, vec![Adaptation::ConvertedToFloat]);
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_F32);
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    assert!(values.iter().all(|&value| value == 1.0));
+}
@@ -0,0 +1,167 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn gradient(width: u32, height: u32, color_format: ColorFormat) -> Image {
+    let desc = ImageDesc::new(width, height, color_format);
+    let count = desc.size_in_bytes() as usize / 4;
+    // flat areas exercise the runs, the range the shared exponents
+    let pixels: Vec<f32> = (0..count)
+        .map(|i| {
+            if i % 7 < 3 {
+                0.25
+            } else {
+                (i % 97) as f32 * 10.0
+            }
+        })
+        .collect();
+
+    Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap()
+}
+
+fn assert_close(loaded: &Image, image: &Image) {
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    let expected: &[f32] = bytemuck::cast_slice(&image.bytes);
+    assert_eq!(values.len(), expected.len());
+    for (pixel, expected_pixel) in values.chunks_exact(3).zip(expected.chunks_exact(3)) {
+        // one step of the 8 bit mantissa of the largest component
+        let step = expected_pixel.iter().fold(0.0f32, |max, &v| max.max(v)) / 128.0;
+        for (value, expected) in pixel.iter().zip(expected_pixel) {
+            assert!(
+                (value - expected).abs() <= step,
+                "{} != {}",
+                value,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn hdr_round_trips_with_and_without_runs() {
+    // narrow scanlines are written flat
+    for width in [64, 5] {
+        let image = gradient(width, 9, ColorFormat::RGB_F32);
+
+        let filename = format!("./test_output/radiance-{}.hdr", width);
+        image.save_file(&filename).unwrap();
+        let loaded = Image::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_F32);
+        assert_eq!((loaded.desc.width(), loaded.desc.height()), (width, 9));
+        assert_close(&loaded, &image);
+    }
+}
+
+#[test]
+fn hdr_gray_is_written_as_rgb() {
+    let image = gradient(16, 4, ColorFormat::GRAY_F32);
+
+    let filename = "./test_output/radiance-gray.hdr";
+    image.save_file(filename).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_F32);
+    let expected = image.convert(ColorFormat::RGB_F32).unwrap();
+    assert_close(&loaded, &expected);
+}
+
+#[test]
+fn hdr_reads_exposure_and_bottom_up_rows() {
+    let mut bytes = b"#?RADIANCE\nEXPOSURE=2.0\nFORMAT=32-bit_rle_rgbe\n\n+Y 2 +X 1\n".to_vec();
+    // 1.0 on the stored first row, the bottom one, and 0.5 on the top one
+    bytes.extend([128, 128, 128, 129, 128, 128, 128, 128]);
+
+    let filename = "./test_output/radiance-exposure.hdr";
+    std::fs::write(filename, bytes).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    let expected = [0.25f32, 0.25, 0.25, 0.5, 0.5, 0.5];
+    for (value, expected) in values.iter().zip(expected) {
+        assert!((value - expected).abs() < 0.01);
+    }
+}
+
+#[test]
+fn pfm_round_trips_exactly() {
+    for color_format in [ColorFormat::RGB_F32, ColorFormat::GRAY_F32] {
+        let image = gradient(7, 5, color_format);
+
+        let filename = format!("./test_output/float-map-{}.pfm", color_format);
+        image.save_file(&filename).unwrap();
+        let loaded = Image::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.desc.color_format(), color_format);
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn pfm_reads_big_endian_bottom_up() {
+    let mut bytes = b"Pf\n1 2\n1.0\n".to_vec();
+    bytes.extend(1.5f32.to_be_bytes());
+    bytes.extend((-3.0f32).to_be_bytes());
+
+    let filename = "./test_output/float-map-big-endian.pfm";
+    std::fs::write(filename, bytes).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_F32);
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(values, [-3.0, 1.5]);
+}
+
+#[test]
+fn integers_are_adapted_for_hdr() {
+    let desc = ImageDesc::new(4, 4, ColorFormat::RGBA_U8);
+    let image = Image::new_with_data(desc, vec![255; 4 * 4 * 4]).unwrap();
+
+    let filename = "./test_output/radiance-adapted.hdr";
+    assert!(image.save_file(filename).is_err());
+    let adaptations = image
+        .save_with_options(filename, &SaveOptions::default().with_auto_adapt(true))
+        .unwrap();
+    assert_eq!(
+        adaptations,
+        vec![Adaptation::ConvertedToFloat, Adaptation::FlattenedAlpha]
+    );
+}
+
+#[test]
+fn invalid_sizes_and_runs_return_error() {
+    let header = |size: &str| format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", size);
+    let filename = "./test_output/radiance-invalid.hdr";
+    for size in [
+        "-Y 70000 +X 70000",
+        "-Y 2147483648 +X 2147483648",
+        "-Y 0 +X 4",
+        "-Y 4 +X 0",
+        "-Y 10000 +X 10000",
+    ] {
+        std::fs::write(filename, header(size)).unwrap();
+        assert!(Image::read_file(filename).is_err(), "{}", size);
+    }
+
+    // old style runs shift their count by 8 more bits on each consecutive repeat
+    let mut bytes = header("-Y 1 +X 100").into_bytes();
+    bytes.extend([5, 5, 5, 128]);
+    for _ in 0..9 {
+        bytes.extend([1, 1, 1, 0]);
+    }
+    std::fs::write(filename, bytes).unwrap();
+    assert!(Image::read_file(filename).is_err());
+
+    let filename = "./test_output/float-map-invalid.pfm";
+    for header in ["PF\n0 1\n-1.0\n", "PF\n4294967295 2\n-1.0\n"] {
+        std::fs::write(filename, header).unwrap();
+        assert!(Image::read_file(filename).is_err(), "{:?}", header);
+    }
+
+    let empty =
+        Image::new_with_data(ImageDesc::new(0, 3, ColorFormat::RGB_F32), Vec::new()).unwrap();
+    assert!(empty.save_file("./test_output/radiance-empty.hdr").is_err());
+    assert!(empty
+        .save_file("./test_output/float-map-empty.pfm")
+        .is_err());
+}
@@ -0,0 +1,132 @@
+use crate::color_format::*;
+use crate::color_primaries::*;
+use crate::icc::IccProfile;
+use crate::image::{Image, ImageDesc};
+
+#[test]
+fn profile_round_trip() {
+    let profile = IccProfile::from_primaries(ColorPrimaries::DisplayP3, TransferFunction::Srgb);
+    let parsed = IccProfile::parse(&profile.to_bytes()).unwrap();
+
+    assert_eq!(parsed.description(), profile.description());
+    for i in 0..3 {
+        for j in 0..3 {
+            assert!((parsed.to_xyz()[i][j] - profile.to_xyz()[i][j]).abs() < 1e-4);
+        }
+    }
+    for x in [0.0, 0.01, 0.2, 0.5, 1.0] {
+        let expected = TransferFunction::Srgb.decode(x);
+        assert!((parsed.curves()[0].eval(x) - expected).abs() < 1e-3);
+    }
+
+    assert!(IccProfile::parse(&[0u8; 200]).is_err());
+}
+
+#[test]
+fn convert_to_srgb() {
+    let desc = ImageDesc::new(2, 1, ColorFormat::RGB_F32)
+        .with_color_primaries(ColorPrimaries::DisplayP3)
+        .with_transfer_function(TransferFunction::Linear);
+    let data: Vec<u8> = bytemuck::cast_slice(&[0.2f32, 0.5, 0.7, 0.9, 0.1, 0.3]).to_vec();
+    let mut src = Image::new_with_data(desc, data).unwrap();
+    src.icc_profile = Some(
+        IccProfile::from_primaries(ColorPrimaries::DisplayP3, TransferFunction::Linear).to_bytes(),
+    );
+
+    let expected = src
+        .clone()
+        .convert_primaries(ColorPrimaries::Srgb, GamutMapping::None)
+        .unwrap()
+        .convert_transfer_function(TransferFunction::Srgb)
+        .unwrap();
+    let result = src.convert_to_srgb().unwrap();
+
+    assert_eq!(result.desc.color_primaries(), ColorPrimaries::Srgb);
+    assert_eq!(
+        IccProfile::parse(result.icc_profile.as_ref().unwrap()).unwrap(),
+        IccProfile::parse(&IccProfile::srgb().to_bytes()).unwrap()
+    );
+    let result: &[f32] = bytemuck::cast_slice(&result.bytes);
+    let expected: &[f32] = bytemuck::cast_slice(&expected.bytes);
+    for (a, b) in result.iter().zip(expected) {
+        assert!((a - b).abs() < 2e-3, "{:?} != {:?}", result, expected);
+    }
+
+    // an 8 bit sRGB image is left as it is
+    let png = Image::read_file("./test_resources/rgb-sample-8bit.png").unwrap();
+    let srgb = png.clone().convert_to_srgb().unwrap();
+    assert!(png
+        .bytes
+        .iter()
+        .zip(&srgb.bytes)
+        .all(|(a, b)| a.abs_diff(*b) <= 1));
+}
+
+#[test]
+fn profile_is_preserved_by_files() {
+    let icc_profile =
+        IccProfile::from_primaries(ColorPrimaries::AdobeRgb, TransferFunction::Gamma22).to_bytes();
+
+    let mut png = Image::read_file("./test_resources/rgb-sample-8bit.png").unwrap();
+    png.icc_profile = Some(icc_profile.clone());
+
+    for filename in [
+        "./test_output/icc-profile.png",
+        "./test_output/icc-profile.jpg",
+        "./test_output/icc-profile.tiff",
+    ] {
+        png.save_file(filename).unwrap();
+        let loaded = Image::read_file(filename).unwrap();
+        assert_eq!(loaded.icc_profile.as_ref(), Some(&icc_profile), "{}", filename);
+    }
+
+    let converted = png.convert(ColorFormat::RGBA_U16).unwrap();
+    assert_eq!(converted.icc_profile, Some(icc_profile.clone()));
+    converted.save_file("./test_output/icc-profile-u16.tiff").unwrap();
+    let loaded = Image::read_file("./test_output/icc-profile-u16.tiff").unwrap();
+    assert_eq!(loaded.icc_profile, Some(icc_profile));
+}
+
+#[test]
+fn conversion_updates_desc_and_gray_drops_profile() {
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_F32);
+    let data: Vec<u8> = bytemuck::cast_slice(&[0.2f32, 0.5, 0.7]).to_vec();
+    let src = Image::new_with_data(desc, data).unwrap();
+
+    let target = IccProfile::from_primaries(ColorPrimaries::Rec2020, TransferFunction::Linear);
+    let parsed = IccProfile::parse(&target.to_bytes()).unwrap();
+    assert_eq!(
+        parsed.closest_primaries(),
+        (ColorPrimaries::Rec2020, TransferFunction::Linear)
+    );
+
+    let converted = src.convert_icc_profile(&target).unwrap();
+    assert_eq!(converted.desc.color_primaries(), ColorPrimaries::Rec2020);
+    assert_eq!(converted.desc.transfer_function(), TransferFunction::Linear);
+    assert!(converted.icc_profile.is_some());
+
+    let gray = converted.convert(ColorFormat::GRAY_U8).unwrap();
+    assert_eq!(gray.icc_profile, None);
+}
+
+#[test]
+fn conversion_keeps_only_matching_profiles() {
+    let mut gray_profile = vec![0u8; 132];
+    gray_profile[16..20].copy_from_slice(b"GRAY");
+    let desc = ImageDesc::new(1, 1, ColorFormat::GRAY_U8);
+    let mut gray = Image::new_with_data(desc, vec![128]).unwrap();
+    gray.icc_profile = Some(gray_profile.clone());
+
+    let wide = gray.clone().convert(ColorFormat::GRAY_U16).unwrap();
+    assert_eq!(wide.icc_profile, Some(gray_profile));
+    let rgb = gray.convert(ColorFormat::RGB_U8).unwrap();
+    assert_eq!(rgb.icc_profile, None);
+}
+
+#[test]
+fn oversized_tag_count_is_clamped() {
+    let mut bytes = IccProfile::srgb().to_bytes();
+    bytes[128..132].copy_from_slice(&u32::MAX.to_be_bytes());
+
+    assert!(IccProfile::parse(&bytes).is_ok());
+}
@@ -0,0 +1,130 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn test_image() -> Image {
+    let desc = ImageDesc::new(64, 48, ColorFormat::RGB_U8);
+    let bytes = (0..64 * 48)
+        .flat_map(|i| {
+            let (x, y) = (i % 64, i / 64);
+            [(x * 4) as u8, (y * 5) as u8, ((x * y) % 251) as u8]
+        })
+        .collect();
+
+    Image::new_with_data(desc, bytes).unwrap()
+}
+
+/// Finds the payload of the first marker segment of the given type.
+fn segment(bytes: &[u8], marker: u8) -> Option<&[u8]> {
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() && bytes[offset] == 0xFF {
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        if bytes[offset + 1] == marker {
+            return Some(&bytes[offset + 4..offset + 2 + length]);
+        }
+        offset += 2 + length;
+    }
+
+    None
+}
+
+#[test]
+fn jpeg_quality_changes_size() {
+    let image = test_image();
+
+    let mut sizes = Vec::new();
+    for quality in [30, 95] {
+        let filename = format!("./test_output/jpeg-quality-{}.jpg", quality);
+        image
+            .save_jpg_with_options(&filename, &JpegOptions::new(quality))
+            .unwrap();
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.desc.width(), 64);
+        assert_eq!(loaded.desc.height(), 48);
+        sizes.push(std::fs::metadata(&filename).unwrap().len());
+    }
+
+    assert!(sizes[0] < sizes[1]);
+}
+
+#[test]
+fn jpeg_subsampling_progressive_and_restart_markers() {
+    let image = test_image();
+
+    for (subsampling, factors) in [
+        (ChromaSubsampling::Yuv444, 0x11),
+        (ChromaSubsampling::Yuv422, 0x21),
+        (ChromaSubsampling::Yuv420, 0x22),
+    ] {
+        let filename = format!("./test_output/jpeg-{:?}.jpg", subsampling);
+        let options = JpegOptions::new(90)
+            .with_subsampling(subsampling)
+            .with_progressive(true)
+            .with_optimize_huffman(true)
+            .with_restart_interval(4);
+        image.save_jpg_with_options(&filename, &options).unwrap();
+
+        let bytes = std::fs::read(&filename).unwrap();
+        // progressive frame header, luma sampling factors of the first component
+        let frame = segment(&bytes, 0xC2).unwrap();
+        assert_eq!(frame[7], factors);
+        let restart = segment(&bytes, 0xDD).unwrap();
+        assert_eq!(restart, &[0, 4]);
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_U8);
+    }
+}
+
+#[test]
+fn save_with_options_routes_by_extension() {
+    let image = test_image();
+    let options = SaveOptions::default()
+        .with_jpeg(JpegOptions::new(80).with_progressive(true))
+        .with_tiff(TiffOptions::new(
+            TiffCompression::Deflate,
+            TiffPredictor::None,
+        ));
+
+    let filename = "./test_output/save-options.jpg";
+    image.save_with_options(filename, &options).unwrap();
+    let bytes = std::fs::read(filename).unwrap();
+    assert!(segment(&bytes, 0xC2).is_some());
+
+    let filename = "./test_output/save-options.tiff";
+    image.save_with_options(filename, &options).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.bytes, image.bytes);
+
+    let filename = "./test_output/save-options-default.jpg";
+    image.save_file(filename).unwrap();
+    let bytes = std::fs::read(filename).unwrap();
+    assert!(segment(&bytes, 0xC0).is_some());
+}
+
+#[test]
+fn optimized_huffman_tables_are_smaller() {
+    let image = test_image();
+
+    let mut sizes = Vec::new();
+    for optimize_huffman in [false, true] {
+        let filename = format!("./test_output/jpeg-huffman-{}.jpg", optimize_huffman);
+        let options = JpegOptions::new(85).with_optimize_huffman(optimize_huffman);
+        image.save_jpg_with_options(&filename, &options).unwrap();
+
+        Image::read_file(&filename).unwrap();
+        sizes.push(std::fs::metadata(&filename).unwrap().len());
+    }
+
+    assert!(sizes[1] < sizes[0]);
+}
+
+#[test]
+fn invalid_jpeg_quality_returns_error() {
+    let image = test_image();
+
+    assert!(image
+        .save_jpg_with_options("./test_output/jpeg-quality-0.jpg", &JpegOptions::new(0))
+        .is_err());
+}
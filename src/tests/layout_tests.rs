@@ -0,0 +1,55 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc, Layout};
+
+#[test]
+fn convert_layout_round_trip() {
+    let desc = ImageDesc::new(2, 1, ColorFormat::RGB_U8);
+    let src = Image::new_with_data(desc, vec![1, 2, 3, 4, 5, 6]).unwrap();
+
+    let planar = src.clone().convert_layout(Layout::Planar).unwrap();
+    assert_eq!(planar.desc.layout(), Layout::Planar);
+    assert_eq!(planar.desc.stride(), 2);
+    assert_eq!(planar.bytes, vec![1, 4, 2, 5, 3, 6]);
+
+    let interleaved = planar.convert_layout(Layout::Interleaved).unwrap();
+    assert_eq!(interleaved.desc, src.desc);
+    assert_eq!(interleaved.bytes, src.bytes);
+}
+
+#[test]
+fn convert_keeps_planar_layout() {
+    let desc = ImageDesc::new(1, 2, ColorFormat::GRAY_ALPHA_U8).with_layout(Layout::Planar);
+    let src = Image::new_with_data(desc, vec![10, 20, 255, 128]).unwrap();
+
+    let result = src.convert(ColorFormat::RGBA_U8).unwrap();
+    assert_eq!(result.desc.layout(), Layout::Planar);
+    assert_eq!(result.bytes, vec![10, 20, 10, 20, 10, 20, 255, 128]);
+}
+
+#[test]
+fn planar_tiff_round_trip() {
+    let tiff = Image::read_file("./test_resources/rgb-sample-32bit.tiff").unwrap();
+    let planar = tiff.clone().convert_layout(Layout::Planar).unwrap();
+
+    planar
+        .save_file("./test_output/planar-rgb-f32.tiff")
+        .unwrap();
+
+    let loaded = Image::read_file("./test_output/planar-rgb-f32.tiff").unwrap();
+    assert_eq!(loaded.desc, planar.desc);
+    assert_eq!(loaded.bytes, planar.bytes);
+
+    let png = Image::read_file("./test_resources/rgba-sample-8bit.png").unwrap();
+    let planar = png.convert_layout(Layout::Planar).unwrap();
+
+    planar
+        .save_file("./test_output/planar-rgba-u8.tiff")
+        .unwrap();
+    planar
+        .save_file("./test_output/planar-rgba-u8.png")
+        .unwrap();
+
+    let loaded = Image::read_file("./test_output/planar-rgba-u8.tiff").unwrap();
+    assert_eq!(loaded.desc, planar.desc);
+    assert_eq!(loaded.bytes, planar.bytes);
+}
@@ -0,0 +1,163 @@
+use crate::color_format::*;
+use crate::ico::IconEntry;
+use crate::image::Image;
+use crate::save_options::*;
+use crate::tests::pattern_image;
+
+#[test]
+fn bmp_round_trips() {
+    for color_format in [
+        ColorFormat::RGB_U8,
+        ColorFormat::RGBA_U8,
+        ColorFormat::GRAY_U8,
+    ] {
+        let image = pattern_image(7, 5, color_format, 0);
+
+        let filename = format!("./test_output/legacy-{}.bmp", color_format);
+        image.save_file(&filename).unwrap();
+        let loaded = Image::read_file(&filename)
+            .unwrap()
+            .convert(color_format)
+            .unwrap();
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+
+    // gray alpha is widened to RGBA
+    let image = pattern_image(3, 3, ColorFormat::GRAY_ALPHA_U8, 0);
+    let filename = "./test_output/legacy-gray-alpha.bmp";
+    image.save_file(filename).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    assert_eq!(
+        loaded.bytes,
+        image.convert(ColorFormat::RGBA_U8).unwrap().bytes
+    );
+}
+
+#[test]
+fn bmp_reads_bottom_up_bgr_rows() {
+    // 2x2 pixels of 24 bits, rows padded to 8 bytes
+    let mut bytes = b"BM".to_vec();
+    bytes.extend((14u32 + 40 + 16).to_le_bytes());
+    bytes.extend([0, 0, 0, 0]);
+    bytes.extend((14u32 + 40).to_le_bytes());
+    bytes.extend(40u32.to_le_bytes());
+    bytes.extend(2i32.to_le_bytes());
+    bytes.extend(2i32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(24u16.to_le_bytes());
+    bytes.extend([0; 24]);
+    // bottom row first, blue green red
+    bytes.extend([255, 0, 0, 0, 255, 0, 0, 0]);
+    bytes.extend([0, 0, 255, 255, 255, 255, 0, 0]);
+
+    let filename = "./test_output/legacy-bottom-up.bmp";
+    std::fs::write(filename, bytes).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_U8);
+    assert_eq!(
+        loaded.bytes,
+        [255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 255, 0]
+    );
+}
+
+#[test]
+fn tga_round_trips_with_and_without_rle() {
+    for color_format in [
+        ColorFormat::GRAY_U8,
+        ColorFormat::GRAY_ALPHA_U8,
+        ColorFormat::RGB_U8,
+        ColorFormat::RGBA_U8,
+    ] {
+        // runs of identical pixels next to varying ones
+        let mut image = pattern_image(9, 4, color_format, 0);
+        let pixel_size = image.bytes_per_pixel() as usize;
+        image.bytes[..4 * pixel_size].fill(7);
+
+        for rle in [true, false] {
+            let filename = format!("./test_output/legacy-{}-{}.tga", color_format, rle);
+            image
+                .save_tga_with_options(&filename, &TgaOptions::default().with_rle(rle))
+                .unwrap();
+
+            let loaded = Image::read_file(&filename).unwrap();
+            assert_eq!(loaded.desc.color_format(), color_format);
+            assert_eq!(loaded.bytes, image.bytes);
+        }
+    }
+}
+
+#[test]
+fn tga_reads_run_length_encoded_bgr() {
+    // 3x1 true color, run length encoded, top-left origin
+    let mut bytes = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend(3u16.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend([24, 0x20]);
+    // a run of two blue pixels then one raw red pixel
+    bytes.extend([0x81, 255, 0, 0, 0x00, 0, 0, 255]);
+
+    let filename = "./test_output/legacy-rle.tga";
+    std::fs::write(filename, bytes).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_U8);
+    assert_eq!(loaded.bytes, [0, 0, 255, 0, 0, 255, 255, 0, 0]);
+}
+
+#[test]
+fn icon_entries_are_separate_images() {
+    let entries = [
+        IconEntry::new(pattern_image(16, 16, ColorFormat::RGBA_U8, 0)),
+        IconEntry::new(pattern_image(256, 256, ColorFormat::RGBA_U8, 0)),
+        IconEntry::new(pattern_image(32, 32, ColorFormat::RGB_U8, 0)),
+    ];
+
+    let filename = "./test_output/legacy-icon.ico";
+    Image::save_icon_entries(&entries, filename).unwrap();
+    let loaded = Image::read_icon_entries(filename).unwrap();
+
+    assert_eq!(loaded.len(), 3);
+    for (loaded, entry) in loaded.iter().zip(&entries) {
+        assert_eq!(loaded.hotspot, None);
+        assert_eq!(loaded.image.desc.color_format(), ColorFormat::RGBA_U8);
+        let expected = entry.image.clone().convert(ColorFormat::RGBA_U8).unwrap();
+        assert_eq!(loaded.image.bytes, expected.bytes);
+    }
+
+    let largest = Image::read_file(filename).unwrap();
+    assert_eq!((largest.desc.width(), largest.desc.height()), (256, 256));
+
+    let too_large = [IconEntry::new(pattern_image(257, 16, ColorFormat::RGBA_U8, 0))];
+    assert!(Image::save_icon_entries(&too_large, filename).is_err());
+}
+
+#[test]
+fn cursor_keeps_hotspots() {
+    let entries = [
+        IconEntry::new(pattern_image(32, 32, ColorFormat::RGBA_U8, 0)).with_hotspot(3, 5),
+        IconEntry::new(pattern_image(16, 16, ColorFormat::RGBA_U8, 0)),
+    ];
+
+    let filename = "./test_output/legacy-cursor.cur";
+    Image::save_icon_entries(&entries, filename).unwrap();
+    let loaded = Image::read_icon_entries(filename).unwrap();
+
+    assert_eq!(loaded[0].hotspot, Some((3, 5)));
+    assert_eq!(loaded[1].hotspot, Some((0, 0)));
+}
+
+#[test]
+fn bmp_and_icon_are_read_by_magic_bytes() {
+    for extension in ["bmp", "ico"] {
+        let image = pattern_image(8, 8, ColorFormat::RGBA_U8, 0);
+        let filename = format!("./test_output/legacy-magic.{}", extension);
+        image.save_file(&filename).unwrap();
+
+        let renamed = format!("./test_output/legacy-magic-{}.bin", extension);
+        std::fs::rename(&filename, &renamed).unwrap();
+        let loaded = Image::read_file(&renamed).unwrap();
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::lut::*;
+
+const CUBE: &str = r#"
+# inverts the colors
+TITLE "Invert"
+LUT_3D_SIZE 2
+DOMAIN_MIN 0.0 0.0 0.0
+DOMAIN_MAX 1.0 1.0 1.0
+
+1.0 1.0 1.0
+0.0 1.0 1.0
+1.0 0.0 1.0
+0.0 0.0 1.0
+1.0 1.0 0.0
+0.0 1.0 0.0
+1.0 0.0 0.0
+0.0 0.0 0.0
+"#;
+
+fn assert_close(a: [f64; 3], b: [f64; 3]) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn parse_cube() {
+    let lut = Lut3D::parse_cube(CUBE).unwrap();
+    assert_eq!(lut.size(), 2);
+    assert_eq!(lut.title(), Some("Invert"));
+
+    for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+        let lut = lut.clone().with_interpolation(interpolation);
+        assert_close(lut.apply([0.2, 0.5, 0.9]), [0.8, 0.5, 0.1]);
+        assert_close(lut.apply([2.0, -1.0, 0.0]), [0.0, 1.0, 1.0]);
+    }
+
+    assert!(Lut3D::parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+    assert!(Lut3D::parse_cube("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+    assert!(Lut3D::parse_cube("LUT_3D_SIZE 2\n0 0 zero\n").is_err());
+    assert!(Lut3D::parse_cube("LUT_3D_SIZE 4294967296\n").is_err());
+    assert!(Lut3D::identity(257).is_err());
+
+    let lut = Lut1D::parse_cube("LUT_1D_SIZE 3\n0 0 0\n0.25 0.5 1\n1 1 1\n").unwrap();
+    assert_eq!(lut.len(), 3);
+    assert_close(lut.apply([0.5, 0.25, 0.75]), [0.25, 0.25, 1.0]);
+}
+
+#[test]
+fn parse_3dl() {
+    let mut text = String::from("# 2 point mesh\n0 1023\n");
+    for r in 0..2 {
+        for g in 0..2 {
+            for b in 0..2 {
+                text.push_str(&format!("{} {} {}\n", r * 4095, g * 4095, b * 4095));
+            }
+        }
+    }
+
+    let lut = Lut3D::parse_3dl(&text).unwrap();
+    assert_eq!(lut, Lut3D::identity(2).unwrap());
+}
+
+#[test]
+fn interpolations_match_on_identity() {
+    let trilinear = Lut3D::identity(17).unwrap();
+    let tetrahedral = trilinear
+        .clone()
+        .with_interpolation(LutInterpolation::Tetrahedral);
+
+    for rgb in [[0.1, 0.7, 0.3], [0.9, 0.2, 0.5], [0.33, 0.33, 0.8]] {
+        assert_close(trilinear.apply(rgb), rgb);
+        assert_close(tetrahedral.apply(rgb), rgb);
+    }
+}
+
+#[test]
+fn apply_lut_to_images() {
+    let png = Image::read_file("./test_resources/rgba-sample-8bit.png").unwrap();
+
+    let identity = png
+        .clone()
+        .apply_lut(&Lut3D::identity(33).unwrap())
+        .unwrap();
+    assert_eq!(identity.desc.color_format(), ColorFormat::RGBA_U8);
+    assert!(png
+        .bytes
+        .iter()
+        .zip(&identity.bytes)
+        .all(|(a, b)| a.abs_diff(*b) <= 1));
+
+    let inverted = png
+        .clone()
+        .apply_lut(&Lut3D::parse_cube(CUBE).unwrap())
+        .unwrap();
+    for (a, b) in png.bytes.chunks(4).zip(inverted.bytes.chunks(4)) {
+        assert!(a[0].abs_diff(255 - b[0]) <= 1);
+        assert_eq!(a[3], b[3]);
+    }
+    inverted.save_file("./test_output/lut-invert.png").unwrap();
+
+    let curve = Lut1D::from_curve(256, |v| v * v).unwrap();
+    for color_format in [
+        ColorFormat::RGB_U16,
+        ColorFormat::RGBA_F32,
+        ColorFormat::RGB_F64,
+        ColorFormat::RGBA_I16,
+    ] {
+        let image = png.clone().convert(color_format).unwrap();
+        let result = image.apply_lut(&curve).unwrap();
+        assert_eq!(result.desc.color_format(), color_format);
+    }
+
+    let desc = ImageDesc::new(1, 1, ColorFormat::GRAY_U8);
+    let gray = Image::new_with_data(desc, vec![0]).unwrap();
+    assert!(gray.apply_lut(&curve).is_err());
+}
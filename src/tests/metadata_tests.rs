@@ -0,0 +1,87 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::math::Rect;
+use crate::metadata::*;
+use crate::save_options::*;
+
+fn image_with_metadata(metadata: Metadata) -> Image {
+    let desc = ImageDesc::new(20, 10, ColorFormat::RGB_U8);
+    let mut image = Image::new_with_data(desc, (0..600).map(|i| i as u8).collect()).unwrap();
+    image.metadata = metadata;
+
+    image
+}
+
+fn full_metadata() -> Metadata {
+    Metadata::default()
+        .with_resolution(Resolution::new(300.0, 150.5, ResolutionUnit::Centimeter))
+        .with_software("imaginarium")
+        .with_date_time("2024:05:17 10:42:00")
+        .with_image_description("Slide 12, section B")
+        .with_artist("Lab 3")
+        .with_custom_tag(
+            65000,
+            TagValue::Undefined(r#"{"pipeline":"stitch","version":2}"#.as_bytes().to_vec()),
+        )
+        .with_custom_tag(65001, TagValue::Ascii("provenance".to_string()))
+        .with_custom_tag(65002, TagValue::Byte(vec![1]))
+        .with_custom_tag(65003, TagValue::Short(vec![1, 2, 3]))
+        .with_custom_tag(65004, TagValue::Long(vec![70000]))
+        .with_custom_tag(65005, TagValue::Rational(vec![[1, 3], [5, 7]]))
+        .with_custom_tag(65006, TagValue::SignedLong(vec![-5, 12]))
+        .with_custom_tag(65007, TagValue::Float(vec![0.25]))
+        .with_custom_tag(65008, TagValue::Double(vec![1.5, -2.75]))
+        .with_custom_tag(65009, TagValue::SignedByte(vec![-7]))
+        .with_custom_tag(65010, TagValue::SignedShort(vec![-300, 300]))
+        .with_custom_tag(65011, TagValue::SignedRational(vec![[-1, 3]]))
+}
+
+#[test]
+fn metadata_round_trip() {
+    let image = image_with_metadata(full_metadata());
+
+    for big_tiff in [false, true] {
+        let filename = format!("./test_output/metadata-{}.tiff", big_tiff);
+        let options = TiffOptions::default().with_big_tiff(big_tiff);
+        image.save_tiff_with_options(&filename, &options).unwrap();
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.metadata, image.metadata);
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn metadata_is_kept_per_page() {
+    let pages = [
+        image_with_metadata(full_metadata()),
+        image_with_metadata(Metadata::default().with_artist("Second page")),
+        image_with_metadata(Metadata::default()),
+    ];
+    let filename = "./test_output/metadata-pages.tiff";
+    Image::save_all_pages(&pages, filename, &TiffOptions::default()).unwrap();
+
+    let loaded = Image::read_all_pages(filename).unwrap();
+    for (loaded, page) in loaded.iter().zip(&pages) {
+        assert_eq!(loaded.metadata, page.metadata);
+    }
+
+    let region = Image::read_region(filename, Rect::new(2, 2, 5, 5)).unwrap();
+    assert_eq!(region.metadata, pages[0].metadata);
+}
+
+#[test]
+fn metadata_survives_conversion() {
+    let image = image_with_metadata(full_metadata())
+        .convert(ColorFormat::RGBA_U16)
+        .unwrap();
+    assert_eq!(image.metadata, full_metadata());
+}
+
+#[test]
+fn non_ascii_text_returns_error() {
+    let image = image_with_metadata(Metadata::default().with_artist("Zoë"));
+    assert!(image
+        .save_file("./test_output/metadata-non-ascii.tiff")
+        .is_err());
+}
@@ -1,6 +1,94 @@
 #[cfg(test)]
+use crate::color_format::{ChannelType, ColorFormat};
+#[cfg(test)]
+use crate::image::{Image, ImageDesc};
+
+#[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod image_tests;
 #[cfg(test)]
 mod conversion_tests;
 #[cfg(test)]
 mod color_format_tests;
+#[cfg(test)]
+mod layout_tests;
+#[cfg(test)]
+mod color_space_tests;
+#[cfg(test)]
+mod color_primaries_tests;
+#[cfg(test)]
+mod icc_tests;
+#[cfg(test)]
+mod tone_mapping_tests;
+#[cfg(test)]
+mod lut_tests;
+#[cfg(test)]
+mod tiff_options_tests;
+#[cfg(test)]
+mod multipage_tests;
+#[cfg(test)]
+mod tiled_tiff_tests;
+#[cfg(test)]
+mod region_tests;
+#[cfg(test)]
+mod pyramid_tests;
+#[cfg(test)]
+mod metadata_tests;
+#[cfg(test)]
+mod exif_tests;
+#[cfg(test)]
+mod png_metadata_tests;
+#[cfg(test)]
+mod jpeg_options_tests;
+#[cfg(test)]
+mod auto_adapt_tests;
+#[cfg(test)]
+mod png_packing_tests;
+#[cfg(test)]
+mod exr_tests;
+#[cfg(test)]
+mod hdr_tests;
+#[cfg(test)]
+mod pnm_tests;
+#[cfg(test)]
+mod legacy_format_tests;
+#[cfg(test)]
+mod webp_gif_tests;
+#[cfg(test)]
+mod animation_tests;
+#[cfg(test)]
+mod qoi_tests;
+#[cfg(test)]
+mod texture_tests;
+
+/// RGBA U16 image with distinct values per channel, column and row.
+#[cfg(test)]
+pub(crate) fn gradient(width: u32, height: u32) -> Image {
+    let desc = ImageDesc::new(width, height, ColorFormat::RGBA_U16);
+    let values: Vec<u16> = (0..width * height * 4)
+        .map(|i| ((i % 4) * 9000 + (i / 4 % width) * 500 + (i / 4 / width) * 300) as u16)
+        .collect();
+
+    Image::new_with_data(desc, bytemuck::cast_slice(&values).to_vec()).unwrap()
+}
+
+/// Image with varied values, different seeds giving different images.
+#[cfg(test)]
+pub(crate) fn pattern_image(
+    width: u32,
+    height: u32,
+    color_format: ColorFormat,
+    seed: u32,
+) -> Image {
+    let desc = ImageDesc::new(width, height, color_format);
+    let bytes = match color_format.channel_type {
+        ChannelType::Float => (0..desc.size_in_bytes() / 4)
+            .flat_map(|i| ((i * 7 + seed) as f32 / 13.0).to_ne_bytes())
+            .collect(),
+        _ => (0..desc.size_in_bytes())
+            .map(|i| ((i * 31 + seed * 17) % 255) as u8)
+            .collect(),
+    };
+
+    Image::new_with_data(desc, bytes).unwrap()
+}
@@ -0,0 +1,62 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn pages() -> Vec<Image> {
+    let desc = ImageDesc::new(23, 11, ColorFormat::RGBA_U16);
+    let values: Vec<u16> = (0..23 * 11 * 4).map(|i| (i * 37) as u16).collect();
+    let source = Image::new_with_data(desc, bytemuck::cast_slice(&values).to_vec()).unwrap();
+
+    vec![
+        source.clone(),
+        source.clone().convert(ColorFormat::GRAY_U8).unwrap(),
+        source.convert(ColorFormat::RGB_F32).unwrap(),
+    ]
+}
+
+#[test]
+fn multipage_round_trip() {
+    let pages = pages();
+
+    for compression in [TiffCompression::None, TiffCompression::Zstd] {
+        let filename = format!("./test_output/multipage-{:?}.tiff", compression);
+        let options = TiffOptions::new(compression, TiffPredictor::None);
+        Image::save_all_pages(&pages, &filename, &options).unwrap();
+
+        assert_eq!(Image::page_count(&filename).unwrap(), pages.len());
+
+        let loaded = Image::read_all_pages(&filename).unwrap();
+        assert_eq!(loaded.len(), pages.len());
+        for (loaded, page) in loaded.iter().zip(&pages) {
+            assert_eq!(loaded.desc, page.desc);
+            assert_eq!(loaded.bytes, page.bytes);
+        }
+    }
+}
+
+#[test]
+fn page_iterator_is_lazy() {
+    let pages = pages();
+    let filename = "./test_output/multipage-lazy.tiff";
+    Image::save_all_pages(&pages, filename, &TiffOptions::default()).unwrap();
+
+    let mut iter = Image::read_pages(filename).unwrap();
+    let last = iter.nth(2).unwrap().unwrap();
+    assert_eq!(last.desc, pages[2].desc);
+    assert!(iter.next().is_none());
+
+    // read_file keeps returning the first page
+    let first = Image::read_file(filename).unwrap();
+    assert_eq!(first.bytes, pages[0].bytes);
+    assert_eq!(
+        Image::page_count("./test_resources/rgb-sample-32bit.tiff").unwrap(),
+        1
+    );
+}
+
+#[test]
+fn empty_page_list_returns_error() {
+    assert!(
+        Image::save_all_pages(&[], "./test_output/no-pages.tiff", &TiffOptions::default()).is_err()
+    );
+}
@@ -0,0 +1,103 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::metadata::*;
+use crate::save_options::*;
+
+fn test_image(color_format: ColorFormat) -> Image {
+    let desc = ImageDesc::new(20, 10, color_format);
+    let size = desc.size_in_bytes() as usize;
+
+    Image::new_with_data(desc, (0..size).map(|i| (i * 7) as u8).collect()).unwrap()
+}
+
+#[test]
+fn png_metadata_round_trip() {
+    let mut image = test_image(ColorFormat::RGB_U8);
+    image.metadata = Metadata::default()
+        .with_resolution(Resolution::new(118.0, 59.0, ResolutionUnit::Centimeter))
+        .with_software("imaginarium")
+        .with_image_description("Słide 12")
+        .with_artist("Lab 3")
+        .with_gamma(0.45455)
+        .with_chromaticities(Chromaticities {
+            white: [0.3127, 0.329],
+            red: [0.68, 0.32],
+            green: [0.265, 0.69],
+            blue: [0.15, 0.06],
+        })
+        .with_text_chunk(TextChunk::new("Comment", "plain"))
+        .with_text_chunk(TextChunk::new("Source", "compressed ".repeat(20)).with_compression(true))
+        .with_text_chunk(
+            TextChunk::new("Title", "Échantillon ✓")
+                .with_compression(true)
+                .with_international(InternationalText {
+                    language_tag: "fr".to_string(),
+                    translated_keyword: "Titre".to_string(),
+                }),
+        );
+
+    let filename = "./test_output/png-metadata.png";
+    image.save_file(filename).unwrap();
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.bytes, image.bytes);
+    assert_eq!(loaded.metadata.software, image.metadata.software);
+    assert_eq!(
+        loaded.metadata.image_description,
+        image.metadata.image_description
+    );
+    assert_eq!(loaded.metadata.artist, image.metadata.artist);
+    assert_eq!(loaded.metadata.text_chunks, image.metadata.text_chunks);
+    assert_eq!(loaded.metadata.text("Title"), Some("Échantillon ✓"));
+    assert_eq!(loaded.metadata.resolution, image.metadata.resolution);
+    assert!((loaded.metadata.gamma.unwrap() - 0.45455).abs() < 1e-5);
+
+    let chromaticities = loaded.metadata.chromaticities.unwrap();
+    assert!((chromaticities.white[0] - 0.3127).abs() < 1e-5);
+    assert!((chromaticities.green[1] - 0.69).abs() < 1e-5);
+}
+
+#[test]
+fn png_srgb_and_dpi() {
+    let mut image = test_image(ColorFormat::RGBA_U16);
+    image.metadata = Metadata::default()
+        .with_resolution(Resolution::dpi(300.0))
+        .with_srgb_intent(RenderingIntent::RelativeColorimetric);
+
+    let filename = "./test_output/png-srgb.png";
+    image.save_file(filename).unwrap();
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.bytes, image.bytes);
+    assert_eq!(
+        loaded.metadata.srgb_intent,
+        Some(RenderingIntent::RelativeColorimetric)
+    );
+    // stored in pixels per meter
+    let resolution = loaded.metadata.resolution.unwrap();
+    assert_eq!(resolution.unit, ResolutionUnit::Centimeter);
+    assert!((resolution.x * 2.54 - 300.0).abs() < 0.01);
+}
+
+#[test]
+fn png_compression_options() {
+    let image = test_image(ColorFormat::GRAY_U16);
+
+    let mut sizes = Vec::new();
+    for (i, compression) in [PngCompression::None, PngCompression::High]
+        .into_iter()
+        .enumerate()
+    {
+        for filter in [PngFilter::None, PngFilter::Paeth, PngFilter::Adaptive] {
+            let filename = format!("./test_output/png-options-{}-{:?}.png", i, filter);
+            let options = PngOptions::new(compression, filter);
+            image.save_png_with_options(&filename, &options).unwrap();
+
+            let loaded = Image::read_file(&filename).unwrap();
+            assert_eq!(loaded.bytes, image.bytes);
+            sizes.push(std::fs::metadata(&filename).unwrap().len());
+        }
+    }
+
+    assert!(sizes[0] > sizes[3]);
+}
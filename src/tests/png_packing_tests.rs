@@ -0,0 +1,63 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+fn packed() -> PngOptions {
+    PngOptions::default().with_pack_range(true)
+}
+
+#[test]
+fn float_png_restores_range() {
+    let desc = ImageDesc::new(16, 8, ColorFormat::RGB_F32);
+    let pixels: Vec<f32> = (0..16 * 8 * 3).map(|i| i as f32 / 100.0 - 0.5).collect();
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/packed-float.png";
+    assert!(image.save_file(filename).is_err());
+    image.save_png_with_options(filename, &packed()).unwrap();
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGB_F32);
+    assert!(loaded.metadata.text_chunks.is_empty());
+
+    let step = (pixels[pixels.len() - 1] - pixels[0]) / 65535.0;
+    let values: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    for (value, expected) in values.iter().zip(&pixels) {
+        assert!((value - expected).abs() <= step);
+    }
+}
+
+#[test]
+fn integers_within_16bit_range_are_exact() {
+    let desc = ImageDesc::new(8, 8, ColorFormat::GRAY_ALPHA_U32);
+    let pixels: Vec<u32> = (0..8 * 8 * 2).map(|i| 100_000 + i * 400).collect();
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/packed-u32.png";
+    image.save_png_with_options(filename, &packed()).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_ALPHA_U32);
+    assert_eq!(loaded.bytes, image.bytes);
+
+    let desc = ImageDesc::new(4, 1, ColorFormat::GRAY_I16);
+    let pixels = [i16::MIN, -1, 0, i16::MAX];
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec()).unwrap();
+
+    let filename = "./test_output/packed-i16.png";
+    image.save_png_with_options(filename, &packed()).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_I16);
+    assert_eq!(loaded.bytes, image.bytes);
+}
+
+#[test]
+fn unpacked_formats_are_saved_as_is() {
+    let desc = ImageDesc::new(4, 4, ColorFormat::RGBA_U8);
+    let image = Image::new_with_data(desc, (0..64).collect()).unwrap();
+
+    let filename = "./test_output/packed-u8.png";
+    image.save_png_with_options(filename, &packed()).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    assert_eq!(loaded.bytes, image.bytes);
+}
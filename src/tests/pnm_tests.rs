@@ -0,0 +1,133 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+use crate::tests::pattern_image;
+
+#[test]
+fn binary_and_ascii_round_trip() {
+    for (extension, color_format) in [
+        ("pgm", ColorFormat::GRAY_U8),
+        ("pgm", ColorFormat::GRAY_U16),
+        ("ppm", ColorFormat::RGB_U8),
+        ("ppm", ColorFormat::RGB_U16),
+        ("pnm", ColorFormat::RGB_U8),
+        ("pam", ColorFormat::GRAY_ALPHA_U8),
+        ("pam", ColorFormat::RGBA_U16),
+    ] {
+        let image = pattern_image(13, 5, color_format, 0);
+
+        for ascii in [false, true] {
+            let filename = format!(
+                "./test_output/netpbm-{}-{}.{}",
+                color_format, ascii, extension
+            );
+            image
+                .save_pnm_with_options(&filename, &PnmOptions::default().with_ascii(ascii))
+                .unwrap();
+
+            let loaded = Image::read_file(&filename).unwrap();
+            assert_eq!(loaded.desc.color_format(), color_format);
+            assert_eq!(loaded.bytes, image.bytes);
+        }
+    }
+}
+
+#[test]
+fn pbm_is_thresholded() {
+    let desc = ImageDesc::new(11, 3, ColorFormat::GRAY_U8);
+    let bytes: Vec<u8> = (0..11 * 3).map(|i| (i * 23 % 256) as u8).collect();
+    let image = Image::new_with_data(desc, bytes.clone()).unwrap();
+    let expected: Vec<u8> = bytes
+        .iter()
+        .map(|&value| if value < 128 { 0 } else { 255 })
+        .collect();
+
+    for ascii in [false, true] {
+        let filename = format!("./test_output/netpbm-bits-{}.pbm", ascii);
+        image
+            .save_pnm_with_options(&filename, &PnmOptions::default().with_ascii(ascii))
+            .unwrap();
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_U8);
+        assert_eq!(loaded.bytes, expected);
+    }
+}
+
+#[test]
+fn headers_with_comments_and_small_max_values() {
+    let filename = "./test_output/netpbm-comments.pbm";
+    std::fs::write(filename, "P1\n# a comment\n3 2\n010\n1 0 1\n").unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.bytes, [255, 0, 255, 0, 255, 0]);
+
+    let filename = "./test_output/netpbm-maxval.pgm";
+    std::fs::write(filename, "P2 2 1 1000 # gray\n0 1000\n").unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_U16);
+    let values: &[u16] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(values, [0, u16::MAX]);
+
+    let filename = "./test_output/netpbm-maxval.pam";
+    let mut bytes =
+        b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 2\nMAXVAL 15\nTUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n".to_vec();
+    bytes.extend([15, 0, 5, 15]);
+    std::fs::write(filename, bytes).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::GRAY_ALPHA_U8);
+    assert_eq!(loaded.bytes, [255, 0, 85, 255]);
+}
+
+#[test]
+fn mismatched_formats_are_rejected() {
+    let rgb = pattern_image(4, 4, ColorFormat::RGB_U8, 0);
+    assert!(rgb.save_file("./test_output/netpbm-rgb.pgm").is_err());
+    let gray = pattern_image(4, 4, ColorFormat::GRAY_U8, 0);
+    assert!(gray.save_file("./test_output/netpbm-gray.ppm").is_err());
+
+    let filename = "./test_output/netpbm-depth.pam";
+    std::fs::write(
+        filename,
+        "P7\nWIDTH 1\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\nTUPLTYPE RGB\nENDHDR\n\0\0",
+    )
+    .unwrap();
+    assert!(Image::read_file(filename).is_err());
+}
+
+#[test]
+fn unknown_extensions_are_read_by_magic_bytes() {
+    for (extension, color_format) in [
+        ("png", ColorFormat::RGB_U8),
+        ("tiff", ColorFormat::GRAY_U16),
+        ("ppm", ColorFormat::RGB_U8),
+        ("pam", ColorFormat::RGBA_U8),
+    ] {
+        let image = pattern_image(6, 4, color_format, 0);
+        let filename = format!("./test_output/magic.{}", extension);
+        image.save_file(&filename).unwrap();
+
+        let renamed = format!("./test_output/magic-{}.bin", extension);
+        std::fs::rename(&filename, &renamed).unwrap();
+        let loaded = Image::read_file(&renamed).unwrap();
+        assert_eq!(loaded.desc.color_format(), color_format);
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+
+    let filename = "./test_output/magic-unknown";
+    std::fs::write(filename, "not an image").unwrap();
+    assert!(Image::read_file(filename).is_err());
+}
+
+#[test]
+fn empty_and_oversized_images_are_rejected() {
+    let filename = "./test_output/netpbm-invalid.pnm";
+    for header in [
+        "P4\n0 1\n",
+        "P4\n4 0\n",
+        "P5\n0 3\n255\n",
+        "P6\n4294967295 4294967295\n65535\n",
+    ] {
+        std::fs::write(filename, header).unwrap();
+        assert!(Image::read_file(filename).is_err(), "{:?}", header);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc, Layout};
+use crate::pyramid::ResampleFilter;
+use crate::save_options::*;
+
+fn checkerboard(width: u32, height: u32) -> Image {
+    let desc = ImageDesc::new(width, height, ColorFormat::GRAY_F32);
+    let values: Vec<f32> = (0..width * height)
+        .map(|i| ((i % width + i / width) % 2) as f32)
+        .collect();
+
+    Image::new_with_data(desc, bytemuck::cast_slice(&values).to_vec()).unwrap()
+}
+
+#[test]
+fn pyramid_level_sizes() {
+    let levels = checkerboard(100, 37)
+        .build_pyramid(10, ResampleFilter::Box)
+        .unwrap();
+
+    let sizes: Vec<(u32, u32)> = levels
+        .iter()
+        .map(|level| (level.desc.width(), level.desc.height()))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            (100, 37),
+            (50, 19),
+            (25, 10),
+            (13, 5),
+            (7, 3),
+            (4, 2),
+            (2, 1),
+            (1, 1)
+        ]
+    );
+
+    let levels = checkerboard(100, 37)
+        .build_pyramid(3, ResampleFilter::Box)
+        .unwrap();
+    assert_eq!(levels.len(), 3);
+
+    assert!(checkerboard(4, 4)
+        .build_pyramid(0, ResampleFilter::Box)
+        .is_err());
+}
+
+#[test]
+fn pyramid_filters_average_checkerboard() {
+    for filter in [
+        ResampleFilter::Box,
+        ResampleFilter::Triangle,
+        ResampleFilter::Lanczos3,
+    ] {
+        let levels = checkerboard(64, 48).build_pyramid(2, filter).unwrap();
+        let values: &[f32] = bytemuck::cast_slice(&levels[1].bytes);
+
+        // a checkerboard averages to mid gray away from the borders
+        for y in 4..20 {
+            for x in 4..28 {
+                let value = values[y * 32 + x];
+                assert!((value - 0.5).abs() < 1e-5, "{:?} {}", filter, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn pyramid_keeps_integer_format() {
+    let desc = ImageDesc::new(40, 40, ColorFormat::RGB_U8);
+    let image = Image::new_with_data(desc, vec![200; 40 * 40 * 3]).unwrap();
+
+    let levels = image.build_pyramid(3, ResampleFilter::Lanczos3).unwrap();
+    assert_eq!(levels[2].desc.color_format(), ColorFormat::RGB_U8);
+    assert!(levels[2].bytes.iter().all(|&v| v.abs_diff(200) <= 1));
+}
+
+#[test]
+fn planar_pyramid_matches_interleaved() {
+    let desc = ImageDesc::new(20, 12, ColorFormat::RGB_U8);
+    let bytes = (0..20 * 12 * 3).map(|i| (i * 7 % 256) as u8).collect();
+    let image = Image::new_with_data(desc, bytes).unwrap();
+
+    let interleaved = image.clone().build_pyramid(3, ResampleFilter::Box).unwrap();
+    let planar = image
+        .convert_layout(Layout::Planar)
+        .unwrap()
+        .build_pyramid(3, ResampleFilter::Box)
+        .unwrap();
+    for (planar, interleaved) in planar.into_iter().zip(&interleaved) {
+        assert_eq!(planar.desc.layout(), Layout::Planar);
+        let planar = planar.convert_layout(Layout::Interleaved).unwrap();
+        assert_eq!(planar.bytes, interleaved.bytes);
+    }
+}
+
+#[test]
+fn pyramid_tiff_round_trip() {
+    let levels = checkerboard(300, 200)
+        .build_pyramid(4, ResampleFilter::Triangle)
+        .unwrap();
+    let filename = "./test_output/pyramid.tiff";
+    let options = TiffOptions::new(TiffCompression::Deflate, TiffPredictor::FloatingPoint);
+    Image::save_pyramid(&levels, filename, &options).unwrap();
+
+    assert_eq!(Image::page_count(filename).unwrap(), 4);
+    let pages = Image::read_all_pages(filename).unwrap();
+    for (page, level) in pages.iter().zip(&levels) {
+        assert_eq!(page.desc, level.desc);
+        assert_eq!(page.bytes, level.bytes);
+    }
+
+    let width = |scale| {
+        Image::read_pyramid_level(filename, scale)
+            .unwrap()
+            .desc
+            .width()
+    };
+    assert_eq!(width(1.0), 300);
+    assert_eq!(width(0.6), 300);
+    assert_eq!(width(0.5), 150);
+    assert_eq!(width(0.3), 150);
+    assert_eq!(width(0.2), 75);
+    assert_eq!(width(0.01), 38);
+
+    // plain multi-page files have no reduced-resolution levels
+    let filename = "./test_output/pyramid-pages.tiff";
+    Image::save_all_pages(&levels, filename, &options).unwrap();
+    assert_eq!(
+        Image::read_pyramid_level(filename, 0.01)
+            .unwrap()
+            .desc
+            .width(),
+        300
+    );
+}
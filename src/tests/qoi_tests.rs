@@ -0,0 +1,119 @@
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+
+/// Runs, small and large differences, repeated colors and alpha changes.
+fn image(width: u32, height: u32, color_format: ColorFormat) -> Image {
+    let channels = color_format.channel_count.channel_count();
+    let desc = ImageDesc::new(width, height, color_format);
+    let bytes = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let pixel = match y % 4 {
+                0 => [10, 20, 30, 255],
+                1 => [x as u8, (x / 2) as u8, 255 - x as u8, 255],
+                2 => [
+                    (x * 37) as u8,
+                    (x * 91) as u8,
+                    (x * 13) as u8,
+                    (x * 7) as u8,
+                ],
+                _ => [(x % 3 * 100) as u8, 50, 50, (x % 2 * 255) as u8],
+            };
+            pixel.into_iter().take(channels as usize)
+        })
+        .collect();
+
+    Image::new_with_data(desc, bytes).unwrap()
+}
+
+#[test]
+fn qoi_round_trips_rgb_and_rgba() {
+    for color_format in [ColorFormat::RGB_U8, ColorFormat::RGBA_U8] {
+        let image = image(70, 9, color_format);
+
+        let filename = format!("./test_output/qoi-{}.qoi", color_format);
+        image.save_file(&filename).unwrap();
+        let loaded = Image::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.desc.color_format(), color_format);
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn qoi_matches_the_reference_codec() {
+    let image = image(70, 9, ColorFormat::RGBA_U8);
+
+    let filename = "./test_output/qoi-reference.qoi";
+    image.save_file(filename).unwrap();
+    let decoded = ::image::open(filename).unwrap().into_rgba8();
+    assert_eq!(decoded.as_raw(), &image.bytes);
+
+    let filename = "./test_output/qoi-reference-encoded.qoi";
+    ::image::RgbaImage::from_raw(70, 9, image.bytes.clone())
+        .unwrap()
+        .save(filename)
+        .unwrap();
+    assert_eq!(Image::read_file(filename).unwrap().bytes, image.bytes);
+}
+
+#[test]
+fn qoi_colorspace_follows_the_transfer_function() {
+    for transfer_function in [TransferFunction::Srgb, TransferFunction::Linear] {
+        let mut image = image(4, 4, ColorFormat::RGB_U8);
+        image.desc = image.desc.with_transfer_function(transfer_function);
+
+        let filename = "./test_output/qoi-colorspace";
+        image
+            .save_with_options(format!("{}.qoi", filename), &SaveOptions::default())
+            .unwrap();
+        // read by magic bytes
+        std::fs::rename(format!("{}.qoi", filename), filename).unwrap();
+        let loaded = Image::read_file(filename).unwrap();
+
+        assert_eq!(loaded.desc.transfer_function(), transfer_function);
+    }
+}
+
+#[test]
+fn gray_and_wide_images_are_adapted_for_qoi() {
+    let image = image(8, 8, ColorFormat::RGB_U8);
+    let gray = image.clone().convert(ColorFormat::GRAY_ALPHA_U16).unwrap();
+
+    let filename = "./test_output/qoi-adapted.qoi";
+    assert!(gray.save_file(filename).is_err());
+
+    let options = SaveOptions::default().with_auto_adapt(true);
+    let adaptations = gray.save_with_options(filename, &options).unwrap();
+    assert_eq!(
+        adaptations,
+        vec![
+            Adaptation::ReducedDepth {
+                from: ChannelSize::_16bit,
+                to: ChannelSize::_8bit
+            },
+            Adaptation::ExpandedGray,
+        ]
+    );
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    assert!(loaded
+        .bytes
+        .chunks_exact(4)
+        .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2] && pixel[3] == 255));
+}
+
+#[test]
+fn truncated_qoi_is_rejected() {
+    let filename = "./test_output/qoi-truncated.qoi";
+    image(16, 16, ColorFormat::RGBA_U8)
+        .save_file(filename)
+        .unwrap();
+    let data = std::fs::read(filename).unwrap();
+    std::fs::write(filename, &data[..data.len() / 2]).unwrap();
+
+    assert!(Image::read_file(filename).is_err());
+}
@@ -0,0 +1,80 @@
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::math::Rect;
+use crate::save_options::*;
+use crate::tests::gradient;
+
+fn crop(image: &Image, rect: Rect) -> Vec<u8> {
+    let desc = &image.desc;
+    let pixel_size = match desc.layout() {
+        Layout::Interleaved => image.bytes_per_pixel(),
+        Layout::Planar => desc.color_format().channel_size.byte_count(),
+    };
+
+    let mut bytes = Vec::new();
+    for plane in 0..desc.plane_count() {
+        for y in rect.y..rect.bottom() {
+            let start =
+                plane * desc.plane_size_in_bytes() + y * desc.stride() + rect.x * pixel_size;
+            let end = start + rect.width * pixel_size;
+            bytes.extend_from_slice(&image.bytes[start as usize..end as usize]);
+        }
+    }
+    bytes
+}
+
+#[test]
+fn region_matches_crop() {
+    let source = gradient(75, 61);
+    let cases = [
+        ("strips", source.clone(), TiffOptions::default()),
+        (
+            "tiles",
+            source.clone().convert(ColorFormat::RGB_F32).unwrap(),
+            TiffOptions::new(TiffCompression::Lzw, TiffPredictor::FloatingPoint).with_tiles(16, 32),
+        ),
+        (
+            "planar-zstd-tiles",
+            source.clone().convert_layout(Layout::Planar).unwrap(),
+            TiffOptions::new(TiffCompression::Zstd, TiffPredictor::Horizontal).with_tiles(32, 16),
+        ),
+        (
+            "big-tiff",
+            source.convert(ColorFormat::GRAY_U8).unwrap(),
+            TiffOptions::default()
+                .with_big_tiff(true)
+                .with_tiles(16, 16),
+        ),
+    ];
+
+    for (name, image, options) in cases {
+        let filename = format!("./test_output/region-{}.tiff", name);
+        image.save_tiff_with_options(&filename, &options).unwrap();
+
+        for rect in [
+            Rect::new(10, 5, 30, 20),
+            Rect::new(0, 0, 1, 1),
+            Rect::new(33, 17, 42, 44),
+            Rect::new(0, 0, 75, 61),
+        ] {
+            let region = Image::read_region(&filename, rect).unwrap();
+            assert_eq!(region.desc.width(), rect.width, "{}", name);
+            assert_eq!(region.desc.height(), rect.height, "{}", name);
+            assert_eq!(region.desc.color_format(), image.desc.color_format());
+            assert_eq!(region.desc.layout(), image.desc.layout());
+            assert_eq!(region.bytes, crop(&image, rect), "{} {:?}", name, rect);
+        }
+    }
+}
+
+#[test]
+fn region_outside_image_returns_error() {
+    let filename = "./test_output/region-outside.tiff";
+    gradient(20, 10).save_file(filename).unwrap();
+
+    assert!(Image::read_region(filename, Rect::new(15, 0, 6, 4)).is_err());
+    assert!(Image::read_region(filename, Rect::new(0, 8, 4, 3)).is_err());
+    assert!(Image::read_region(filename, Rect::new(0, 0, 0, 3)).is_err());
+    assert!(Image::read_region(filename, Rect::new(u32::MAX, 0, 2, 3)).is_err());
+    assert!(Image::read_region(filename, Rect::new(0, 1, 4, u32::MAX)).is_err());
+}
@@ -0,0 +1,230 @@
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{Image, ImageDesc};
+use crate::pyramid::ResampleFilter;
+use crate::save_options::*;
+use crate::tests::pattern_image;
+use crate::texture::Texture;
+
+fn assert_same(loaded: &Texture, texture: &Texture) {
+    assert_eq!(loaded.cube_map, texture.cube_map);
+    assert_eq!(loaded.layers.len(), texture.layers.len());
+    for (loaded, chain) in loaded.layers.iter().zip(&texture.layers) {
+        assert_eq!(loaded.len(), chain.len());
+        for (loaded, image) in loaded.iter().zip(chain) {
+            assert_eq!(loaded.desc.color_format(), image.desc.color_format());
+            assert_eq!(loaded.desc.width(), image.desc.width());
+            assert_eq!(loaded.desc.height(), image.desc.height());
+            assert_eq!(loaded.bytes, image.bytes);
+        }
+    }
+}
+
+#[test]
+fn texture_arrays_with_mip_chains_round_trip() {
+    for color_format in [
+        ColorFormat::GRAY_U8,
+        ColorFormat::GRAY_ALPHA_U16,
+        ColorFormat::RGBA_U8,
+        ColorFormat::RGBA_I16,
+        ColorFormat::GRAY_U32,
+        ColorFormat::RGB_F32,
+        ColorFormat::RGBA_F32,
+    ] {
+        let texture = Texture::new(
+            (0..3)
+                .map(|seed| vec![pattern_image(13, 7, color_format, seed)])
+                .collect(),
+            false,
+        )
+        .with_mipmaps(ResampleFilter::Box)
+        .unwrap();
+
+        let sizes: Vec<(u32, u32)> = texture.layers[0]
+            .iter()
+            .map(|image| (image.desc.width(), image.desc.height()))
+            .collect();
+        assert_eq!(sizes, vec![(13, 7), (6, 3), (3, 1), (1, 1)]);
+
+        for extension in ["dds", "ktx2"] {
+            let filename = format!("./test_output/texture-{}.{}", color_format, extension);
+            texture.save_file(&filename).unwrap();
+            let loaded = Texture::read_file(&filename).unwrap();
+
+            assert_same(&loaded, &texture);
+        }
+    }
+}
+
+#[test]
+fn cube_maps_round_trip() {
+    let texture = Texture::new(
+        (0..12)
+            .map(|seed| vec![pattern_image(8, 8, ColorFormat::RGBA_U8, seed)])
+            .collect(),
+        true,
+    )
+    .with_mipmaps(ResampleFilter::Triangle)
+    .unwrap();
+
+    for extension in ["dds", "ktx2"] {
+        let filename = format!("./test_output/texture-cube.{}", extension);
+        texture.save_file(&filename).unwrap();
+        let loaded = Texture::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.layers[0].len(), 4);
+        assert_same(&loaded, &texture);
+    }
+}
+
+#[test]
+fn srgb_formats_follow_the_transfer_function() {
+    for transfer_function in [TransferFunction::Srgb, TransferFunction::Linear] {
+        let mut image = pattern_image(4, 4, ColorFormat::RGBA_U8, 0);
+        image.desc = image.desc.with_transfer_function(transfer_function);
+
+        for extension in ["dds", "ktx2"] {
+            let filename = format!(
+                "./test_output/texture-{:?}.{}",
+                transfer_function, extension
+            );
+            image.save_file(&filename).unwrap();
+            let loaded = Image::read_file(&filename).unwrap();
+
+            assert_eq!(loaded.desc.transfer_function(), transfer_function);
+            assert_eq!(loaded.bytes, image.bytes);
+        }
+    }
+
+    let float = pattern_image(4, 4, ColorFormat::RGB_F32, 0);
+    float.save_file("./test_output/texture-float.ktx2").unwrap();
+    let loaded = Image::read_file("./test_output/texture-float.ktx2").unwrap();
+    assert_eq!(loaded.desc.transfer_function(), TransferFunction::Linear);
+}
+
+#[test]
+fn half_float_dds_is_read_as_float() {
+    let halves: Vec<u16> = [0x3C00u16, 0x3800, 0x0000, 0xC000].repeat(4);
+    let image = Image::new_with_data(
+        ImageDesc::new(2, 2, ColorFormat::RGBA_U16),
+        bytemuck::cast_slice(&halves).to_vec(),
+    )
+    .unwrap();
+
+    let filename = "./test_output/texture-half.dds";
+    image.save_file(filename).unwrap();
+    // R16G16B16A16_UNORM into R16G16B16A16_FLOAT, of the same size
+    let mut data = std::fs::read(filename).unwrap();
+    data[128..132].copy_from_slice(&10u32.to_le_bytes());
+    std::fs::write(filename, data).unwrap();
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_F32);
+    assert_eq!(loaded.desc.transfer_function(), TransferFunction::Linear);
+    let pixels: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(&pixels[..4], &[1.0, 0.5, 0.0, -2.0]);
+}
+
+#[test]
+fn rgb_is_adapted_for_dds() {
+    let image = pattern_image(5, 5, ColorFormat::RGB_U8, 0);
+
+    image.save_file("./test_output/texture-rgb.ktx2").unwrap();
+    assert_eq!(
+        Image::read_file("./test_output/texture-rgb.ktx2")
+            .unwrap()
+            .bytes,
+        image.bytes
+    );
+
+    let filename = "./test_output/texture-rgb.dds";
+    assert!(image.save_file(filename).is_err());
+    let options = SaveOptions::default().with_auto_adapt(true);
+    let adaptations = image.save_with_options(filename, &options).unwrap();
+    assert_eq!(adaptations, vec![Adaptation::AddedAlpha]);
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    assert_eq!(
+        loaded.bytes,
+        image.convert(ColorFormat::RGBA_U8).unwrap().bytes
+    );
+}
+
+#[test]
+fn invalid_textures_are_rejected() {
+    let filename = "./test_output/texture-invalid.dds";
+
+    let not_square = Texture::new(
+        (0..6)
+            .map(|seed| vec![pattern_image(8, 4, ColorFormat::RGBA_U8, seed)])
+            .collect(),
+        true,
+    );
+    assert!(not_square.save_file(filename).is_err());
+
+    let missing_faces = Texture::new(
+        vec![vec![pattern_image(8, 8, ColorFormat::RGBA_U8, 0)]],
+        true,
+    );
+    assert!(missing_faces.save_file(filename).is_err());
+
+    let wrong_level = Texture::new(
+        vec![vec![
+            pattern_image(8, 8, ColorFormat::RGBA_U8, 0),
+            pattern_image(3, 4, ColorFormat::RGBA_U8, 0),
+        ]],
+        false,
+    );
+    assert!(wrong_level.save_file(filename).is_err());
+
+    let mixed_formats = Texture::new(
+        vec![
+            vec![pattern_image(8, 8, ColorFormat::RGBA_U8, 0)],
+            vec![pattern_image(8, 8, ColorFormat::RGBA_U16, 0)],
+        ],
+        false,
+    );
+    assert!(mixed_formats.save_file(filename).is_err());
+
+    assert!(Texture::default().save_file(filename).is_err());
+    assert!(Texture::from_image(pattern_image(4, 4, ColorFormat::RGBA_U64, 0))
+        .save_file("./test_output/texture-invalid.ktx2")
+        .is_err());
+}
+
+#[test]
+fn oversized_headers_are_rejected() {
+    let texture = Texture::from_image(pattern_image(4, 4, ColorFormat::RGBA_U8, 0));
+    let patch = |filename: &str, offset: usize, value: u32| {
+        let mut data = std::fs::read(filename).unwrap();
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        let patched = filename.replace("oversized", &format!("oversized-{}", offset));
+        std::fs::write(&patched, data).unwrap();
+        patched
+    };
+
+    let filename = "./test_output/texture-oversized.dds";
+    texture.save_file(filename).unwrap();
+    // width, array size and, with the mip count flag set, mip count
+    for (offset, value) in [(16, 0), (140, u32::MAX), (8, u32::MAX)] {
+        let patched = patch(filename, offset, value);
+        let patched = if offset == 8 {
+            patch(&patched, 28, u32::MAX)
+        } else {
+            patched
+        };
+        assert!(Texture::read_file(&patched).is_err(), "{}", offset);
+    }
+
+    let filename = "./test_output/texture-oversized.ktx2";
+    texture.save_file(filename).unwrap();
+    // width, element count and level count
+    for (offset, value) in [(20, 0), (32, u32::MAX), (40, u32::MAX)] {
+        assert!(
+            Texture::read_file(patch(filename, offset, value)).is_err(),
+            "{}",
+            offset
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::save_options::*;
+use crate::tests::gradient;
+
+const COMPRESSIONS: [TiffCompression; 5] = [
+    TiffCompression::None,
+    TiffCompression::Lzw,
+    TiffCompression::Deflate,
+    TiffCompression::PackBits,
+    TiffCompression::Zstd,
+];
+
+fn all_color_formats() -> Vec<ColorFormat> {
+    let mut color_formats = Vec::new();
+    for channel_count in [
+        ChannelCount::Gray,
+        ChannelCount::GrayAlpha,
+        ChannelCount::Rgb,
+        ChannelCount::Rgba,
+    ] {
+        for channel_size in [
+            ChannelSize::_8bit,
+            ChannelSize::_16bit,
+            ChannelSize::_32bit,
+            ChannelSize::_64bit,
+        ] {
+            for channel_type in [ChannelType::UInt, ChannelType::Int, ChannelType::Float] {
+                let color_format = ColorFormat::from((channel_count, channel_size, channel_type));
+                if color_format.validate().is_ok() {
+                    color_formats.push(color_format);
+                }
+            }
+        }
+    }
+    color_formats
+}
+
+#[test]
+fn compressed_round_trip() {
+    let source = gradient(67, 13);
+
+    for color_format in all_color_formats() {
+        let image = source.clone().convert(color_format).unwrap();
+        let predictor = match color_format.channel_type {
+            ChannelType::Float => TiffPredictor::FloatingPoint,
+            _ => TiffPredictor::Horizontal,
+        };
+
+        for compression in COMPRESSIONS {
+            for predictor in [TiffPredictor::None, predictor] {
+                let filename = format!(
+                    "./test_output/compressed-{}-{:?}-{:?}.tiff",
+                    color_format, compression, predictor
+                );
+                image
+                    .save_tiff_with_options(&filename, &TiffOptions::new(compression, predictor))
+                    .unwrap();
+
+                // the tiff decoder does not read gray images with alpha
+                if color_format.channel_count == ChannelCount::GrayAlpha {
+                    continue;
+                }
+
+                let loaded = Image::read_file(&filename).unwrap();
+                assert_eq!(loaded.desc, image.desc, "{}", filename);
+                assert_eq!(loaded.bytes, image.bytes, "{}", filename);
+            }
+        }
+    }
+}
+
+#[test]
+fn compressed_planar_round_trip() {
+    let image = gradient(45, 9).convert_layout(Layout::Planar).unwrap();
+
+    for compression in COMPRESSIONS {
+        let filename = format!("./test_output/compressed-planar-{:?}.tiff", compression);
+        let options = TiffOptions::new(compression, TiffPredictor::Horizontal);
+        image.save_tiff_with_options(&filename, &options).unwrap();
+
+        let loaded = Image::read_file(&filename).unwrap();
+        assert_eq!(loaded.desc, image.desc);
+        assert_eq!(loaded.bytes, image.bytes);
+    }
+}
+
+#[test]
+fn compression_reduces_size() {
+    let tiff = Image::read_file("./test_resources/rgb-sample-32bit.tiff")
+        .unwrap()
+        .convert(ColorFormat::RGB_F64)
+        .unwrap();
+
+    tiff.save_file("./test_output/uncompressed-f64.tiff").unwrap();
+    tiff.save_tiff_with_options(
+        "./test_output/deflate-f64.tiff",
+        &TiffOptions::new(TiffCompression::Deflate, TiffPredictor::FloatingPoint),
+    )
+    .unwrap();
+
+    let size = |filename| std::fs::metadata(filename).unwrap().len();
+    assert!(
+        size("./test_output/deflate-f64.tiff") < size("./test_output/uncompressed-f64.tiff")
+    );
+}
+
+#[test]
+fn invalid_predictor_returns_error() {
+    let image = gradient(4, 4);
+
+    let options = TiffOptions::new(TiffCompression::Lzw, TiffPredictor::FloatingPoint);
+    assert!(image
+        .save_tiff_with_options("./test_output/invalid-predictor.tiff", &options)
+        .is_err());
+
+    let options = TiffOptions::new(TiffCompression::Lzw, TiffPredictor::Horizontal);
+    assert!(image
+        .convert(ColorFormat::RGBA_F32)
+        .unwrap()
+        .save_tiff_with_options("./test_output/invalid-predictor.tiff", &options)
+        .is_err());
+}
+
+#[test]
+fn zstd_chunk_outside_of_file_returns_error() {
+    let filename = "./test_output/zstd-bad-byte-count.tiff";
+    let options = TiffOptions::new(TiffCompression::Zstd, TiffPredictor::None);
+    gradient(4, 1)
+        .save_tiff_with_options(filename, &options)
+        .unwrap();
+
+    // a single strip keeps its byte count inline in the StripByteCounts entry
+    let mut bytes = std::fs::read(filename).unwrap();
+    set_inline_value(&mut bytes, 279, 0xFFFF_FFF0);
+    std::fs::write(filename, &bytes).unwrap();
+
+    assert!(Image::read_file(filename).is_err());
+}
+
+#[test]
+fn short_zstd_chunk_returns_error() {
+    let filename = "./test_output/zstd-short-chunk.tiff";
+    let options = TiffOptions::new(TiffCompression::Zstd, TiffPredictor::None);
+    gradient(4, 1)
+        .save_tiff_with_options(filename, &options)
+        .unwrap();
+
+    // a wider image needs more data than the strip decompresses to
+    let mut bytes = std::fs::read(filename).unwrap();
+    set_inline_value(&mut bytes, 256, 8);
+    std::fs::write(filename, &bytes).unwrap();
+
+    assert!(Image::read_file(filename).is_err());
+}
+
+/// Overwrites the inline value of an entry of the first IFD of a little endian TIFF.
+fn set_inline_value(bytes: &mut [u8], tag: u16, value: u32) {
+    let ifd = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let entry_count = u16::from_le_bytes(bytes[ifd..ifd + 2].try_into().unwrap()) as usize;
+    let entry = (0..entry_count)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16::from_le_bytes(bytes[entry..entry + 2].try_into().unwrap()) == tag)
+        .unwrap();
+    bytes[entry + 8..entry + 12].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,107 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc, Layout};
+use crate::save_options::*;
+use crate::tests::gradient;
+
+fn assert_round_trip(image: &Image, filename: &str, options: &TiffOptions) {
+    image.save_tiff_with_options(filename, options).unwrap();
+
+    let loaded = Image::read_file(filename).unwrap();
+    assert_eq!(loaded.desc, image.desc, "{}", filename);
+    assert_eq!(loaded.bytes, image.bytes, "{}", filename);
+}
+
+#[test]
+fn tiled_round_trip() {
+    // edge tiles are partial in both directions
+    let source = gradient(67, 45);
+
+    for color_format in [
+        ColorFormat::GRAY_U8,
+        ColorFormat::RGB_U16,
+        ColorFormat::RGBA_I32,
+        ColorFormat::RGB_F32,
+    ] {
+        let image = source.clone().convert(color_format).unwrap();
+        let predictor = match color_format.channel_type {
+            ChannelType::Float => TiffPredictor::FloatingPoint,
+            _ => TiffPredictor::Horizontal,
+        };
+
+        for compression in [
+            TiffCompression::None,
+            TiffCompression::Lzw,
+            TiffCompression::Zstd,
+        ] {
+            let filename = format!(
+                "./test_output/tiled-{}-{:?}.tiff",
+                color_format, compression
+            );
+            let options = TiffOptions::new(compression, predictor).with_tiles(32, 16);
+            assert_round_trip(&image, &filename, &options);
+        }
+    }
+}
+
+#[test]
+fn tiled_planar_round_trip() {
+    let image = gradient(50, 20).convert_layout(Layout::Planar).unwrap();
+
+    for compression in [TiffCompression::Deflate, TiffCompression::Zstd] {
+        let filename = format!("./test_output/tiled-planar-{:?}.tiff", compression);
+        let options = TiffOptions::new(compression, TiffPredictor::Horizontal).with_tiles(16, 16);
+        assert_round_trip(&image, &filename, &options);
+    }
+}
+
+#[test]
+fn big_tiff_round_trip() {
+    let image = gradient(40, 30);
+
+    let options = TiffOptions::new(TiffCompression::Lzw, TiffPredictor::None).with_big_tiff(true);
+    assert_round_trip(&image, "./test_output/big-strips.tiff", &options);
+    assert_round_trip(
+        &image,
+        "./test_output/big-tiles.tiff",
+        &options.with_tiles(16, 16),
+    );
+
+    // version 43 in the header marks a BigTIFF
+    let header = std::fs::read("./test_output/big-tiles.tiff").unwrap();
+    assert_eq!(&header[..4], b"II\x2b\x00");
+
+    let pages = [image.clone(), image.convert(ColorFormat::GRAY_F64).unwrap()];
+    Image::save_all_pages(&pages, "./test_output/big-pages.tiff", &options).unwrap();
+    let loaded = Image::read_all_pages("./test_output/big-pages.tiff").unwrap();
+    assert_eq!(loaded[1].desc, pages[1].desc);
+    assert_eq!(loaded[1].bytes, pages[1].bytes);
+}
+
+#[test]
+fn invalid_tile_size_returns_error() {
+    let image = gradient(8, 8);
+
+    for (width, height) in [(0, 16), (16, 20), (24, 16)] {
+        let options = TiffOptions::default().with_tiles(width, height);
+        assert!(image
+            .save_tiff_with_options("./test_output/invalid-tiles.tiff", &options)
+            .is_err());
+    }
+}
+
+#[test]
+fn empty_image_returns_error() {
+    for (width, height) in [(0, 4), (4, 0)] {
+        let desc = ImageDesc::new(width, height, ColorFormat::RGBA_U8);
+        let image = Image::new_with_data(desc, Vec::new()).unwrap();
+
+        for options in [
+            TiffOptions::default(),
+            TiffOptions::default().with_tiles(16, 16),
+        ] {
+            assert!(image
+                .save_tiff_with_options("./test_output/empty.tiff", &options)
+                .is_err());
+        }
+    }
+}
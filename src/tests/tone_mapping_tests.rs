@@ -0,0 +1,77 @@
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::image::{Image, ImageDesc};
+use crate::tone_mapping::*;
+
+const OPERATORS: [ToneMapOperator; 5] = [
+    ToneMapOperator::Reinhard,
+    ToneMapOperator::ExtendedReinhard,
+    ToneMapOperator::AcesFilmic,
+    ToneMapOperator::Hable,
+    ToneMapOperator::AgX,
+];
+
+#[test]
+fn operators_are_monotonic_and_bounded() {
+    for operator in OPERATORS {
+        let tone_mapping = ToneMapping::new(operator);
+        let mut previous = -1.0;
+
+        for i in 0..200 {
+            let v = tone_mapping.apply([i as f64 * 0.1; 3])[1];
+            assert!((0.0..=1.0).contains(&v), "{:?}: {}", operator, v);
+            assert!(v >= previous - 1e-9, "{:?} is not monotonic", operator);
+            previous = v;
+        }
+    }
+
+    let reinhard = ToneMapping::new(ToneMapOperator::Reinhard);
+    assert!((reinhard.apply([1.0; 3])[0] - 0.5).abs() < 1e-12);
+    assert!((reinhard.with_exposure(1.0).apply([0.5; 3])[0] - 0.5).abs() < 1e-12);
+
+    for operator in [ToneMapOperator::ExtendedReinhard, ToneMapOperator::Hable] {
+        let tone_mapping = ToneMapping::new(operator).with_white_point(4.0);
+        assert!(tone_mapping.apply([4.0; 3])[0] > 0.999, "{:?}", operator);
+    }
+
+    let hable = ToneMapping::new(ToneMapOperator::Hable).with_white_point(4.0);
+    assert!((hable.apply([4.0; 3])[0] - 1.0).abs() < 1e-12);
+    assert!(hable.apply([3.9; 3])[0] < 1.0);
+}
+
+#[test]
+fn tone_map_image() {
+    let tiff = Image::read_file("./test_resources/rgb-sample-32bit.tiff").unwrap();
+    let hdr = tiff
+        .convert(ColorFormat::RGBA_F32)
+        .unwrap()
+        .convert_transfer_function(TransferFunction::Linear)
+        .unwrap();
+
+    for operator in OPERATORS {
+        let mapped = hdr
+            .clone()
+            .tone_map(ToneMapping::new(operator).with_exposure(2.0))
+            .unwrap();
+        assert_eq!(mapped.desc.transfer_function(), TransferFunction::Srgb);
+
+        let pixels: &[f32] = bytemuck::cast_slice(&mapped.bytes);
+        assert!(pixels
+            .chunks(4)
+            .all(|p| p[..3].iter().all(|v| (0.0..=1.0).contains(v))));
+
+        // alpha is kept
+        let source: &[f32] = bytemuck::cast_slice(&hdr.bytes);
+        assert!(pixels.iter().zip(source).skip(3).step_by(4).all(|(a, b)| a == b));
+
+        mapped
+            .convert(ColorFormat::RGB_U8)
+            .unwrap()
+            .save_file(format!("./test_output/tone-map-{:?}.png", operator))
+            .unwrap();
+    }
+
+    let desc = ImageDesc::new(1, 1, ColorFormat::RGB_U8);
+    let src = Image::new_with_data(desc, vec![0, 255, 0]).unwrap();
+    assert!(src.tone_map(ToneMapOperator::AcesFilmic).is_err());
+}
@@ -0,0 +1,129 @@
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+use crate::save_options::*;
+use crate::tests::pattern_image;
+
+#[test]
+fn lossless_webp_round_trips_as_rgba() {
+    for color_format in [
+        ColorFormat::GRAY_U8,
+        ColorFormat::GRAY_ALPHA_U8,
+        ColorFormat::RGB_U8,
+        ColorFormat::RGBA_U8,
+    ] {
+        let image = pattern_image(19, 11, color_format, 0);
+
+        let filename = format!("./test_output/webp-{}.webp", color_format);
+        image.save_file(&filename).unwrap();
+        let loaded = Image::read_file(&filename).unwrap();
+
+        assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+        assert_eq!(
+            loaded.bytes,
+            image.convert(ColorFormat::RGBA_U8).unwrap().bytes
+        );
+    }
+}
+
+#[test]
+fn lossy_webp_is_decoded() {
+    let loaded = Image::read_file("./test_resources/rgb-sample-lossy.webp").unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    assert_eq!((loaded.desc.width(), loaded.desc.height()), (32, 24));
+    // a gradient of x * 8 red, y * 10 green and 128 blue, opaque
+    for (i, pixel) in loaded.bytes.chunks_exact(4).enumerate() {
+        let (x, y) = ((i % 32) as i32, (i / 32) as i32);
+        assert!((pixel[0] as i32 - x * 8).abs() < 16);
+        assert!((pixel[1] as i32 - y * 10).abs() < 16);
+        assert!((pixel[2] as i32 - 128).abs() < 16);
+        assert_eq!(pixel[3], 255);
+    }
+}
+
+#[test]
+fn gif_with_few_colors_round_trips() {
+    // four colors fit the palette exactly, the transparent pixels lose their color
+    let desc = ImageDesc::new(16, 8, ColorFormat::RGBA_U8);
+    let colors = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [0, 0, 0, 0],
+    ];
+    let bytes: Vec<u8> = (0..16 * 8).flat_map(|i| colors[i / 2 % 4]).collect();
+    let image = Image::new_with_data(desc, bytes).unwrap();
+
+    let filename = "./test_output/gif-colors.gif";
+    image
+        .save_gif_with_options(filename, &GifOptions::default().with_speed(1))
+        .unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    for (pixel, expected) in loaded
+        .bytes
+        .chunks_exact(4)
+        .zip(image.bytes.chunks_exact(4))
+    {
+        assert_eq!(pixel[3], expected[3]);
+        if expected[3] == 255 {
+            assert_eq!(pixel, expected);
+        }
+    }
+}
+
+#[test]
+fn gif_quantizes_many_colors() {
+    let image = pattern_image(64, 64, ColorFormat::RGB_U8, 0);
+
+    let filename = "./test_output/gif-quantized.gif";
+    image.save_file(filename).unwrap();
+    let loaded = Image::read_file(filename).unwrap();
+
+    let mut colors: Vec<&[u8]> = loaded.bytes.chunks_exact(4).collect();
+    colors.sort();
+    colors.dedup();
+    assert!(colors.len() <= 256);
+
+    assert!(image
+        .save_gif_with_options(filename, &GifOptions::default().with_speed(31))
+        .is_err());
+}
+
+#[test]
+fn webp_and_gif_are_read_by_magic_bytes() {
+    for extension in ["webp", "gif"] {
+        let image = pattern_image(8, 8, ColorFormat::RGBA_U8, 0);
+        let filename = format!("./test_output/webp-gif-magic.{}", extension);
+        image.save_file(&filename).unwrap();
+
+        let renamed = format!("./test_output/webp-gif-magic-{}.bin", extension);
+        std::fs::rename(&filename, &renamed).unwrap();
+        let loaded = Image::read_file(&renamed).unwrap();
+        assert_eq!(loaded.desc.color_format(), ColorFormat::RGBA_U8);
+    }
+}
+
+#[test]
+fn wide_images_are_adapted_for_webp_and_gif() {
+    let image = pattern_image(6, 5, ColorFormat::RGBA_U16, 0);
+    let options = SaveOptions::default().with_auto_adapt(true);
+
+    for filename in ["./test_output/adapted.webp", "./test_output/adapted.gif"] {
+        assert!(image.save_file(filename).is_err());
+
+        let adaptations = image.save_with_options(filename, &options).unwrap();
+        assert_eq!(
+            adaptations,
+            vec![Adaptation::ReducedDepth {
+                from: ChannelSize::_16bit,
+                to: ChannelSize::_8bit
+            }]
+        );
+        assert_eq!(
+            Image::read_file(filename).unwrap().desc.color_format(),
+            ColorFormat::RGBA_U8
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use crate::color_format::*;
+use crate::color_primaries::TransferFunction;
+use crate::dds_extentions::{load_dds, save_dds};
+use crate::image::{float_working_format, format_from_magic, Image, ImageDesc, Layout};
+use crate::ktx2_extentions::{load_ktx2, save_ktx2};
+use crate::pyramid::{resample, ResampleFilter};
+
+/// Image of a GPU texture container: the mip chains of its array elements and cube faces.
+#[derive(Clone, Default)]
+pub struct Texture {
+    /// Mip chains, largest level first, of every array element, or of the six faces
+    /// of every cube in +X, -X, +Y, -Y, +Z, -Z order for cube maps.
+    pub layers: Vec<Vec<Image>>,
+    pub cube_map: bool,
+}
+
+impl Texture {
+    pub fn new(layers: Vec<Vec<Image>>, cube_map: bool) -> Self {
+        Self { layers, cube_map }
+    }
+    /// A 2D texture of a single level.
+    pub fn from_image(image: Image) -> Self {
+        Self::new(vec![vec![image]], false)
+    }
+
+    /// Replaces the mip chain of every layer by the full chain down to 1x1, each level
+    /// half the size of the previous one rounded down, downsampled from the first level.
+    pub fn with_mipmaps(mut self, filter: ResampleFilter) -> anyhow::Result<Self> {
+        for chain in &mut self.layers {
+            let image = chain
+                .first()
+                .ok_or(anyhow::anyhow!("Texture layer without levels"))?;
+            *chain = mip_chain(image, filter)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Reads a DDS with a DX10 header or a KTX2 of an uncompressed format.
+    ///
+    /// Single channel formats are read as gray and two channel ones as gray alpha,
+    /// 16 bit floats as 32 bit ones. Float and non-sRGB 8 bit formats with an sRGB
+    /// counterpart are marked as linear.
+    pub fn read_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Texture> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str());
+        let format = match extension {
+            Some(extension @ ("dds" | "ktx2")) => extension,
+            _ => format_from_magic(&filename)?,
+        };
+
+        match format {
+            "dds" => load_dds(filename),
+            "ktx2" => load_ktx2(filename),
+
+            _ => Err(anyhow::anyhow!("Unsupported texture format: {}", format)),
+        }
+    }
+
+    /// Saves the texture as a DDS with a DX10 header or as a KTX2, as the extension says.
+    ///
+    /// Gray is written as a red channel and gray alpha as red and green. 8 bit colors
+    /// use the sRGB format when the image has an sRGB transfer function and the
+    /// format has one. DDS has no 8 or 16 bit RGB formats.
+    pub fn save_file<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
+        let extension = filename
+            .as_ref()
+            .extension()
+            .and_then(|os_str| os_str.to_str())
+            .ok_or(anyhow::anyhow!("Failed to get file extension"))?;
+
+        match extension {
+            "dds" => save_dds(self, filename),
+            "ktx2" => save_ktx2(self, filename),
+
+            _ => Err(anyhow::anyhow!(
+                "Unsupported texture extension: {}",
+                extension
+            )),
+        }
+    }
+
+    /// Returns the largest level of the first layer.
+    pub(crate) fn into_image(self) -> anyhow::Result<Image> {
+        self.layers
+            .into_iter()
+            .next()
+            .and_then(|chain| chain.into_iter().next())
+            .ok_or(anyhow::anyhow!("Texture without layers"))
+    }
+
+    /// Checks that the layers share their color format and mip chain sizes, and that
+    /// cube faces are square and complete.
+    pub(crate) fn shape(&self) -> anyhow::Result<TextureShape> {
+        let first = self
+            .layers
+            .first()
+            .and_then(|chain| chain.first())
+            .ok_or(anyhow::anyhow!("No texture layer to save"))?;
+        let (width, height) = (first.desc.width(), first.desc.height());
+        let level_count = self.layers[0].len();
+
+        let face_count = if self.cube_map { 6 } else { 1 };
+        if !self.layers.len().is_multiple_of(face_count) {
+            return Err(anyhow::anyhow!(
+                "Cube maps need 6 faces per cube, got {} layers",
+                self.layers.len()
+            ));
+        }
+        if self.cube_map && width != height {
+            return Err(anyhow::anyhow!(
+                "Cube map faces must be square, got {}x{}",
+                width,
+                height
+            ));
+        }
+        if level_count > mip_count(width, height) {
+            return Err(anyhow::anyhow!(
+                "Too many mip levels for {}x{}: {}",
+                width,
+                height,
+                level_count
+            ));
+        }
+
+        for chain in &self.layers {
+            if chain.len() != level_count {
+                return Err(anyhow::anyhow!("Texture layers differ in mip level count"));
+            }
+            for (level, image) in chain.iter().enumerate() {
+                if image.desc.color_format() != first.desc.color_format() {
+                    return Err(anyhow::anyhow!("Texture images differ in color format"));
+                }
+                let size = mip_size(width, height, level);
+                if (image.desc.width(), image.desc.height()) != size {
+                    return Err(anyhow::anyhow!(
+                        "Mip level {} must be {}x{}, got {}x{}",
+                        level,
+                        size.0,
+                        size.1,
+                        image.desc.width(),
+                        image.desc.height()
+                    ));
+                }
+            }
+        }
+
+        Ok(TextureShape {
+            desc: first.desc.clone(),
+            level_count,
+            face_count,
+            element_count: self.layers.len() / face_count,
+        })
+    }
+}
+
+/// Dimensions shared by the layers of a texture.
+pub(crate) struct TextureShape {
+    /// Description of the largest level.
+    pub(crate) desc: ImageDesc,
+    pub(crate) level_count: usize,
+    pub(crate) face_count: usize,
+    pub(crate) element_count: usize,
+}
+
+/// Size of a mip level, halved and rounded down per level, at least a pixel.
+pub(crate) fn mip_size(width: u32, height: u32, level: usize) -> (u32, u32) {
+    (
+        width.checked_shr(level as u32).unwrap_or(0).max(1),
+        height.checked_shr(level as u32).unwrap_or(0).max(1),
+    )
+}
+
+/// Number of levels of a full mip chain.
+fn mip_count(width: u32, height: u32) -> usize {
+    (u32::BITS - width.max(height).leading_zeros()) as usize
+}
+
+/// Validates the size and level count read from a texture header and returns the
+/// byte size of the texels of one mip chain, for the loaders to check against the
+/// data before allocating anything.
+pub(crate) fn mip_chain_size(
+    width: u32,
+    height: u32,
+    level_count: usize,
+    texel_size: usize,
+) -> anyhow::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(anyhow::anyhow!(
+            "Invalid texture size: {}x{}",
+            width,
+            height
+        ));
+    }
+    if level_count > mip_count(width, height) {
+        return Err(anyhow::anyhow!(
+            "Invalid level count {} for a {}x{} texture",
+            level_count,
+            width,
+            height
+        ));
+    }
+
+    (0..level_count)
+        .try_fold(0usize, |total, level| {
+            let (width, height) = mip_size(width, height, level);
+            (width as usize)
+                .checked_mul(height as usize)?
+                .checked_mul(texel_size)?
+                .checked_add(total)
+        })
+        .ok_or(anyhow::anyhow!(
+            "Texture of {}x{} is too large",
+            width,
+            height
+        ))
+}
+
+fn mip_chain(image: &Image, filter: ResampleFilter) -> anyhow::Result<Vec<Image>> {
+    let color_format = image.desc.color_format();
+    let layout = image.desc.layout();
+    let (width, height) = (image.desc.width(), image.desc.height());
+
+    let working = image
+        .clone()
+        .convert(float_working_format(color_format))?
+        .convert_layout(Layout::Interleaved)?;
+    let mut chain = vec![image.clone()];
+    for level in 1..mip_count(width, height) {
+        let (level_width, level_height) = mip_size(width, height, level);
+        let desc = ImageDesc::new(level_width, level_height, working.desc.color_format())
+            .with_color_space(working.desc.color_space())
+            .with_color_primaries(working.desc.color_primaries())
+            .with_transfer_function(working.desc.transfer_function());
+        let mut resampled = Image::new_empty(desc)?;
+        resampled.icc_profile = image.icc_profile.clone();
+        // every level is resampled from the first, keeping odd sizes aligned
+        resample(&working, &mut resampled, filter)?;
+
+        chain.push(resampled.convert(color_format)?.convert_layout(layout)?);
+    }
+
+    Ok(chain)
+}
+
+/// How a GPU format stores the channels of a color format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TexelEncoding {
+    /// As the color format, 8 and 16 bit integers being normalized.
+    Plain,
+    /// 8 bit sRGB encoded colors with a linear alpha.
+    Srgb,
+    /// 16 bit floats, read as 32 bit ones.
+    Half,
+}
+
+/// GPU format codes and the color format and encoding of their texels.
+pub(crate) type FormatTable = [(u32, ColorFormat, TexelEncoding)];
+
+/// Returns the code of the format storing the image, sRGB for 8 bit sRGB images
+/// when the table has one.
+pub(crate) fn format_code(table: &FormatTable, desc: &ImageDesc) -> Option<u32> {
+    let find = |encoding| {
+        table
+            .iter()
+            .find(|(_, color_format, texels)| {
+                *color_format == desc.color_format() && *texels == encoding
+            })
+            .map(|(code, _, _)| *code)
+    };
+
+    match desc.transfer_function() {
+        TransferFunction::Srgb => find(TexelEncoding::Srgb).or_else(|| find(TexelEncoding::Plain)),
+        _ => find(TexelEncoding::Plain),
+    }
+}
+
+/// Returns the color format and encoding of a format code, with the transfer function
+/// it implies if any.
+pub(crate) fn format_from_code(
+    table: &FormatTable,
+    code: u32,
+) -> Option<(ColorFormat, TexelEncoding, Option<TransferFunction>)> {
+    let &(_, color_format, encoding) = table.iter().find(|(entry, _, _)| *entry == code)?;
+    let has_srgb = table
+        .iter()
+        .any(|(_, entry, texels)| *entry == color_format && *texels == TexelEncoding::Srgb);
+    let transfer_function = match encoding {
+        TexelEncoding::Srgb => Some(TransferFunction::Srgb),
+        TexelEncoding::Half => Some(TransferFunction::Linear),
+        TexelEncoding::Plain if has_srgb || color_format.channel_type == ChannelType::Float => {
+            Some(TransferFunction::Linear)
+        }
+        TexelEncoding::Plain => None,
+    };
+
+    Some((color_format, encoding, transfer_function))
+}
+
+/// Size in bytes of a texel stored with an encoding.
+pub(crate) fn texel_size(color_format: ColorFormat, encoding: TexelEncoding) -> usize {
+    match encoding {
+        TexelEncoding::Half => color_format.channel_count.channel_count() as usize * 2,
+        _ => color_format.byte_count() as usize,
+    }
+}
+
+/// Returns the tightly packed little endian texels of an image.
+pub(crate) fn texel_bytes(image: &Image) -> anyhow::Result<Vec<u8>> {
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    let mut bytes = image.bytes[..image.desc.size_in_bytes() as usize].to_vec();
+    to_little_endian(&mut bytes, image.desc.color_format().channel_size);
+
+    Ok(bytes)
+}
+
+/// Builds an image from tightly packed little endian texels.
+pub(crate) fn image_from_texels(
+    texels: &[u8],
+    (width, height): (u32, u32),
+    color_format: ColorFormat,
+    encoding: TexelEncoding,
+    transfer_function: Option<TransferFunction>,
+) -> anyhow::Result<Image> {
+    let mut bytes = match encoding {
+        TexelEncoding::Half => texels
+            .chunks_exact(2)
+            .flat_map(|half| {
+                half::f16::from_le_bytes([half[0], half[1]])
+                    .to_f32()
+                    .to_le_bytes()
+            })
+            .collect(),
+        _ => texels.to_vec(),
+    };
+    to_little_endian(&mut bytes, color_format.channel_size);
+
+    let mut desc = ImageDesc::new(width, height, color_format);
+    if let Some(transfer_function) = transfer_function {
+        desc = desc.with_transfer_function(transfer_function);
+    }
+
+    Image::new_with_data(desc, bytes)
+}
+
+/// Swaps the bytes of every channel between native and little endian order.
+fn to_little_endian(bytes: &mut [u8], channel_size: ChannelSize) {
+    if cfg!(target_endian = "big") {
+        for channel in bytes.chunks_exact_mut(channel_size.byte_count() as usize) {
+            channel.reverse();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use ::image::codecs::tga::TgaEncoder;
+use ::image::ExtendedColorType;
+
+use crate::color_format::*;
+use crate::image::{Image, Layout};
+use crate::save_options::TgaOptions;
+
+/// Writes an 8 bit image as a BGR(A) or gray TGA, run-length encoded unless disabled.
+pub(crate) fn save_tga<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &TgaOptions,
+) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format.channel_size != ChannelSize::_8bit
+        || color_format.channel_type != ChannelType::UInt
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported TGA color format: {}",
+            color_format
+        ));
+    }
+    let color_type = match color_format.channel_count {
+        // @formatter:off
+        ChannelCount::Gray => ExtendedColorType::L8,
+        ChannelCount::GrayAlpha => ExtendedColorType::La8,
+        ChannelCount::Rgb => ExtendedColorType::Rgb8,
+        ChannelCount::Rgba => ExtendedColorType::Rgba8,
+        // @formatter:on
+    };
+
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    let mut encoder = TgaEncoder::new(BufWriter::new(File::create(filename)?));
+    if !options.rle {
+        encoder = encoder.disable_rle();
+    }
+    encoder.encode(
+        &image.bytes[..image.desc.size_in_bytes() as usize],
+        image.desc.width(),
+        image.desc.height(),
+        color_type,
+    )?;
+
+    Ok(())
+}
@@ -1,14 +1,94 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::mem::{align_of, size_of};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
-use bytemuck::{Pod, PodCastError};
+use bytemuck::Pod;
+use num_traits::{WrappingAdd, WrappingSub};
+use ruzstd::encoding::CompressionLevel;
+use tiff::decoder::ifd::Value;
+use tiff::decoder::{ChunkType, Decoder, DecodingResult};
 use tiff::encoder::colortype::*;
-use tiff::encoder::{colortype, TiffEncoder, TiffValue};
-use tiff::tags::{PhotometricInterpretation, SampleFormat};
+use tiff::encoder::compression::{CompressionAlgorithm, Deflate, Lzw, Packbits};
+use tiff::encoder::{colortype, DirectoryEncoder, Rational, TiffEncoder, TiffKind, TiffValue};
+use tiff::tags::{
+    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, Predictor, SampleFormat,
+    Tag, Type,
+};
 
 use crate::color_format::*;
-use crate::image::Image;
+use crate::exif::{Exif, EXIF_IFD_TAG, EXIF_IMAGE_TAGS, GPS_IFD_TAG};
+use crate::ifd::{read_header, read_ifd, read_next_ifd, write_ifd};
+use crate::image::{Image, ImageDesc, Layout};
+use crate::math::Rect;
+use crate::metadata::{Metadata, Resolution, ResolutionUnit, TagValue};
+use crate::save_options::{TiffCompression, TiffOptions, TiffPredictor};
+
+/// `InterColorProfile` tag holding an embedded ICC profile.
+const ICC_PROFILE_TAG: Tag = Tag::Unknown(34675);
+/// Compression method value of Zstandard, not known by the tiff crate.
+const ZSTD_COMPRESSION: u16 = 50000;
+/// `NewSubfileType` bit of the reduced-resolution versions of another page.
+const REDUCED_RESOLUTION: u32 = 1;
+
+/// Tags describing the image data or pointing into the file, and the ones
+/// read into the fields of [`Metadata`].
+// @formatter:off
+const STANDARD_TAGS: [u16; 44] = [
+    254, 255, 256, 257, 258, 259, 262, 263, 266, 270, 273, 277, 278, 279, 282, 283, 284, 288, 289,
+    290, 291, 296, 305, 306, 315, 317, 320, 322, 323, 324, 325, 330, 338, 339, 340, 341, 347, 513,
+    514, 530, 532, 34665, 34675, 34853,
+];
+// @formatter:on
+
+/// Opaque bytes written with the `UNDEFINED` field type.
+struct Undefined<'a>(&'a [u8]);
+
+/// Numerator and denominator pairs written with the `RATIONAL` field type.
+struct Rationals<'a>(&'a [[u32; 2]]);
+
+impl TiffValue for Rationals<'_> {
+    const BYTE_LEN: u8 = 8;
+    const FIELD_TYPE: Type = Type::RATIONAL;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(bytemuck::cast_slice(self.0))
+    }
+}
+
+/// Numerator and denominator pairs written with the `SRATIONAL` field type.
+struct SignedRationals<'a>(&'a [[i32; 2]]);
+
+impl TiffValue for SignedRationals<'_> {
+    const BYTE_LEN: u8 = 8;
+    const FIELD_TYPE: Type = Type::SRATIONAL;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(bytemuck::cast_slice(self.0))
+    }
+}
+
+impl TiffValue for Undefined<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.0)
+    }
+}
 
 pub struct GrayAlphaI8;
 
@@ -172,7 +252,515 @@ impl ColorType for RGBAI64 {
     const SAMPLE_FORMAT: &'static [SampleFormat] = &[SampleFormat::Int; 4];
 }
 
-pub(crate) fn save_tiff<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+/// Opens a TIFF once for the decoder and for the raw reads of tags and chunks,
+/// both sharing the file handle.
+pub(crate) fn open_tiff<P: AsRef<Path>>(filename: P) -> anyhow::Result<(Decoder<File>, File)> {
+    let file = File::open(filename)?;
+    let decoder = Decoder::new(file.try_clone()?)?;
+
+    Ok((decoder, file))
+}
+
+/// Loads the current page, `page` being its index in the file.
+pub(crate) fn load_tiff<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    page: usize,
+) -> anyhow::Result<Image> {
+    let (w, h) = decoder.dimensions()?;
+
+    load_tiff_region(decoder, reader, page, Rect::new(0, 0, w, h))
+}
+
+/// Loads the smallest reduced-resolution top-level page of a pyramid that is at least `scale`
+/// times the width of the first page, or the first page when none is.
+pub(crate) fn load_tiff_level<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    scale: f64,
+) -> anyhow::Result<Image> {
+    let target_width = decoder.dimensions()?.0 as f64 * scale;
+
+    let mut best = (0, decoder.dimensions()?.0);
+    let mut index = 0;
+    while decoder.more_images() {
+        decoder.next_image()?;
+        index += 1;
+
+        let subfile_type = decoder
+            .find_tag_unsigned::<u32>(Tag::NewSubfileType)?
+            .unwrap_or(0);
+        let width = decoder.dimensions()?.0;
+        if subfile_type & REDUCED_RESOLUTION != 0 && width as f64 >= target_width && width < best.1
+        {
+            best = (index, width);
+        }
+    }
+    decoder.seek_to_image(best.0)?;
+
+    load_tiff(decoder, reader, best.0)
+}
+
+/// Loads a region of the current page, only decoding the strips or tiles it overlaps.
+pub(crate) fn load_tiff_region<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    page: usize,
+    rect: Rect,
+) -> anyhow::Result<Image> {
+    let (w, h) = decoder.dimensions()?;
+    let right = rect.x.checked_add(rect.width);
+    let bottom = rect.y.checked_add(rect.height);
+    if rect.width == 0
+        || rect.height == 0
+        || right.is_none_or(|right| right > w)
+        || bottom.is_none_or(|bottom| bottom > h)
+    {
+        return Err(anyhow::anyhow!(
+            "Region {:?} is empty or outside of the {}x{} image",
+            rect,
+            w,
+            h
+        ));
+    }
+
+    let icc_profile = decoder
+        .find_tag(ICC_PROFILE_TAG)?
+        .map(|value| value.into_u8_vec())
+        .transpose()?;
+
+    let metadata = read_metadata(decoder, reader, page)?;
+
+    let mut image = load_tiff_pixels(decoder, reader, rect)?;
+    image.icc_profile = icc_profile;
+    image.metadata = metadata;
+
+    Ok(image)
+}
+
+fn load_tiff_pixels<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    rect: Rect,
+) -> anyhow::Result<Image> {
+    let (channel_bits, channel_count) = match decoder.colortype()? {
+        // @formatter:off
+        tiff::ColorType::Gray(b) => (b, ChannelCount::Gray),
+        tiff::ColorType::GrayA(b) => (b, ChannelCount::GrayAlpha),
+        tiff::ColorType::RGB(b) => (b, ChannelCount::Rgb),
+        tiff::ColorType::RGBA(b) => (b, ChannelCount::Rgba),
+        color_type => return Err(anyhow::anyhow!("Unsupported color type: {:?}", color_type)),
+        // @formatter:on
+    };
+    let channel_size = ChannelSize::from_bit_count(channel_bits as u32);
+
+    let (w, h) = decoder.dimensions()?;
+
+    let planar_config = decoder
+        .find_tag_unsigned::<u16>(Tag::PlanarConfiguration)?
+        .and_then(PlanarConfiguration::from_u16)
+        .unwrap_or(PlanarConfiguration::Chunky);
+    let zstd = decoder.find_tag_unsigned::<u16>(Tag::Compression)? == Some(ZSTD_COMPRESSION);
+
+    if planar_config == PlanarConfiguration::Chunky && !zstd && rect == Rect::new(0, 0, w, h) {
+        let (bytes, channel_type) = decoding_result_to_bytes(decoder.read_image()?);
+        let color_format = ColorFormat::from((channel_count, channel_size, channel_type));
+
+        return Image::new_with_data(ImageDesc::new(w, h, color_format), bytes);
+    }
+
+    // planar, ZSTD and partial images are read chunk by chunk, as the decoder
+    // only reads the whole first plane and does not support ZSTD
+    let (layout, samples) = match planar_config {
+        PlanarConfiguration::Planar => (Layout::Planar, 1),
+        _ => (Layout::Interleaved, channel_count.channel_count()),
+    };
+    let chunk_count = match decoder.get_chunk_type() {
+        ChunkType::Strip => decoder.strip_count()?,
+        ChunkType::Tile => decoder.tile_count()?,
+    };
+    let (chunk_w, chunk_h) = decoder.chunk_dimensions();
+    let chunks_across = w.div_ceil(chunk_w);
+
+    let desc = ImageDesc::new(
+        rect.width,
+        rect.height,
+        ColorFormat::from((channel_count, channel_size, ChannelType::UInt)),
+    )
+    .with_layout(layout);
+    let chunks_per_plane = chunk_count / desc.plane_count();
+    let pixel_size = samples * channel_size.byte_count();
+    let mut bytes = vec![0u8; desc.size_in_bytes() as usize];
+    let mut channel_type = ChannelType::UInt;
+
+    for plane in 0..desc.plane_count() {
+        for chunk_y in rect.y / chunk_h..rect.bottom().div_ceil(chunk_h) {
+            for chunk_x in rect.x / chunk_w..rect.right().div_ceil(chunk_w) {
+                let index = chunk_y * chunks_across + chunk_x;
+                let chunk = plane * chunks_per_plane + index;
+
+                // the decoder only computes the data dimensions of the chunks of the first plane
+                let (data_w, data_h) = decoder.chunk_data_dimensions(index);
+                let (data, data_channel_type) = if zstd {
+                    read_zstd_chunk(
+                        decoder,
+                        reader,
+                        chunk,
+                        (data_w, data_h),
+                        samples as usize,
+                        channel_size,
+                    )?
+                } else {
+                    decoding_result_to_bytes(decoder.read_chunk(chunk)?)
+                };
+                channel_type = data_channel_type;
+
+                // intersection of the chunk with the region
+                let (chunk_left, chunk_top) = (chunk_x * chunk_w, chunk_y * chunk_h);
+                let left = chunk_left.max(rect.x);
+                let right = (chunk_left + data_w).min(rect.right());
+                let top = chunk_top.max(rect.y);
+                let bottom = (chunk_top + data_h).min(rect.bottom());
+
+                let data_row_size = (data_w * pixel_size) as usize;
+                let len = ((right - left) * pixel_size) as usize;
+                for y in top..bottom {
+                    let offset = (plane * desc.plane_size_in_bytes()
+                        + (y - rect.y) * desc.stride()
+                        + (left - rect.x) * pixel_size) as usize;
+                    let data_offset = (y - chunk_top) as usize * data_row_size
+                        + ((left - chunk_left) * pixel_size) as usize;
+
+                    bytes[offset..offset + len]
+                        .copy_from_slice(&data[data_offset..data_offset + len]);
+                }
+            }
+        }
+    }
+
+    let desc = ImageDesc::new(
+        rect.width,
+        rect.height,
+        ColorFormat::from((channel_count, channel_size, channel_type)),
+    )
+    .with_layout(layout);
+
+    Image::new_with_data(desc, bytes)
+}
+
+fn read_metadata<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    page: usize,
+) -> anyhow::Result<Metadata> {
+    let mut ascii = |tag| match decoder.find_tag(tag) {
+        Ok(Some(Value::Ascii(text))) => Ok(Some(text)),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let software = ascii(Tag::Software)?;
+    let date_time = ascii(Tag::DateTime)?;
+    let image_description = ascii(Tag::ImageDescription)?;
+    let artist = ascii(Tag::Artist)?;
+
+    let mut rational = |tag| match decoder.find_tag(tag) {
+        Ok(Some(Value::Rational(n, d))) if d != 0 => Ok(Some(n as f64 / d as f64)),
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let x_resolution = rational(Tag::XResolution)?;
+    let y_resolution = rational(Tag::YResolution)?;
+    let unit = match decoder.find_tag_unsigned::<u16>(Tag::ResolutionUnit)? {
+        // @formatter:off
+        Some(1) => ResolutionUnit::None,
+        Some(3) => ResolutionUnit::Centimeter,
+        _ => ResolutionUnit::Inch,
+        // @formatter:on
+    };
+    let resolution = x_resolution.map(|x| Resolution::new(x, y_resolution.unwrap_or(x), unit));
+    let (custom_tags, exif) = read_ifd_tags(reader, page)?;
+
+    Ok(Metadata {
+        resolution,
+        software,
+        date_time,
+        image_description,
+        artist,
+        exif,
+        custom_tags,
+        ..Metadata::default()
+    })
+}
+
+/// Reads the tags of a page that are not interpreted by the decoder, the custom tags
+/// and the EXIF metadata. The decoder does not list the tags of a directory,
+/// so the directory is parsed here.
+fn read_ifd_tags<R: Read + Seek>(
+    reader: &mut R,
+    page: usize,
+) -> anyhow::Result<(BTreeMap<u16, TagValue>, Option<Exif>)> {
+    let header = read_header(reader)?;
+
+    let mut offset = header.first_ifd;
+    for _ in 0..page {
+        offset = read_next_ifd(reader, &header, offset)?;
+    }
+    let mut tags = read_ifd(reader, &header, offset)?;
+
+    let mut exif = Exif::default();
+    for tag in EXIF_IMAGE_TAGS {
+        if let Some(value) = tags.remove(&tag) {
+            exif.image_tags.insert(tag, value);
+        }
+    }
+    for (tag, sub_tags) in [
+        (EXIF_IFD_TAG, &mut exif.exif_tags),
+        (GPS_IFD_TAG, &mut exif.gps_tags),
+    ] {
+        let sub_offset = match tags.get(&tag) {
+            Some(TagValue::Long(offsets)) => offsets.first().map(|&offset| offset as u64),
+            Some(TagValue::Long8(offsets)) => offsets.first().copied(),
+            _ => None,
+        };
+        if let Some(sub_offset) = sub_offset {
+            *sub_tags = read_ifd(reader, &header, sub_offset)?;
+        }
+    }
+    let exif = (exif != Exif::default()).then_some(exif);
+
+    tags.retain(|tag, _| !STANDARD_TAGS.contains(tag));
+
+    Ok((tags, exif))
+}
+
+/// Reads and decodes a ZSTD compressed chunk into native endian samples.
+fn read_zstd_chunk<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    reader: &mut R,
+    chunk: u32,
+    (data_w, data_h): (u32, u32),
+    samples: usize,
+    channel_size: ChannelSize,
+) -> anyhow::Result<(Vec<u8>, ChannelType)> {
+    // chunks are stored with the full tile width
+    let (offsets_tag, byte_counts_tag, stored_width) = match decoder.get_chunk_type() {
+        ChunkType::Strip => (
+            Tag::StripOffsets,
+            Tag::StripByteCounts,
+            decoder.dimensions()?.0,
+        ),
+        ChunkType::Tile => (
+            Tag::TileOffsets,
+            Tag::TileByteCounts,
+            decoder.chunk_dimensions().0,
+        ),
+    };
+    let offset = decoder
+        .get_tag_u64_vec(offsets_tag)?
+        .get(chunk as usize)
+        .copied();
+    let byte_count = decoder
+        .get_tag_u64_vec(byte_counts_tag)?
+        .get(chunk as usize)
+        .copied();
+    let (Some(offset), Some(byte_count)) = (offset, byte_count) else {
+        return Err(anyhow::anyhow!("Missing TIFF chunk {}", chunk));
+    };
+
+    let channel_type = match decoder
+        .find_tag_unsigned_vec::<u16>(Tag::SampleFormat)?
+        .and_then(|formats| formats.first().copied())
+        .map(SampleFormat::from_u16_exhaustive)
+    {
+        Some(SampleFormat::Int) => ChannelType::Int,
+        Some(SampleFormat::IEEEFP) => ChannelType::Float,
+        _ => ChannelType::UInt,
+    };
+    let predictor = decoder
+        .find_tag_unsigned::<u16>(Tag::Predictor)?
+        .and_then(Predictor::from_u16)
+        .unwrap_or(Predictor::None);
+
+    let mut byte_order = [0u8; 2];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut byte_order)?;
+    let swap = (&byte_order == b"II") != cfg!(target_endian = "little");
+
+    let file_length = reader.seek(SeekFrom::End(0))?;
+    if offset
+        .checked_add(byte_count)
+        .is_none_or(|end| end > file_length)
+    {
+        return Err(anyhow::anyhow!(
+            "TIFF chunk {} lies outside of the file",
+            chunk
+        ));
+    }
+
+    let mut compressed = vec![0u8; byte_count as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut compressed)?;
+
+    let sample_size = channel_size.byte_count() as usize;
+    let row_size = stored_width as usize * samples * sample_size;
+    let data_row_size = data_w as usize * samples * sample_size;
+
+    // the last row only needs the data width, the rest of the chunk is never read
+    let mut data = Vec::new();
+    ruzstd::decoding::StreamingDecoder::new(&compressed[..])
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .take((row_size * data_h as usize) as u64)
+        .read_to_end(&mut data)?;
+    if data.len() < row_size * (data_h as usize).saturating_sub(1) + data_row_size {
+        return Err(anyhow::anyhow!("TIFF chunk {} is too short", chunk));
+    }
+
+    let mut bytes = Vec::with_capacity(data_row_size * data_h as usize);
+    for row in data.chunks_mut(row_size).take(data_h as usize) {
+        unpredict(row, samples, sample_size, predictor, swap);
+        bytes.extend_from_slice(&row[..data_row_size]);
+    }
+
+    Ok((bytes, channel_type))
+}
+
+/// Reverts the predictor of a decompressed row, converting its samples to native endian.
+fn unpredict(row: &mut [u8], samples: usize, sample_size: usize, predictor: Predictor, swap: bool) {
+    if predictor == Predictor::FloatingPoint {
+        for i in samples..row.len() {
+            row[i] = row[i].wrapping_add(row[i - samples]);
+        }
+
+        // the bytes are split into big endian planes
+        let count = row.len() / sample_size;
+        let shuffled = row.to_vec();
+        for i in 0..count {
+            for b in 0..sample_size {
+                let index = if cfg!(target_endian = "little") {
+                    i * sample_size + sample_size - 1 - b
+                } else {
+                    i * sample_size + b
+                };
+                row[index] = shuffled[b * count + i];
+            }
+        }
+
+        return;
+    }
+
+    if swap {
+        for sample in row.chunks_mut(sample_size) {
+            sample.reverse();
+        }
+    }
+    if predictor == Predictor::Horizontal {
+        match sample_size {
+            // @formatter:off
+            1 => horizontal_sum::<u8>(row, samples),
+            2 => horizontal_sum::<u16>(row, samples),
+            4 => horizontal_sum::<u32>(row, samples),
+            _ => horizontal_sum::<u64>(row, samples),
+            // @formatter:on
+        }
+    }
+}
+
+fn horizontal_sum<T: Pod + WrappingAdd>(row: &mut [u8], samples: usize) {
+    let mut values: Vec<T> = bytemuck::pod_collect_to_vec(row);
+    for i in samples..values.len() {
+        values[i] = values[i].wrapping_add(&values[i - samples]);
+    }
+
+    row.copy_from_slice(bytemuck::cast_slice(&values));
+}
+
+fn decoding_result_to_bytes(result: DecodingResult) -> (Vec<u8>, ChannelType) {
+    match result {
+        // @formatter:off
+        DecodingResult::U8(buf) => (buf, ChannelType::UInt),
+        DecodingResult::I8(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Int),
+        DecodingResult::U16(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::UInt),
+        DecodingResult::I16(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Int),
+        DecodingResult::U32(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::UInt),
+        DecodingResult::I32(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Int),
+        DecodingResult::U64(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::UInt),
+        DecodingResult::I64(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Int),
+        DecodingResult::F32(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Float),
+        DecodingResult::F64(buf) => (bytemuck::cast_slice(&buf).to_vec(), ChannelType::Float),
+        // @formatter:on
+    }
+}
+
+pub(crate) fn save_tiff<P: AsRef<Path>>(
+    image: &Image,
+    filename: P,
+    options: &TiffOptions,
+) -> anyhow::Result<()> {
+    save_tiff_pages(std::slice::from_ref(image), filename, options)
+}
+
+/// Saves the images as the pages of a single TIFF, each one keeping its own color format.
+pub(crate) fn save_tiff_pages<P: AsRef<Path>>(
+    images: &[Image],
+    filename: P,
+    options: &TiffOptions,
+) -> anyhow::Result<()> {
+    save_tiff_file(images, filename, options, false)
+}
+
+/// Saves the levels of a pyramid as a tiled TIFF, the levels after the first one
+/// being marked as reduced-resolution pages.
+pub(crate) fn save_tiff_pyramid<P: AsRef<Path>>(
+    levels: &[Image],
+    filename: P,
+    options: &TiffOptions,
+) -> anyhow::Result<()> {
+    let options = match options.tile_size {
+        Some(_) => *options,
+        None => options.with_tiles(256, 256),
+    };
+
+    save_tiff_file(levels, filename, &options, true)
+}
+
+fn save_tiff_file<P: AsRef<Path>>(
+    images: &[Image],
+    filename: P,
+    options: &TiffOptions,
+    pyramid: bool,
+) -> anyhow::Result<()> {
+    if images.is_empty() {
+        return Err(anyhow::anyhow!("A TIFF requires at least one page"));
+    }
+
+    let mut file = File::create(filename)?;
+    if options.big_tiff {
+        write_tiff_pages(TiffEncoder::new_big(&mut file)?, images, options, pyramid)
+    } else {
+        write_tiff_pages(TiffEncoder::new(&mut file)?, images, options, pyramid)
+    }
+}
+
+fn write_tiff_pages<W: Write + Seek, K: TiffKind>(
+    mut tiff: TiffEncoder<W, K>,
+    images: &[Image],
+    options: &TiffOptions,
+    pyramid: bool,
+) -> anyhow::Result<()> {
+    for (index, image) in images.iter().enumerate() {
+        write_tiff_page(&mut tiff, image, options, pyramid && index > 0)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the image as a new directory of `tiff`.
+pub(crate) fn write_tiff_page<W: Write + Seek, K: TiffKind>(
+    tiff: &mut TiffEncoder<W, K>,
+    image: &Image,
+    options: &TiffOptions,
+    reduced_resolution: bool,
+) -> anyhow::Result<()> {
     match (
         image.desc.color_format().channel_count,
         image.desc.color_format().channel_size,
@@ -180,135 +768,145 @@ pub(crate) fn save_tiff<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::R
     ) {
         // @formatter:off
         (ChannelCount::Gray, ChannelSize::_8bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayI8, P>(image, filename)?
+            write_tiff_directory::<GrayI8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_16bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayI16, P>(image, filename)?
+            write_tiff_directory::<GrayI16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_32bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayI32, P>(image, filename)?
+            write_tiff_directory::<GrayI32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_64bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayI64, P>(image, filename)?
+            write_tiff_directory::<GrayI64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Gray, ChannelSize::_8bit, ChannelType::UInt) => {
-            save_tiff_internal::<Gray8, P>(image, filename)?
+            write_tiff_directory::<Gray8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_16bit, ChannelType::UInt) => {
-            save_tiff_internal::<Gray16, P>(image, filename)?
+            write_tiff_directory::<Gray16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_32bit, ChannelType::UInt) => {
-            save_tiff_internal::<Gray32, P>(image, filename)?
+            write_tiff_directory::<Gray32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_64bit, ChannelType::UInt) => {
-            save_tiff_internal::<Gray64, P>(image, filename)?
+            write_tiff_directory::<Gray64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Gray, ChannelSize::_32bit, ChannelType::Float) => {
-            save_tiff_internal::<Gray32Float, P>(image, filename)?
+            write_tiff_directory::<Gray32Float, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Gray, ChannelSize::_64bit, ChannelType::Float) => {
-            save_tiff_internal::<Gray64Float, P>(image, filename)?
+            write_tiff_directory::<Gray64Float, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::GrayAlpha, ChannelSize::_8bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayAlphaI8, P>(image, filename)?
+            write_tiff_directory::<GrayAlphaI8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_16bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayAlphaI16, P>(image, filename)?
+            write_tiff_directory::<GrayAlphaI16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_32bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayAlphaI32, P>(image, filename)?
+            write_tiff_directory::<GrayAlphaI32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_64bit, ChannelType::Int) => {
-            save_tiff_internal::<GrayAlphaI64, P>(image, filename)?
+            write_tiff_directory::<GrayAlphaI64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::GrayAlpha, ChannelSize::_8bit, ChannelType::UInt) => {
-            save_tiff_internal::<GrayAlpha8, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_16bit, ChannelType::UInt) => {
-            save_tiff_internal::<GrayAlpha16, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_32bit, ChannelType::UInt) => {
-            save_tiff_internal::<GrayAlpha32, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_64bit, ChannelType::UInt) => {
-            save_tiff_internal::<GrayAlpha64, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::GrayAlpha, ChannelSize::_32bit, ChannelType::Float) => {
-            save_tiff_internal::<GrayAlpha32Float, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha32Float, W, K>(
+                tiff,
+                image,
+                options,
+                reduced_resolution,
+            )?
         }
         (ChannelCount::GrayAlpha, ChannelSize::_64bit, ChannelType::Float) => {
-            save_tiff_internal::<GrayAlpha64Float, P>(image, filename)?
+            write_tiff_directory::<GrayAlpha64Float, W, K>(
+                tiff,
+                image,
+                options,
+                reduced_resolution,
+            )?
         }
 
         (ChannelCount::Rgb, ChannelSize::_8bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBI8, P>(image, filename)?
+            write_tiff_directory::<RGBI8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_16bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBI16, P>(image, filename)?
+            write_tiff_directory::<RGBI16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_32bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBI32, P>(image, filename)?
+            write_tiff_directory::<RGBI32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_64bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBI64, P>(image, filename)?
+            write_tiff_directory::<RGBI64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Rgb, ChannelSize::_8bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGB8, P>(image, filename)?
+            write_tiff_directory::<RGB8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_16bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGB16, P>(image, filename)?
+            write_tiff_directory::<RGB16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_32bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGB32, P>(image, filename)?
+            write_tiff_directory::<RGB32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_64bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGB64, P>(image, filename)?
+            write_tiff_directory::<RGB64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Rgb, ChannelSize::_32bit, ChannelType::Float) => {
-            save_tiff_internal::<RGB32Float, P>(image, filename)?
+            write_tiff_directory::<RGB32Float, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgb, ChannelSize::_64bit, ChannelType::Float) => {
-            save_tiff_internal::<RGB64Float, P>(image, filename)?
+            write_tiff_directory::<RGB64Float, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Rgba, ChannelSize::_8bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBAI8, P>(image, filename)?
+            write_tiff_directory::<RGBAI8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_16bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBAI16, P>(image, filename)?
+            write_tiff_directory::<RGBAI16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_32bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBAI32, P>(image, filename)?
+            write_tiff_directory::<RGBAI32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_64bit, ChannelType::Int) => {
-            save_tiff_internal::<RGBAI64, P>(image, filename)?
+            write_tiff_directory::<RGBAI64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Rgba, ChannelSize::_8bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGBA8, P>(image, filename)?
+            write_tiff_directory::<RGBA8, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_16bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGBA16, P>(image, filename)?
+            write_tiff_directory::<RGBA16, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_32bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGBA32, P>(image, filename)?
+            write_tiff_directory::<RGBA32, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_64bit, ChannelType::UInt) => {
-            save_tiff_internal::<RGBA64, P>(image, filename)?
+            write_tiff_directory::<RGBA64, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         (ChannelCount::Rgba, ChannelSize::_32bit, ChannelType::Float) => {
-            save_tiff_internal::<RGBA32Float, P>(image, filename)?
+            write_tiff_directory::<RGBA32Float, W, K>(tiff, image, options, reduced_resolution)?
         }
         (ChannelCount::Rgba, ChannelSize::_64bit, ChannelType::Float) => {
-            save_tiff_internal::<RGBA64Float, P>(image, filename)?
+            write_tiff_directory::<RGBA64Float, W, K>(tiff, image, options, reduced_resolution)?
         }
 
         // @formatter:on
@@ -325,38 +923,352 @@ pub(crate) fn save_tiff<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::R
     Ok(())
 }
 
-fn save_tiff_internal<ColorType, P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()>
+fn write_tiff_directory<ColorType, W: Write + Seek, K: TiffKind>(
+    tiff: &mut TiffEncoder<W, K>,
+    image: &Image,
+    options: &TiffOptions,
+    reduced_resolution: bool,
+) -> anyhow::Result<()>
 where
     ColorType: colortype::ColorType,
-    [ColorType::Inner]: TiffValue,
 {
-    let buf: &[ColorType::Inner] =
-        cast_slice(&image.bytes).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let desc = &image.desc;
+    if desc.width() == 0 || desc.height() == 0 {
+        return Err(anyhow::anyhow!(
+            "Cannot save an empty {}x{} image as TIFF",
+            desc.width(),
+            desc.height()
+        ));
+    }
+    // sizes are computed in usize, BigTIFF images can exceed 4 GB
+    let sample_size = desc.color_format().channel_size.byte_count() as usize;
+    let stride = desc.stride() as usize;
+    let plane_size = desc.height() as usize * stride;
+    let size_in_bytes = plane_size * desc.plane_count() as usize;
+    if image.bytes.len() < size_in_bytes {
+        return Err(anyhow::anyhow!(
+            "Image data is too short: {} bytes, expected {}",
+            image.bytes.len(),
+            size_in_bytes
+        ));
+    }
+    if !image.bytes.len().is_multiple_of(sample_size) {
+        return Err(anyhow::anyhow!(
+            "Image data of {} bytes is not made of {} byte samples",
+            image.bytes.len(),
+            sample_size
+        ));
+    }
 
-    let mut file = File::create(filename)?;
-    let mut tiff = TiffEncoder::new(&mut file)?;
-    let img = tiff.new_image::<ColorType>(image.desc.width(), image.desc.height())?;
+    let channel_type = desc.color_format().channel_type;
+    match (options.predictor, channel_type) {
+        (TiffPredictor::Horizontal, ChannelType::Float) => {
+            return Err(anyhow::anyhow!(
+                "Horizontal predictor requires integer samples"
+            ))
+        }
+        (TiffPredictor::FloatingPoint, ChannelType::Int | ChannelType::UInt) => {
+            return Err(anyhow::anyhow!(
+                "Floating point predictor requires float samples"
+            ))
+        }
+        _ => {}
+    }
+
+    let (samples, planar_configuration) = match desc.layout() {
+        Layout::Interleaved => (
+            desc.color_format().channel_count.channel_count() as usize,
+            PlanarConfiguration::Chunky,
+        ),
+        Layout::Planar => (1, PlanarConfiguration::Planar),
+    };
+
+    let (chunk_w, chunk_h) = match options.tile_size {
+        Some((tile_w, tile_h)) => {
+            if tile_w == 0 || tile_h == 0 || tile_w % 16 != 0 || tile_h % 16 != 0 {
+                return Err(anyhow::anyhow!(
+                    "Tile size must be a non-zero multiple of 16, got {}x{}",
+                    tile_w,
+                    tile_h
+                ));
+            }
+            (tile_w, tile_h)
+        }
+        // same strip size limit as the tiff encoder uses
+        None => (
+            desc.width(),
+            1_000_000_usize.div_ceil(stride).min(desc.height() as usize) as u32,
+        ),
+    };
+    let pixel_size = samples * sample_size;
+    let row_size = chunk_w as usize * pixel_size;
+
+    let mut dir = tiff.new_directory()?;
+
+    let mut chunk_offsets: Vec<K::OffsetType> = Vec::new();
+    let mut chunk_byte_counts: Vec<K::OffsetType> = Vec::new();
+
+    for plane in 0..desc.plane_count() {
+        let plane_offset = plane as usize * plane_size;
+
+        for y in (0..desc.height()).step_by(chunk_h as usize) {
+            for x in (0..desc.width()).step_by(chunk_w as usize) {
+                let data_w = chunk_w.min(desc.width() - x);
+                let data_h = chunk_h.min(desc.height() - y);
+                // tiles are padded to their full size, the last strip is not
+                let rows = match options.tile_size {
+                    Some(_) => chunk_h,
+                    None => data_h,
+                };
+
+                let mut chunk = vec![0u8; row_size * rows as usize];
+                for row in 0..data_h {
+                    let start =
+                        plane_offset + (y + row) as usize * stride + x as usize * pixel_size;
+                    let len = data_w as usize * pixel_size;
+                    let chunk_start = row as usize * row_size;
 
-    img.write_data(buf)?;
+                    chunk[chunk_start..chunk_start + len]
+                        .copy_from_slice(&image.bytes[start..start + len]);
+                }
+                for row in chunk.chunks_mut(row_size) {
+                    predict(row, samples, sample_size, options.predictor);
+                }
+                let chunk = compress(chunk, row_size, options.compression)?;
+
+                let offset = dir.write_data(&chunk[..])?;
+                chunk_offsets.push(K::convert_offset(offset)?);
+                chunk_byte_counts.push(K::convert_offset(chunk.len() as u64)?);
+            }
+        }
+    }
+
+    let sample_format: Vec<u16> = ColorType::SAMPLE_FORMAT
+        .iter()
+        .map(|s| s.to_u16())
+        .collect();
+
+    // the tags describing the data are written after the custom ones, replacing them
+    write_metadata(&mut dir, &image.metadata)?;
+    if reduced_resolution {
+        dir.write_tag(Tag::NewSubfileType, REDUCED_RESOLUTION)?;
+    }
+    dir.write_tag(Tag::ImageWidth, desc.width())?;
+    dir.write_tag(Tag::ImageLength, desc.height())?;
+    dir.write_tag(Tag::Compression, compression_method(options.compression))?;
+    dir.write_tag(Tag::BitsPerSample, ColorType::BITS_PER_SAMPLE)?;
+    dir.write_tag(Tag::SampleFormat, &sample_format[..])?;
+    dir.write_tag(
+        Tag::PhotometricInterpretation,
+        ColorType::TIFF_VALUE.to_u16(),
+    )?;
+    dir.write_tag(
+        Tag::SamplesPerPixel,
+        ColorType::BITS_PER_SAMPLE.len() as u16,
+    )?;
+    dir.write_tag(Tag::PlanarConfiguration, planar_configuration.to_u16())?;
+    if options.tile_size.is_some() {
+        dir.write_tag(Tag::TileWidth, chunk_w)?;
+        dir.write_tag(Tag::TileLength, chunk_h)?;
+        dir.write_tag(Tag::TileOffsets, K::convert_slice(&chunk_offsets))?;
+        dir.write_tag(Tag::TileByteCounts, K::convert_slice(&chunk_byte_counts))?;
+    } else {
+        dir.write_tag(Tag::RowsPerStrip, chunk_h)?;
+        dir.write_tag(Tag::StripOffsets, K::convert_slice(&chunk_offsets))?;
+        dir.write_tag(Tag::StripByteCounts, K::convert_slice(&chunk_byte_counts))?;
+    }
+    if options.predictor != TiffPredictor::None {
+        dir.write_tag(Tag::Predictor, predictor_tag(options.predictor).to_u16())?;
+    }
+    if let Some(icc_profile) = &image.icc_profile {
+        dir.write_tag(ICC_PROFILE_TAG, Undefined(icc_profile))?;
+    }
+    dir.finish()?;
 
     Ok(())
 }
 
-fn cast_slice<A, B>(a: &[A]) -> Result<&[B], PodCastError>
-where
-    A: Pod + Copy,
-    [B]: TiffValue,
-{
-    if align_of::<B>() > align_of::<A>() && (a.as_ptr() as usize) % align_of::<B>() != 0 {
-        Err(PodCastError::TargetAlignmentGreaterAndInputNotAligned)
-    } else if size_of::<B>() == size_of::<A>() {
-        Ok(unsafe { core::slice::from_raw_parts(a.as_ptr() as *const B, a.len()) })
-    } else if size_of::<A>() == 0 || size_of::<B>() == 0 {
-        Err(PodCastError::SizeMismatch)
-    } else if core::mem::size_of_val(a) % size_of::<B>() == 0 {
-        let new_len = core::mem::size_of_val(a) / size_of::<B>();
-        Ok(unsafe { core::slice::from_raw_parts(a.as_ptr() as *const B, new_len) })
-    } else {
-        Err(PodCastError::OutputSliceWouldHaveSlop)
+fn write_metadata<W: Write + Seek, K: TiffKind>(
+    dir: &mut DirectoryEncoder<'_, W, K>,
+    metadata: &Metadata,
+) -> anyhow::Result<()> {
+    if let Some(exif) = &metadata.exif {
+        for (&tag, value) in &exif.image_tags {
+            write_tag_value(dir, tag, value)?;
+        }
+        for (tag, sub_tags) in [
+            (EXIF_IFD_TAG, &exif.exif_tags),
+            (GPS_IFD_TAG, &exif.gps_tags),
+        ] {
+            if sub_tags.is_empty() {
+                continue;
+            }
+
+            // directories start on a word boundary
+            let mut offset = dir.write_data(&[][..] as &[u8])?;
+            if offset % 2 == 1 {
+                offset = dir.write_data(&[0u8][..])? + 1;
+            }
+            let big_tiff = <K::OffsetType as TiffValue>::BYTE_LEN == 8;
+            let ifd = write_ifd(sub_tags, offset, cfg!(target_endian = "big"), big_tiff);
+            dir.write_data(&ifd[..])?;
+            dir.write_tag(Tag::Unknown(tag), K::convert_offset(offset)?)?;
+        }
+    }
+    for (&tag, value) in &metadata.custom_tags {
+        write_tag_value(dir, tag, value)?;
+    }
+
+    if let Some(resolution) = &metadata.resolution {
+        // rationals with a fixed precision of a thousandth
+        let rational = |v: f64| Rational {
+            n: (v * 1000.0).round() as u32,
+            d: 1000,
+        };
+        let unit: u16 = match resolution.unit {
+            // @formatter:off
+            ResolutionUnit::None => 1,
+            ResolutionUnit::Inch => 2,
+            ResolutionUnit::Centimeter => 3,
+            // @formatter:on
+        };
+        dir.write_tag(Tag::XResolution, rational(resolution.x))?;
+        dir.write_tag(Tag::YResolution, rational(resolution.y))?;
+        dir.write_tag(Tag::ResolutionUnit, unit)?;
+    }
+    for (tag, text) in [
+        (Tag::Software, &metadata.software),
+        (Tag::DateTime, &metadata.date_time),
+        (Tag::ImageDescription, &metadata.image_description),
+        (Tag::Artist, &metadata.artist),
+    ] {
+        if let Some(text) = text {
+            dir.write_tag(tag, &text[..])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_tag_value<W: Write + Seek, K: TiffKind>(
+    dir: &mut DirectoryEncoder<'_, W, K>,
+    tag: u16,
+    value: &TagValue,
+) -> anyhow::Result<()> {
+    let tag = Tag::from_u16_exhaustive(tag);
+    match value {
+        // @formatter:off
+        TagValue::Byte(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Ascii(text) => dir.write_tag(tag, &text[..])?,
+        TagValue::Short(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Long(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Rational(values) => dir.write_tag(tag, Rationals(values))?,
+        TagValue::SignedByte(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Undefined(bytes) => dir.write_tag(tag, Undefined(bytes))?,
+        TagValue::SignedShort(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::SignedLong(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::SignedRational(values) => dir.write_tag(tag, SignedRationals(values))?,
+        TagValue::Float(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Double(values) => dir.write_tag(tag, &values[..])?,
+        TagValue::Long8(values) => dir.write_tag(tag, &values[..])?,
+        // @formatter:on
+    }
+
+    Ok(())
+}
+
+fn compression_method(compression: TiffCompression) -> u16 {
+    match compression {
+        // @formatter:off
+        TiffCompression::None => CompressionMethod::None.to_u16(),
+        TiffCompression::Lzw => CompressionMethod::LZW.to_u16(),
+        TiffCompression::Deflate => CompressionMethod::Deflate.to_u16(),
+        TiffCompression::PackBits => CompressionMethod::PackBits.to_u16(),
+        TiffCompression::Zstd => ZSTD_COMPRESSION,
+        // @formatter:on
     }
 }
+
+fn predictor_tag(predictor: TiffPredictor) -> Predictor {
+    match predictor {
+        TiffPredictor::None => Predictor::None,
+        TiffPredictor::Horizontal => Predictor::Horizontal,
+        TiffPredictor::FloatingPoint => Predictor::FloatingPoint,
+    }
+}
+
+/// Compresses a strip made of rows of `row_size` bytes.
+fn compress(
+    strip: Vec<u8>,
+    row_size: usize,
+    compression: TiffCompression,
+) -> anyhow::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+
+    match compression {
+        TiffCompression::None => return Ok(strip),
+        TiffCompression::Lzw => {
+            Lzw.write_to(&mut compressed, &strip)?;
+        }
+        TiffCompression::Deflate => {
+            Deflate::default().write_to(&mut compressed, &strip)?;
+        }
+        TiffCompression::PackBits => {
+            // runs may not cross rows
+            for row in strip.chunks(row_size) {
+                Packbits.write_to(&mut compressed, row)?;
+            }
+        }
+        TiffCompression::Zstd => {
+            compressed = ruzstd::encoding::compress_to_vec(&strip[..], CompressionLevel::Fastest);
+        }
+    }
+
+    Ok(compressed)
+}
+
+/// Applies the predictor to a row of native endian samples,
+/// `samples` being the distance between two samples of the same channel.
+fn predict(row: &mut [u8], samples: usize, sample_size: usize, predictor: TiffPredictor) {
+    match (predictor, sample_size) {
+        // @formatter:off
+        (TiffPredictor::None, _) => {}
+        (TiffPredictor::Horizontal, 1) => horizontal_difference::<u8>(row, samples),
+        (TiffPredictor::Horizontal, 2) => horizontal_difference::<u16>(row, samples),
+        (TiffPredictor::Horizontal, 4) => horizontal_difference::<u32>(row, samples),
+        (TiffPredictor::Horizontal, _) => horizontal_difference::<u64>(row, samples),
+        (TiffPredictor::FloatingPoint, _) => floating_point_difference(row, samples, sample_size),
+        // @formatter:on
+    }
+}
+
+fn horizontal_difference<T: Pod + WrappingSub>(row: &mut [u8], samples: usize) {
+    let mut values: Vec<T> = bytemuck::pod_collect_to_vec(row);
+    for i in (samples..values.len()).rev() {
+        values[i] = values[i].wrapping_sub(&values[i - samples]);
+    }
+
+    row.copy_from_slice(bytemuck::cast_slice(&values));
+}
+
+/// Splits the big endian bytes of the samples into planes, then differences them.
+fn floating_point_difference(row: &mut [u8], samples: usize, sample_size: usize) {
+    let count = row.len() / sample_size;
+
+    let mut shuffled = vec![0u8; row.len()];
+    for i in 0..count {
+        for b in 0..sample_size {
+            let byte = if cfg!(target_endian = "little") {
+                row[i * sample_size + sample_size - 1 - b]
+            } else {
+                row[i * sample_size + b]
+            };
+            shuffled[b * count + i] = byte;
+        }
+    }
+    for i in (samples..shuffled.len()).rev() {
+        shuffled[i] = shuffled[i].wrapping_sub(shuffled[i - samples]);
+    }
+
+    row.copy_from_slice(&shuffled);
+}
@@ -0,0 +1,163 @@
+use crate::color_format::*;
+use crate::color_space::{convert_pixels, mul, Mat3};
+use crate::image::Image;
+
+/// Curve compressing scene-referred linear values into the displayable `[0, 1]` range.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum ToneMapOperator {
+    /// `x / (1 + x)`, never reaches white.
+    #[default]
+    Reinhard,
+    /// Reinhard with the white point mapped to one.
+    ExtendedReinhard,
+    /// Narkowicz fit of the ACES filmic reference rendering.
+    AcesFilmic,
+    /// John Hable's Uncharted 2 filmic curve, normalized by the white point.
+    Hable,
+    /// Minimal AgX with its default contrast look.
+    AgX,
+}
+
+/// Tone mapping operator with its controls.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Exposure adjustment in stops, applied before the operator.
+    pub exposure: f64,
+    /// Linear value mapped to 1.0 by `ExtendedReinhard` and `Hable`.
+    pub white_point: f64,
+}
+
+// @formatter:off
+const AGX_INSET: Mat3 = [
+    [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+    [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+    [0.0423756549057051, 0.0784336, 0.879142973793104],
+];
+const AGX_OUTSET: Mat3 = [
+    [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+    [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+    [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+];
+// @formatter:on
+const AGX_MIN_EV: f64 = -12.47393;
+const AGX_MAX_EV: f64 = 4.026069;
+
+impl ToneMapping {
+    pub fn new(operator: ToneMapOperator) -> Self {
+        Self {
+            operator,
+            exposure: 0.0,
+            white_point: 11.2,
+        }
+    }
+    pub fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+
+        self
+    }
+    pub fn with_white_point(mut self, white_point: f64) -> Self {
+        self.white_point = white_point;
+
+        self
+    }
+
+    /// Maps a scene-referred linear color into a display-referred linear color in `[0, 1]`.
+    pub fn apply(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let scale = self.exposure.exp2();
+        let rgb = rgb.map(|v| (v * scale).max(0.0));
+        let white = self.white_point;
+
+        let mapped = match self.operator {
+            ToneMapOperator::Reinhard => rgb.map(|v| v / (1.0 + v)),
+            ToneMapOperator::ExtendedReinhard => {
+                rgb.map(|v| v * (1.0 + v / (white * white)) / (1.0 + v))
+            }
+            ToneMapOperator::AcesFilmic => {
+                rgb.map(|v| (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14))
+            }
+            ToneMapOperator::Hable => {
+                // exposure bias of the original presentation
+                rgb.map(|v| hable(v * 2.0) / hable(white * 2.0))
+            }
+            ToneMapOperator::AgX => {
+                let encoded = mul(&AGX_INSET, rgb).map(|v| {
+                    let ev = v.max(1e-10).log2().clamp(AGX_MIN_EV, AGX_MAX_EV);
+                    agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV))
+                });
+                // the AgX output is encoded with a 2.2 display gamma
+                mul(&AGX_OUTSET, encoded).map(|v| v.max(0.0).powf(2.2))
+            }
+        };
+
+        mapped.map(|v| v.clamp(0.0, 1.0))
+    }
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping::new(ToneMapOperator::default())
+    }
+}
+
+impl From<ToneMapOperator> for ToneMapping {
+    fn from(operator: ToneMapOperator) -> Self {
+        ToneMapping::new(operator)
+    }
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+/// Polynomial approximation of the default AgX contrast sigmoid.
+fn agx_contrast(x: f64) -> f64 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+        - 0.00232
+}
+
+/// Tone maps the RGB channels of a float image, decoding them with the transfer function
+/// of `from` and encoding the result with the one of `to`.
+pub(crate) fn tone_map(
+    from: &Image,
+    to: &mut Image,
+    tone_mapping: &ToneMapping,
+) -> anyhow::Result<()> {
+    assert_eq!(from.desc.width(), to.desc.width());
+    assert_eq!(from.desc.height(), to.desc.height());
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+
+    let from_transfer = from.desc.transfer_function();
+    let to_transfer = to.desc.transfer_function();
+
+    let convert = |rgb: [f64; 3]| {
+        tone_mapping
+            .apply(rgb.map(|v| from_transfer.decode(v)))
+            .map(|v| to_transfer.encode(v))
+    };
+
+    let color_format = from.desc.color_format();
+    match (color_format.channel_type, color_format.channel_size) {
+        // @formatter:off
+        (ChannelType::Float, ChannelSize::_32bit) => {
+            convert_pixels::<f32>(from, to, |v| v as f64, |v| v as f32, convert)
+        }
+        (ChannelType::Float, ChannelSize::_64bit) => {
+            convert_pixels::<f64>(from, to, |v| v, |v| v, convert)
+        }
+        // @formatter:on
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Tone mapping requires a float image, got {}",
+                color_format
+            ))
+        }
+    }
+
+    Ok(())
+}
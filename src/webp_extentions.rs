@@ -0,0 +1,86 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::time::Duration;
+
+use ::image::codecs::webp::WebPEncoder;
+use ::image::ExtendedColorType;
+
+use crate::animation::{Animation, Frame};
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc, Layout};
+
+/// Writes an 8 bit image as a lossless WebP.
+pub(crate) fn save_webp<P: AsRef<Path>>(image: &Image, filename: P) -> anyhow::Result<()> {
+    let color_format = image.desc.color_format();
+    if color_format.channel_size != ChannelSize::_8bit
+        || color_format.channel_type != ChannelType::UInt
+    {
+        return Err(anyhow::anyhow!(
+            "Unsupported WebP color format: {}",
+            color_format
+        ));
+    }
+    let color_type = match color_format.channel_count {
+        // @formatter:off
+        ChannelCount::Gray => ExtendedColorType::L8,
+        ChannelCount::GrayAlpha => ExtendedColorType::La8,
+        ChannelCount::Rgb => ExtendedColorType::Rgb8,
+        ChannelCount::Rgba => ExtendedColorType::Rgba8,
+        // @formatter:on
+    };
+
+    let image = image.clone().convert_layout(Layout::Interleaved)?;
+    WebPEncoder::new_lossless(BufWriter::new(File::create(filename)?)).encode(
+        &image.bytes[..image.desc.size_in_bytes() as usize],
+        image.desc.width(),
+        image.desc.height(),
+        color_type,
+    )?;
+
+    Ok(())
+}
+
+/// Reads the frames of an animated WebP, composited by the decoder, or a still WebP
+/// as a single frame.
+pub(crate) fn load_webp_animation<P: AsRef<Path>>(filename: P) -> anyhow::Result<Animation> {
+    let mut decoder = image_webp::WebPDecoder::new(BufReader::new(File::open(filename)?))?;
+    let (width, height) = decoder.dimensions();
+    let color_format = if decoder.has_alpha() {
+        ColorFormat::RGBA_U8
+    } else {
+        ColorFormat::RGB_U8
+    };
+    let mut bytes = vec![
+        0u8;
+        decoder
+            .output_buffer_size()
+            .ok_or(anyhow::anyhow!("WebP is too large"))?
+    ];
+
+    if !decoder.is_animated() {
+        decoder.read_image(&mut bytes)?;
+        let image = Image::new_with_data(ImageDesc::new(width, height, color_format), bytes)?
+            .convert(ColorFormat::RGBA_U8)?;
+
+        return Ok(Animation::new(vec![Frame::new(image, Duration::ZERO)], 1));
+    }
+
+    let mut frames = Vec::with_capacity(decoder.num_frames() as usize);
+    for _ in 0..decoder.num_frames() {
+        let milliseconds = decoder.read_frame(&mut bytes)?;
+        let image =
+            Image::new_with_data(ImageDesc::new(width, height, color_format), bytes.clone())?
+                .convert(ColorFormat::RGBA_U8)?;
+        frames.push(Frame::new(
+            image,
+            Duration::from_millis(milliseconds as u64),
+        ));
+    }
+    let loop_count = match decoder.loop_count() {
+        image_webp::LoopCount::Forever => 0,
+        image_webp::LoopCount::Times(plays) => plays.get() as u32,
+    };
+
+    Ok(Animation::new(frames, loop_count))
+}